@@ -0,0 +1,307 @@
+//! Unique assignments
+//!
+//! Day 16 matches ticket fields to columns and day 21 matches allergens to ingredients. Both give
+//! every key a value of its own from a set of candidates, and the puzzle only has an answer if
+//! exactly one such assignment exists. [`resolve`] settles keys with a single candidate first,
+//! removing that value from every other key, then matches whatever is left along augmenting paths.
+//! Keys and values are visited in order, so the outcome doesn't depend on hash order.
+//!
+//! ```
+//! use std::collections::{HashMap, HashSet};
+//! use advent_2020::assignment::{resolve, AssignmentError};
+//!
+//! let mut candidates: HashMap<&str, HashSet<u32>> = HashMap::new();
+//! candidates.insert("row", vec![0, 1, 2].into_iter().collect());
+//! candidates.insert("class", vec![1, 2].into_iter().collect());
+//! candidates.insert("seat", vec![2].into_iter().collect());
+//!
+//! let expected = vec![("row", 0), ("class", 1), ("seat", 2)].into_iter().collect();
+//! assert_eq!(resolve(candidates.clone()), Ok(expected));
+//!
+//! candidates.insert("seat", vec![1, 2].into_iter().collect());
+//! assert!(matches!(resolve(candidates.clone()), Err(AssignmentError::Ambiguous(_))));
+//!
+//! candidates.insert("row", vec![1, 2].into_iter().collect());
+//! assert!(matches!(resolve(candidates), Err(AssignmentError::Unsatisfiable(_))));
+//! ```
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+/// Reason there is no unique assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssignmentError<K: Eq + Hash, V> {
+    /// no assignment gives every key a value of its own. Lists keys left without a value, in order
+    Unsatisfiable(Vec<K>),
+    /// more than one assignment fits, shown by two that differ
+    Ambiguous(Vec<HashMap<K, V>>),
+}
+
+/// A step towards an assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deduction<K, V> {
+    /// the value is the only candidate the key has left
+    Forced(K, V),
+    /// the value is the key's only match that leaves every other key a value
+    Matched(K, V),
+}
+
+/// An assignment, with the deductions that lead to it in order
+pub type Explanation<K, V> = (HashMap<K, V>, Vec<Deduction<K, V>>);
+
+/// Return the only assignment giving each key one of its candidates, with no value used twice
+pub fn resolve<K, V>(
+    candidates: HashMap<K, HashSet<V>>,
+) -> Result<HashMap<K, V>, AssignmentError<K, V>>
+where
+    K: Ord + Hash + Clone,
+    V: Ord + Hash + Clone,
+{
+    explain(candidates).map(|(assignment, _)| assignment)
+}
+
+/// Resolve an assignment like [`resolve`], also returning the deductions that lead to it
+pub fn explain<K, V>(
+    candidates: HashMap<K, HashSet<V>>,
+) -> Result<Explanation<K, V>, AssignmentError<K, V>>
+where
+    K: Ord + Hash + Clone,
+    V: Ord + Hash + Clone,
+{
+    // work with indexes, so sets are cheap to copy and compare. Indexes follow the order of the
+    // keys and values
+    let mut candidates: Vec<(K, HashSet<V>)> = candidates.into_iter().collect();
+    candidates.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    let values: Vec<V> = candidates
+        .iter()
+        .flat_map(|(_, key_values)| key_values.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let value_ids: HashMap<&V, usize> = values
+        .iter()
+        .enumerate()
+        .map(|(id, value)| (value, id))
+        .collect();
+    let (keys, mut options): (Vec<K>, Vec<BTreeSet<usize>>) = candidates
+        .into_iter()
+        .map(|(key, key_values)| {
+            let ids = key_values.iter().map(|value| value_ids[value]).collect();
+            (key, ids)
+        })
+        .unzip();
+
+    let mut holders = vec![BTreeSet::new(); values.len()];
+    for (key, ids) in options.iter().enumerate() {
+        ids.iter().for_each(|id| {
+            holders[*id].insert(key);
+        });
+    }
+
+    // a key with a single candidate must take it, so no other key can
+    let mut assigned: Vec<Option<usize>> = vec![None; keys.len()];
+    let mut deductions = Vec::new();
+    let mut singles: Vec<usize> = (0..keys.len())
+        .filter(|key| options[*key].len() == 1)
+        .collect();
+    while let Some(key) = singles.pop() {
+        let value = match options[key].iter().next() {
+            Some(value) if assigned[key].is_none() => *value,
+            _ => continue,
+        };
+        assigned[key] = Some(value);
+        deductions.push(Deduction::Forced(keys[key].clone(), values[value].clone()));
+        let others = std::mem::take(&mut holders[value]);
+        for other in others.into_iter().filter(|other| *other != key) {
+            options[other].remove(&value);
+            if options[other].len() == 1 {
+                singles.push(other);
+            }
+        }
+    }
+
+    let empty: Vec<usize> = (0..keys.len())
+        .filter(|key| options[*key].is_empty())
+        .collect();
+    if !empty.is_empty() {
+        return Err(AssignmentError::Unsatisfiable(
+            empty.into_iter().map(|key| keys[key].clone()).collect(),
+        ));
+    }
+
+    // whatever propagation couldn't settle is left to matching
+    let open: Vec<usize> = (0..keys.len())
+        .filter(|key| assigned[*key].is_none())
+        .collect();
+    let mut matching = Matching::new(open.iter().map(|key| &options[*key]).collect());
+    let unmatched = matching.complete();
+    if !unmatched.is_empty() {
+        return Err(AssignmentError::Unsatisfiable(
+            unmatched
+                .into_iter()
+                .map(|idx| keys[open[idx]].clone())
+                .collect(),
+        ));
+    }
+
+    let assignment = |matching: &Matching| {
+        let mut full = assigned.clone();
+        for (idx, value) in matching.matched.iter().enumerate() {
+            full[open[idx]] = *value;
+        }
+
+        keys.iter()
+            .zip(full)
+            .filter_map(|(key, value)| value.map(|value| (key.clone(), values[value].clone())))
+            .collect()
+    };
+    match matching.alternative() {
+        Some(other) => Err(AssignmentError::Ambiguous(vec![
+            assignment(&matching),
+            assignment(&other),
+        ])),
+        None => {
+            deductions.extend(
+                open.iter()
+                    .zip(&matching.matched)
+                    .filter_map(|(key, value)| {
+                        value.map(|value| {
+                            Deduction::Matched(keys[*key].clone(), values[value].clone())
+                        })
+                    }),
+            );
+            Ok((assignment(&matching), deductions))
+        }
+    }
+}
+
+/// A matching of keys to candidate values, grown along augmenting paths
+#[derive(Debug, Clone)]
+struct Matching {
+    candidates: Vec<Vec<usize>>,
+    /// value matched to each key
+    matched: Vec<Option<usize>>,
+    /// key matched to each value
+    owners: HashMap<usize, usize>,
+}
+
+impl Matching {
+    fn new(candidates: Vec<&BTreeSet<usize>>) -> Self {
+        Self {
+            matched: vec![None; candidates.len()],
+            candidates: candidates
+                .into_iter()
+                .map(|values| values.iter().copied().collect())
+                .collect(),
+            owners: HashMap::new(),
+        }
+    }
+
+    /// try to match `key`, re-matching others along the way, without using `forbidden`
+    fn augment(
+        &mut self,
+        key: usize,
+        visited: &mut HashSet<usize>,
+        forbidden: Option<(usize, usize)>,
+    ) -> bool {
+        for idx in 0..self.candidates[key].len() {
+            let value = self.candidates[key][idx];
+            if forbidden == Some((key, value)) || !visited.insert(value) {
+                continue;
+            }
+
+            let free = match self.owners.get(&value) {
+                Some(owner) => self.augment(*owner, visited, forbidden),
+                None => true,
+            };
+            if free {
+                self.matched[key] = Some(value);
+                self.owners.insert(value, key);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// match as many keys as possible, returning those left unmatched
+    fn complete(&mut self) -> Vec<usize> {
+        (0..self.candidates.len())
+            .filter(|key| !self.augment(*key, &mut HashSet::new(), None))
+            .collect()
+    }
+
+    /// find a different complete matching, by re-matching some key without its value
+    fn alternative(&self) -> Option<Self> {
+        (0..self.candidates.len()).find_map(|key| {
+            let value = self.matched[key]?;
+            let mut other = self.clone();
+            other.matched[key] = None;
+            other.owners.remove(&value);
+
+            if other.augment(key, &mut HashSet::new(), Some((key, value))) {
+                Some(other)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidates(sets: &[&[usize]]) -> HashMap<usize, HashSet<usize>> {
+        sets.iter()
+            .enumerate()
+            .map(|(key, values)| (key, values.iter().copied().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn resolves() {
+        let msg = "should settle keys with a single candidate in turn";
+        let expected = vec![(0, 3), (1, 1), (2, 2), (3, 0)].into_iter().collect();
+        let actual = resolve(candidates(&[&[0, 1, 2, 3], &[0, 1], &[0, 1, 2], &[0]]));
+        assert_eq!(actual, Ok(expected), "{}", msg);
+
+        let msg = "should resolve hundreds of keys";
+        // key n may take any value up to n, so only the identity fits
+        let sets: Vec<Vec<usize>> = (0..500).map(|key| (0..=key).collect()).collect();
+        let sets: Vec<&[usize]> = sets.iter().map(|set| set.as_slice()).collect();
+        let actual = resolve(candidates(&sets)).unwrap();
+        assert!(actual.iter().all(|(key, value)| key == value), "{}", msg);
+    }
+
+    #[test]
+    fn explains() {
+        let msg = "should list deductions in the order they were made";
+        let (_, deductions) = explain(candidates(&[&[0, 1], &[1], &[1, 2]])).unwrap();
+        let expected = vec![
+            Deduction::Forced(1, 1),
+            Deduction::Forced(2, 2),
+            Deduction::Forced(0, 0),
+        ];
+        assert_eq!(deductions, expected, "{}", msg);
+    }
+
+    #[test]
+    fn rejects() {
+        let msg = "should report keys without a value of their own";
+        let expected = Err(AssignmentError::Unsatisfiable(vec![1]));
+        assert_eq!(resolve(candidates(&[&[0], &[]])), expected, "{}", msg);
+        let expected = Err(AssignmentError::Unsatisfiable(vec![2]));
+        let actual = resolve(candidates(&[&[0, 1], &[0, 1], &[0, 1], &[2]]));
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should show two assignments when several fit";
+        match resolve(candidates(&[&[0, 1], &[0, 1, 2], &[1, 2], &[3]])) {
+            Err(AssignmentError::Ambiguous(examples)) => {
+                assert_eq!(examples.len(), 2, "{}", msg);
+                assert_ne!(examples[0], examples[1], "{}", msg);
+                assert!(examples.iter().all(|example| example[&3] == 3), "{}", msg);
+            }
+            actual => panic!("{}, got {:?}", msg, actual),
+        }
+    }
+}
@@ -0,0 +1,580 @@
+//! Cellular automata
+//!
+//! Days 11, 17 and 24 each run a Game of Life style simulation: every generation, each cell is
+//! alive or dead depending on whether it was alive and how many of its neighbors were. An
+//! [`Automaton`] combines the three parts that vary between puzzles:
+//!
+//! - a [`Topology`], which decides which cells exist and which cells are neighbors
+//! - a [`Rule`], which decides the next state of a cell from its live neighbor count
+//! - a [`Backend`], which stores the live cells. [`Sparse`] works on infinite topologies,
+//!   [`Dense`] precomputes neighbors for [`Bounded`] ones.
+//!
+//! ```
+//! use advent_2020::automaton::{Automaton, Lattice, LifeRule, SparseAutomaton};
+//!
+//! // a blinker oscillates between horizontal and vertical
+//! let blinker = vec![[0, -1], [0, 0], [0, 1]];
+//! let mut life: SparseAutomaton<_, _> = Automaton::new(Lattice::<2>, LifeRule::conway(), blinker);
+//! life.run(2);
+//! assert_eq!(life.population(), 3);
+//! assert!(life.is_alive([0, 1]));
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+
+use crate::grid::{Grid, Position};
+
+/// The shape of the space cells live in
+pub trait Topology {
+    /// Coordinate of a single cell
+    type Cell: Copy + Eq + Hash;
+
+    /// Return the cells adjacent to `cell`. Adjacency must be symmetric
+    fn neighbors(&self, cell: Self::Cell) -> Vec<Self::Cell>;
+
+    /// true if `cell` exists in this topology
+    fn contains(&self, _cell: Self::Cell) -> bool {
+        true
+    }
+}
+
+/// A [`Topology`] with a finite number of cells
+pub trait Bounded: Topology {
+    /// Return every cell in the topology
+    fn cells(&self) -> Vec<Self::Cell>;
+}
+
+/// Decides the next state of a cell
+pub trait Rule {
+    /// Return true if a cell is alive in the next generation
+    fn next(&self, alive: bool, live_neighbors: usize) -> bool;
+}
+
+impl<F> Rule for F
+where
+    F: Fn(bool, usize) -> bool,
+{
+    fn next(&self, alive: bool, live_neighbors: usize) -> bool {
+        self(alive, live_neighbors)
+    }
+}
+
+/// A birth/survival rule: a dead cell comes alive when its live neighbor count is in `birth`,
+/// a live cell stays alive when its count is in `survival`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifeRule {
+    birth: RangeInclusive<usize>,
+    survival: RangeInclusive<usize>,
+}
+
+impl LifeRule {
+    /// Create a rule from birth and survival neighbor counts
+    pub fn new(birth: RangeInclusive<usize>, survival: RangeInclusive<usize>) -> Self {
+        Self { birth, survival }
+    }
+
+    /// Conway's Game of Life, B3/S23
+    pub fn conway() -> Self {
+        Self::new(3..=3, 2..=3)
+    }
+}
+
+impl Rule for LifeRule {
+    fn next(&self, alive: bool, live_neighbors: usize) -> bool {
+        if alive {
+            self.survival.contains(&live_neighbors)
+        } else {
+            self.birth.contains(&live_neighbors)
+        }
+    }
+}
+
+/// An infinite N dimensional lattice, where every cell touching a cell is its neighbor
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Lattice<const N: usize>;
+
+impl<const N: usize> Topology for Lattice<N> {
+    type Cell = [isize; N];
+
+    fn neighbors(&self, cell: Self::Cell) -> Vec<Self::Cell> {
+        // count through the 3^N offsets in base 3, each digit mapping to -1, 0 or 1
+        let count = 3usize.pow(N as u32);
+        (0..count)
+            .filter(|offset| *offset != count / 2)
+            .map(|mut offset| {
+                let mut neighbor = cell;
+                for coordinate in neighbor.iter_mut() {
+                    *coordinate += (offset % 3) as isize - 1;
+                    offset /= 3;
+                }
+                neighbor
+            })
+            .collect()
+    }
+}
+
+/// An infinite lattice of hexagons in axial `(q, r)` coordinates
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HexLattice;
+
+impl HexLattice {
+    /// Offsets to the 6 adjacent hexagons: w, nw, sw, e, ne, se
+    pub const DIRECTIONS: [(isize, isize); 6] =
+        [(-1, 0), (0, -1), (-1, 1), (1, 0), (1, -1), (0, 1)];
+}
+
+impl Topology for HexLattice {
+    type Cell = (isize, isize);
+
+    fn neighbors(&self, (q, r): Self::Cell) -> Vec<Self::Cell> {
+        Self::DIRECTIONS
+            .iter()
+            .map(|(d_q, d_r)| (q + d_q, r + d_r))
+            .collect()
+    }
+}
+
+/// A rectangular grid where some positions may be missing, with the 8 surrounding positions as
+/// neighbors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FiniteGrid {
+    cells: Grid<bool>,
+}
+
+impl FiniteGrid {
+    /// Create a grid where every position is a cell
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            cells: Grid::new(width, height, true),
+        }
+    }
+
+    /// Create a grid with the same shape as `grid`, keeping only positions matching `is_cell`
+    pub fn from_grid<T>(grid: &Grid<T>, is_cell: impl Fn(&T) -> bool) -> Self {
+        Self {
+            cells: grid.map(is_cell),
+        }
+    }
+}
+
+impl Topology for FiniteGrid {
+    type Cell = Position;
+
+    fn neighbors(&self, cell: Self::Cell) -> Vec<Self::Cell> {
+        self.cells
+            .neighbors_8(cell)
+            .filter(|neighbor| self.cells[*neighbor])
+            .collect()
+    }
+
+    fn contains(&self, cell: Self::Cell) -> bool {
+        self.cells.get(cell) == Some(&true)
+    }
+}
+
+impl Bounded for FiniteGrid {
+    fn cells(&self) -> Vec<Self::Cell> {
+        self.cells
+            .indexed_iter()
+            .filter(|(_, is_cell)| **is_cell)
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// A rectangular grid where the neighbors of a cell are the first cells visible in each of the 8
+/// directions, looking past positions that are not cells
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineOfSight {
+    neighbors: Grid<Option<Vec<Position>>>,
+}
+
+impl LineOfSight {
+    /// Create a grid with the same shape as `grid`, keeping only positions matching `is_cell`
+    pub fn from_grid<T>(grid: &Grid<T>, is_cell: impl Fn(&T) -> bool) -> Self {
+        let cells = grid.map(is_cell);
+        let mut neighbors = Grid::new(grid.width(), grid.height(), None);
+        for (position, is_cell) in cells.indexed_iter() {
+            if *is_cell {
+                let visible = cells
+                    .rays(position)
+                    .filter_map(|mut ray| ray.find(|neighbor| cells[*neighbor]))
+                    .collect();
+                neighbors[position] = Some(visible);
+            }
+        }
+
+        Self { neighbors }
+    }
+}
+
+impl Topology for LineOfSight {
+    type Cell = Position;
+
+    fn neighbors(&self, cell: Self::Cell) -> Vec<Self::Cell> {
+        self.neighbors
+            .get(cell)
+            .and_then(Option::clone)
+            .unwrap_or_default()
+    }
+
+    fn contains(&self, cell: Self::Cell) -> bool {
+        matches!(self.neighbors.get(cell), Some(Some(_)))
+    }
+}
+
+impl Bounded for LineOfSight {
+    fn cells(&self) -> Vec<Self::Cell> {
+        self.neighbors
+            .indexed_iter()
+            .filter(|(_, neighbors)| neighbors.is_some())
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// Storage for the live cells of an [`Automaton`]
+pub trait Backend<T: Topology> {
+    /// Create a backend with the provided live cells. Cells outside the topology are ignored
+    fn new(topology: &T, live: Vec<T::Cell>) -> Self;
+
+    /// Advance one generation, returning true if any cell changed
+    fn step<R: Rule>(&mut self, topology: &T, rule: &R) -> bool;
+
+    /// true if `cell` is alive
+    fn is_alive(&self, cell: T::Cell) -> bool;
+
+    /// Return the number of live cells
+    fn population(&self) -> usize;
+
+    /// Return the live cells, in no particular order
+    fn live_cells(&self) -> Vec<T::Cell>;
+}
+
+/// Stores only the live cells, so works on infinite topologies
+///
+/// Only live cells and their neighbors are considered each generation, so a rule that brings
+/// cells to life with no live neighbors needs the [`Dense`] backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sparse<C: Eq + Hash> {
+    live: HashSet<C>,
+}
+
+impl<T: Topology> Backend<T> for Sparse<T::Cell> {
+    fn new(topology: &T, live: Vec<T::Cell>) -> Self {
+        Self {
+            live: live
+                .into_iter()
+                .filter(|cell| topology.contains(*cell))
+                .collect(),
+        }
+    }
+
+    fn step<R: Rule>(&mut self, topology: &T, rule: &R) -> bool {
+        let mut counts: HashMap<T::Cell, usize> = HashMap::new();
+        for cell in &self.live {
+            for neighbor in topology.neighbors(*cell) {
+                *counts.entry(neighbor).or_insert(0) += 1;
+            }
+        }
+
+        // live cells with no live neighbors don't appear in the counts
+        let isolated = self
+            .live
+            .iter()
+            .filter(|cell| !counts.contains_key(cell))
+            .map(|cell| (*cell, 0));
+        let next: HashSet<T::Cell> = counts
+            .iter()
+            .map(|(cell, count)| (*cell, *count))
+            .chain(isolated)
+            .filter(|(cell, count)| rule.next(self.live.contains(cell), *count))
+            .map(|(cell, _)| cell)
+            .collect();
+
+        let changed = next != self.live;
+        self.live = next;
+        changed
+    }
+
+    fn is_alive(&self, cell: T::Cell) -> bool {
+        self.live.contains(&cell)
+    }
+
+    fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    fn live_cells(&self) -> Vec<T::Cell> {
+        self.live.iter().copied().collect()
+    }
+}
+
+/// Stores a flag for every cell of a [`Bounded`] topology, with neighbors resolved up front
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dense<C: Eq + Hash> {
+    cells: Vec<C>,
+    index: HashMap<C, usize>,
+    neighbors: Vec<Vec<usize>>,
+    alive: Vec<bool>,
+}
+
+impl<T: Bounded> Backend<T> for Dense<T::Cell> {
+    fn new(topology: &T, live: Vec<T::Cell>) -> Self {
+        let cells = topology.cells();
+        let index: HashMap<_, _> = cells
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| (*cell, idx))
+            .collect();
+        let neighbors = cells
+            .iter()
+            .map(|cell| {
+                topology
+                    .neighbors(*cell)
+                    .iter()
+                    .filter_map(|neighbor| index.get(neighbor).copied())
+                    .collect()
+            })
+            .collect();
+        let mut alive = vec![false; cells.len()];
+        for cell in live {
+            if let Some(idx) = index.get(&cell) {
+                alive[*idx] = true;
+            }
+        }
+
+        Self {
+            cells,
+            index,
+            neighbors,
+            alive,
+        }
+    }
+
+    fn step<R: Rule>(&mut self, _topology: &T, rule: &R) -> bool {
+        let next: Vec<bool> = self
+            .neighbors
+            .iter()
+            .zip(&self.alive)
+            .map(|(neighbors, alive)| {
+                let count = neighbors.iter().filter(|idx| self.alive[**idx]).count();
+                rule.next(*alive, count)
+            })
+            .collect();
+
+        let changed = next != self.alive;
+        self.alive = next;
+        changed
+    }
+
+    fn is_alive(&self, cell: T::Cell) -> bool {
+        match self.index.get(&cell) {
+            Some(idx) => self.alive[*idx],
+            None => false,
+        }
+    }
+
+    fn population(&self) -> usize {
+        self.alive.iter().filter(|alive| **alive).count()
+    }
+
+    fn live_cells(&self) -> Vec<T::Cell> {
+        self.cells
+            .iter()
+            .zip(&self.alive)
+            .filter(|(_, alive)| **alive)
+            .map(|(cell, _)| *cell)
+            .collect()
+    }
+}
+
+/// A cellular automaton
+#[derive(Debug, Clone)]
+pub struct Automaton<T, R, B> {
+    topology: T,
+    rule: R,
+    backend: B,
+    generation: usize,
+}
+
+/// An [`Automaton`] using the [`Sparse`] backend
+pub type SparseAutomaton<T, R> = Automaton<T, R, Sparse<<T as Topology>::Cell>>;
+
+/// An [`Automaton`] using the [`Dense`] backend
+pub type DenseAutomaton<T, R> = Automaton<T, R, Dense<<T as Topology>::Cell>>;
+
+impl<T, R, B> Automaton<T, R, B>
+where
+    T: Topology,
+    R: Rule,
+    B: Backend<T>,
+{
+    /// Create an automaton with the provided live cells
+    pub fn new(topology: T, rule: R, live: impl IntoIterator<Item = T::Cell>) -> Self {
+        let backend = B::new(&topology, live.into_iter().collect());
+        Self {
+            topology,
+            rule,
+            backend,
+            generation: 0,
+        }
+    }
+
+    /// Advance one generation, returning true if any cell changed
+    pub fn step(&mut self) -> bool {
+        self.generation += 1;
+        self.backend.step(&self.topology, &self.rule)
+    }
+
+    /// Advance the provided number of generations
+    pub fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Advance until a generation makes no changes, returning the generation the automaton
+    /// settled at. Returns None if it hasn't settled after `limit` generations
+    pub fn run_to_fixpoint(&mut self, limit: usize) -> Option<usize> {
+        for _ in 0..limit {
+            if !self.step() {
+                // the last step didn't change anything
+                return Some(self.generation - 1);
+            }
+        }
+
+        None
+    }
+
+    /// Return the number of generations run so far
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Return the number of live cells
+    pub fn population(&self) -> usize {
+        self.backend.population()
+    }
+
+    /// true if `cell` is alive
+    pub fn is_alive(&self, cell: T::Cell) -> bool {
+        self.backend.is_alive(cell)
+    }
+
+    /// Return the live cells, in no particular order
+    pub fn live_cells(&self) -> Vec<T::Cell> {
+        self.backend.live_cells()
+    }
+
+    /// Return the topology the automaton runs on
+    pub fn topology(&self) -> &T {
+        &self.topology
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lattice_neighbors() {
+        let msg = "should return every touching cell of an N dimensional lattice";
+
+        let neighbors = Lattice::<2>.neighbors([0, 0]);
+        let expected = vec![
+            [-1, -1],
+            [0, -1],
+            [1, -1],
+            [-1, 0],
+            [1, 0],
+            [-1, 1],
+            [0, 1],
+            [1, 1],
+        ];
+        assert_eq!(neighbors, expected, "{}", msg);
+
+        assert_eq!(Lattice::<3>.neighbors([0, 0, 0]).len(), 26, "{}", msg);
+        assert_eq!(Lattice::<4>.neighbors([0, 0, 0, 0]).len(), 80, "{}", msg);
+    }
+
+    #[test]
+    fn grid_neighbors() {
+        let msg = "should only return neighbors that are cells";
+
+        let grid: Grid<char> = "L.L\n...\nL.L".parse().unwrap();
+        let adjacent = FiniteGrid::from_grid(&grid, |ch| *ch == 'L');
+        assert_eq!(adjacent.neighbors((0, 0)), vec![], "{}", msg);
+        assert!(!adjacent.contains((0, 1)), "{}", msg);
+
+        let visible = LineOfSight::from_grid(&grid, |ch| *ch == 'L');
+        let expected = vec![(0, 2), (2, 2), (2, 0)];
+        assert_eq!(visible.neighbors((0, 0)), expected, "{}", msg);
+        assert_eq!(visible.cells().len(), 4, "{}", msg);
+    }
+
+    #[test]
+    fn glider() {
+        let msg = "should move a glider one cell diagonally every 4 generations";
+
+        let glider = vec![[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]];
+        let mut life: SparseAutomaton<_, _> =
+            Automaton::new(Lattice::<2>, LifeRule::conway(), glider.clone());
+        life.run(4);
+
+        let mut expected: Vec<_> = glider.iter().map(|[r, c]| [r + 1, c + 1]).collect();
+        let mut actual = life.live_cells();
+        expected.sort_unstable();
+        actual.sort_unstable();
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(life.generation(), 4, "{}", msg);
+    }
+
+    #[test]
+    fn backends_agree() {
+        let msg = "should produce the same generations with either backend";
+
+        let live = vec![(0, 1), (1, 1), (2, 1), (3, 3), (3, 4), (4, 3)];
+        let mut sparse: SparseAutomaton<_, _> =
+            Automaton::new(FiniteGrid::new(6, 6), LifeRule::conway(), live.clone());
+        let mut dense: DenseAutomaton<_, _> =
+            Automaton::new(FiniteGrid::new(6, 6), LifeRule::conway(), live);
+
+        for _ in 0..5 {
+            sparse.step();
+            dense.step();
+            let mut expected = sparse.live_cells();
+            let mut actual = dense.live_cells();
+            expected.sort_unstable();
+            actual.sort_unstable();
+            assert_eq!(actual, expected, "{}", msg);
+        }
+    }
+
+    #[test]
+    fn fixpoint() {
+        let msg = "should stop once a generation makes no changes";
+
+        let block = vec![[0, 0], [0, 1], [1, 0], [1, 1]];
+        let mut life: SparseAutomaton<_, _> =
+            Automaton::new(Lattice::<2>, LifeRule::conway(), block);
+        assert_eq!(life.run_to_fixpoint(10), Some(0), "{}", msg);
+
+        let blinker = vec![[0, -1], [0, 0], [0, 1]];
+        let mut life: SparseAutomaton<_, _> =
+            Automaton::new(Lattice::<2>, LifeRule::conway(), blinker);
+        assert_eq!(life.run_to_fixpoint(10), None, "{}", msg);
+    }
+
+    #[test]
+    fn closure_rule() {
+        let msg = "should accept a closure as a rule";
+
+        // every cell with a live neighbor comes alive
+        let spread = |alive: bool, count: usize| alive || count > 0;
+        let mut automaton: SparseAutomaton<_, _> = Automaton::new(HexLattice, spread, vec![(0, 0)]);
+        automaton.run(2);
+        assert_eq!(automaton.population(), 19, "{}", msg);
+    }
+}
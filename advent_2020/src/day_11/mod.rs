@@ -1,8 +1,8 @@
 //! Solutions to 2020 day 11
 //! --- Day 11: Seating System ---
-use std::iter::Sum;
-
+use crate::automaton::{Automaton, Bounded, DenseAutomaton, FiniteGrid, LifeRule};
 use crate::day_3::load_terrain;
+use crate::grid::{Grid, Position};
 
 mod two;
 pub use two::two;
@@ -18,17 +18,6 @@ enum Seating {
     Floor,
 }
 
-impl Seating {
-    // return numeric value for this instance
-    fn value(&self) -> usize {
-        match self {
-            Seating::Full => 1,
-            Seating::Open => 0,
-            Seating::Floor => 0,
-        }
-    }
-}
-
 impl From<char> for Seating {
     fn from(character: char) -> Self {
         match character {
@@ -40,121 +29,77 @@ impl From<char> for Seating {
     }
 }
 
-impl<'a> Sum<&'a Seating> for usize {
-    fn sum<I>(iter: I) -> Self
-    where
-        I: Iterator<Item = &'a Seating>,
-    {
-        iter.fold(0, |acc, next| acc + next.value())
-    }
-}
-
-type Map<T> = Vec<Vec<T>>;
-
-fn count_neighbors(map: &[Vec<Seating>], r: usize, c: usize) -> usize {
-    let mut neighbors = 0usize;
-    let left = c.checked_sub(1);
-    let right = c.checked_add(1);
-    let up = r.checked_sub(1);
-    let down = r.checked_add(1);
-
-    // count left
-    neighbors += left
-        .and_then(|left| map[r].get(left).map(Seating::value))
-        .unwrap_or(0);
-    // count right
-    neighbors += right
-        .and_then(|right| map[r].get(right).map(Seating::value))
-        .unwrap_or(0);
-    // count up
-    neighbors += up
-        .and_then(|up| map.get(up).and_then(|row| row.get(c).map(Seating::value)))
-        .unwrap_or(0);
-    // count down
-    neighbors += down
-        .and_then(|down| map.get(down).and_then(|row| row.get(c).map(Seating::value)))
-        .unwrap_or(0);
-
-    // diagonals
-    if let Some(up) = up {
-        // count up left
-        neighbors += map
-            .get(up)
-            .and_then(|row| left.and_then(|left| row.get(left).map(Seating::value)))
-            .unwrap_or(0);
-        // count up right
-        neighbors += map
-            .get(up)
-            .and_then(|row| right.and_then(|right| row.get(right).map(Seating::value)))
-            .unwrap_or(0);
-    }
-    if let Some(down) = down {
-        // count down left
-        neighbors += map
-            .get(down)
-            .and_then(|row| left.and_then(|left| row.get(left).map(Seating::value)))
-            .unwrap_or(0);
-        // count down right
-        neighbors += map
-            .get(down)
-            .and_then(|row| right.and_then(|right| row.get(right).map(Seating::value)))
-            .unwrap_or(0);
-    }
-
-    neighbors
+type Map<T> = Grid<T>;
+
+/// Set up the seating rules as an automaton over the seats of a map, where occupied seats are
+/// alive.
+///
+/// - If a seat is empty and there are no occupied seats adjacent to it, the seat becomes occupied.
+/// - If a seat is occupied and `tolerance` or more seats adjacent to it are also occupied, the
+///   seat becomes empty.
+fn seating<T>(topology: T, tolerance: usize, map: &Map<Seating>) -> DenseAutomaton<T, LifeRule>
+where
+    T: Bounded<Cell = Position>,
+{
+    let occupied = map
+        .indexed_iter()
+        .filter(|(_, seat)| **seat == Seating::Full)
+        .map(|(position, _)| position);
+
+    Automaton::new(
+        topology,
+        LifeRule::new(0..=0, 0..=(tolerance - 1)),
+        occupied,
+    )
 }
 
-fn next(map: &[Vec<Seating>]) -> Option<Map<Seating>> {
-    let mut result = map.to_vec();
-    let map_height = map.len();
-    let map_width = map[0].len();
-    let mut changes = 0;
-
-    for r in 0..map_height {
-        for c in 0..map_width {
-            match map[r][c] {
-                Seating::Full => {
-                    // If a seat is occupied and four or more seats adjacent to it are also occupied,
-                    // the seat becomes empty.
-                    if count_neighbors(map, r, c) >= 4 {
-                        result[r][c] = Seating::Open;
-                        changes += 1;
-                    }
-                }
-                Seating::Open => {
-                    // If a seat is empty and there are no occupied seats adjacent to it,
-                    // the seat becomes occupied.
-                    if count_neighbors(map, r, c) == 0 {
-                        result[r][c] = Seating::Full;
-                        changes += 1;
-                    }
-                }
-                Seating::Floor => {}
-            }
+/// return the map with seats filled according to the automaton
+#[cfg(test)]
+fn render<T>(automaton: &DenseAutomaton<T, LifeRule>, map: &Map<Seating>) -> Map<Seating>
+where
+    T: Bounded<Cell = Position>,
+{
+    let mut result = map.clone();
+    for (position, seat) in map.indexed_iter() {
+        if *seat != Seating::Floor {
+            result[position] = match automaton.is_alive(position) {
+                true => Seating::Full,
+                false => Seating::Open,
+            };
         }
     }
 
-    if changes == 0 {
-        return None;
-    }
-    Some(result)
+    result
 }
 
 /// return the count of occupied seats once the system has stagnated
 pub fn one(file_path: &str) -> usize {
-    let mut map: Map<Seating> = load_terrain(file_path);
-    while let Some(next_map) = next(&map) {
-        map = next_map;
-    }
-
-    map.iter()
-        .fold(0, |acc, next| acc + next.iter().sum::<usize>())
+    let map: Map<Seating> = load_terrain(file_path);
+    let topology = FiniteGrid::from_grid(&map, |seat| *seat != Seating::Floor);
+    let mut automaton = seating(topology, 4, &map);
+    automaton
+        .run_to_fixpoint(usize::MAX)
+        .expect("Seating never stagnated");
+
+    automaton.population()
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn calc_next() {
+        let msg = "should fill every seat with no occupied neighbors";
+        let map: Map<Seating> = "L.LL\nLLL.\n#.LL".parse().unwrap();
+        let expected: Map<_> = "#.##\nLL#.\n#.##".parse().unwrap();
+
+        let topology = FiniteGrid::from_grid(&map, |seat| *seat != Seating::Floor);
+        let mut automaton = seating(topology, 4, &map);
+        automaton.step();
+        assert_eq!(render(&automaton, &map), expected, "{}", msg);
+    }
+
     #[test]
     fn part_one() {
         let msg = "should return the count of occupied seats once the system has stagnated";
@@ -1,15 +1,25 @@
 //! Solutions to 2020 day 12
 //! --- Day 12: Rain Risk ---
-use std::collections::VecDeque;
+//!
+//! A [`Ship`] follows navigation instructions according to a [`NavigationModel`]: part one moves
+//! the ship on its own [`HeadingModel`], part two steers it with a [`WaypointModel`]. Either way
+//! the ship records every position it visits.
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 use crate::day_1::read_file;
 
+mod plot;
+pub use plot::Plot;
+
 /// Ship navigation instruction
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Instruction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// move in a direction by the given value
     Move(Direction, u32),
-    Turn(u32),
+    /// turn clockwise by the given number of degrees. Left turns are negative
+    Turn(i32),
+    /// move forward by the given value
     Forward(u32),
 }
 
@@ -17,6 +27,9 @@ impl FromStr for Instruction {
     type Err = String;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.is_empty() {
+            return Err("Empty instruction".to_string());
+        }
         let (instruction, argument) = value.split_at(1);
         let argument: u32 = argument.parse().map_err(|err| {
             format!(
@@ -24,53 +37,52 @@ impl FromStr for Instruction {
                 value, argument, err
             )
         })?;
+        let degrees = || {
+            i32::try_from(argument)
+                .map_err(|_| format!("Turn of {} degrees is too large", argument))
+        };
 
         match instruction {
             "N" => Ok(Self::Move(Direction::North, argument)),
             "S" => Ok(Self::Move(Direction::South, argument)),
             "E" => Ok(Self::Move(Direction::East, argument)),
             "W" => Ok(Self::Move(Direction::West, argument)),
-            "L" => Ok(Self::Turn(360 - argument)),
-            "R" => Ok(Self::Turn(argument)),
+            "L" => Ok(Self::Turn(-degrees()?)),
+            "R" => Ok(Self::Turn(degrees()?)),
             "F" => Ok(Self::Forward(argument)),
             _ => Err(format!("Unrecognized instruction: {}", instruction)),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Direction {
+/// Compass direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// positive y
     North,
+    /// negative y
     South,
+    /// positive x
     East,
+    /// negative x
     West,
 }
 
 impl Direction {
-    pub fn change_heading(&self, degrees: u32) -> Self {
-        // given the problem statement, assume turns are restricted to cardinal directions (thus
-        // multiples of 90 degrees)
-        match degrees {
-            90 => match self {
-                Direction::North => Direction::East,
-                Direction::South => Direction::West,
-                Direction::East => Direction::South,
-                Direction::West => Direction::North,
-            },
-            180 => match self {
-                Direction::North => Direction::South,
-                Direction::South => Direction::North,
-                Direction::East => Direction::West,
-                Direction::West => Direction::East,
-            },
-            270 => match self {
-                Direction::North => Direction::West,
-                Direction::South => Direction::East,
-                Direction::East => Direction::North,
-                Direction::West => Direction::South,
-            },
-            _ => *self,
-        }
+    /// Return the direction after turning clockwise by `degrees`, which must be a multiple of 90
+    pub fn change_heading(&self, degrees: i32) -> Result<Self, String> {
+        const CLOCKWISE: [Direction; 4] = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+        let current = CLOCKWISE
+            .iter()
+            .position(|direction| direction == self)
+            .unwrap_or_default();
+
+        Ok(CLOCKWISE[(current + quarter_turns(degrees)?) % 4])
     }
 }
 
@@ -88,7 +100,21 @@ fn deserialize(serialized: &str) -> Result<Vec<Instruction>, String> {
         .collect::<Result<Vec<_>, _>>()
 }
 
-type Position = (i32, i32);
+/// East/west and north/south coordinate
+pub type Position = (i32, i32);
+
+/// returns the number of clockwise quarter turns, from 0 to 3, in a turn of `degrees`
+fn quarter_turns(degrees: i32) -> Result<usize, String> {
+    // the ship only sails on a grid, so only turns to cardinal directions make sense
+    if degrees % 90 != 0 {
+        return Err(format!(
+            "Invalid turn of {} degrees, turns must be a multiple of 90",
+            degrees
+        ));
+    }
+
+    Ok((degrees.rem_euclid(360) / 90) as usize)
+}
 
 /// returns a new coordinate resulting from moving the supplied coordinate in the specified
 /// direction and amount
@@ -103,126 +129,210 @@ fn move_position(pos: Position, direction: Direction, value: u32) -> Position {
 }
 
 /// returns a new coordinate calculated from rotating the supplied coordinate clockwise around the
-/// origin by the specified number of degrees
-fn rotate_position(pos: Position, degrees: u32) -> Position {
-    // given the problem statement, assume turns are restricted to cardinal directions (thus
-    // multiples of 90 degrees)
-    match degrees {
-        90 => (pos.1, -pos.0),
-        180 => (-pos.0, -pos.1),
-        270 => (-pos.1, pos.0),
+/// origin by the specified number of degrees, which must be a multiple of 90
+fn rotate_position(pos: Position, degrees: i32) -> Result<Position, String> {
+    Ok(match quarter_turns(degrees)? {
+        1 => (pos.1, -pos.0),
+        2 => (-pos.0, -pos.1),
+        3 => (-pos.1, pos.0),
         _ => pos,
-    }
+    })
 }
 
-struct Nav {
-    /// List of navigation instructions
-    instructions: VecDeque<Instruction>,
-    /// Current position
-    position: Position,
-    /// Current direction of travel
-    direction: Direction,
+/// returns the south-west and north-east corners of the smallest box containing every point
+fn bounding_box(points: &[Position]) -> Option<(Position, Position)> {
+    let first = *points.first()?;
+    Some(points.iter().fold(
+        (first, first),
+        |((min_x, min_y), (max_x, max_y)), (x, y)| {
+            (
+                (min_x.min(*x), min_y.min(*y)),
+                (max_x.max(*x), max_y.max(*y)),
+            )
+        },
+    ))
 }
 
-impl Nav {
-    pub fn with_instructions(instructions: &[Instruction]) -> Self {
-        Self {
-            position: (0, 0),
-            direction: Direction::East,
-            instructions: instructions.iter().copied().collect(),
-        }
+/// Decides how an instruction moves the ship
+pub trait NavigationModel {
+    /// Apply `instruction`, returning the ship's next position
+    fn navigate(
+        &mut self,
+        position: Position,
+        instruction: &Instruction,
+    ) -> Result<Position, String>;
+
+    /// Return the waypoint relative to the ship, if the model steers by one
+    fn waypoint(&self) -> Option<Position> {
+        None
     }
+}
 
-    /// Advance state by processing next instruction
-    fn next(&mut self) -> Option<()> {
-        if let Some(instruction) = self.instructions.pop_front() {
-            match instruction {
-                Instruction::Move(direction, value) => {
-                    self.position = move_position(self.position, direction, value);
-                }
-                Instruction::Turn(degrees) => {
-                    self.direction = self.direction.change_heading(degrees);
-                }
-                Instruction::Forward(value) => {
-                    self.position = move_position(self.position, self.direction, value);
-                }
-            }
-            return Some(());
-        }
+/// Instructions move the ship directly. Forward moves it in the direction it faces
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeadingModel {
+    /// direction the ship faces
+    pub heading: Direction,
+}
 
-        None
+impl NavigationModel for HeadingModel {
+    fn navigate(
+        &mut self,
+        position: Position,
+        instruction: &Instruction,
+    ) -> Result<Position, String> {
+        Ok(match instruction {
+            Instruction::Move(direction, value) => move_position(position, *direction, *value),
+            Instruction::Turn(degrees) => {
+                self.heading = self.heading.change_heading(*degrees)?;
+                position
+            }
+            Instruction::Forward(value) => move_position(position, self.heading, *value),
+        })
     }
 }
 
-impl Iterator for Nav {
-    type Item = ();
+/// Instructions move a waypoint relative to the ship. Forward moves the ship to the waypoint a
+/// number of times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaypointModel {
+    /// navigation waypoint relative to the ship
+    pub waypoint: Position,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next()
+impl Default for WaypointModel {
+    fn default() -> Self {
+        Self { waypoint: (10, 1) }
     }
 }
 
-/// return the manhattan distance from the start position
-pub fn one(file_path: &str) -> usize {
-    let file_content = read_file(file_path);
-    let instructions = deserialize(&file_content).unwrap();
-    let mut nav = Nav::with_instructions(&instructions);
+impl NavigationModel for WaypointModel {
+    fn navigate(
+        &mut self,
+        position: Position,
+        instruction: &Instruction,
+    ) -> Result<Position, String> {
+        Ok(match instruction {
+            Instruction::Move(direction, value) => {
+                // move the waypoint in the given direction by the given value
+                self.waypoint = move_position(self.waypoint, *direction, *value);
+                position
+            }
+            Instruction::Turn(degrees) => {
+                // rotate the waypoint around the ship
+                self.waypoint = rotate_position(self.waypoint, *degrees)?;
+                position
+            }
+            Instruction::Forward(value) => (
+                position.0 + self.waypoint.0 * (*value as i32),
+                position.1 + self.waypoint.1 * (*value as i32),
+            ),
+        })
+    }
 
-    while nav.next().is_some() {}
-    let (x, y) = nav.position;
-    (x.abs() + y.abs()) as usize
+    fn waypoint(&self) -> Option<Position> {
+        Some(self.waypoint)
+    }
 }
 
-#[derive(Debug, PartialEq)]
-struct Nav2 {
-    /// Current position
-    position: Position,
-    /// navigation waypoint relative to current position
-    waypoint: Position,
+/// A ship following navigation instructions, starting at the origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ship<M> {
+    model: M,
+    /// position before the first instruction and after each one since
+    path: Vec<Position>,
+    /// waypoint relative to the ship alongside each position, if the model has one
+    waypoints: Vec<Position>,
 }
 
-impl Default for Nav2 {
-    fn default() -> Self {
+impl<M: NavigationModel> Ship<M> {
+    /// Create a ship at the origin, navigating with `model`
+    pub fn new(model: M) -> Self {
         Self {
-            position: Default::default(),
-            waypoint: (10, 1),
+            waypoints: model.waypoint().into_iter().collect(),
+            model,
+            path: vec![(0, 0)],
         }
     }
-}
 
-/// return the next [`Nav`](Nav2) state given an initial state and an [`Instruction`]
-fn process_instruction(state: Nav2, instruction: &Instruction) -> Nav2 {
-    let mut next = Nav2 { ..state };
-    match instruction {
-        Instruction::Move(direction, value) => {
-            // move the waypoint in the given direction by the given value
-            next.waypoint = move_position(next.waypoint, *direction, *value);
-        }
-        Instruction::Turn(degrees) => {
-            // rotate the waypoint around the ship
-            next.waypoint = rotate_position(next.waypoint, *degrees);
-        }
-        Instruction::Forward(value) => {
-            // moves the ship to the waypoint value times
-            next.position.0 += next.waypoint.0 * (*value as i32);
-            next.position.1 += next.waypoint.1 * (*value as i32);
+    /// Return the navigation model
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Return the current position
+    pub fn position(&self) -> Position {
+        self.path[self.path.len() - 1]
+    }
+
+    /// Return the starting position followed by the position after each instruction
+    pub fn path(&self) -> &[Position] {
+        &self.path
+    }
+
+    /// Return the waypoint relative to the ship at each position in the path. Empty if the model
+    /// has no waypoint
+    pub fn waypoint_track(&self) -> &[Position] {
+        &self.waypoints
+    }
+
+    /// Follow a single instruction. On error the ship doesn't move
+    pub fn execute(&mut self, instruction: &Instruction) -> Result<Position, String> {
+        let position = self.model.navigate(self.position(), instruction)?;
+        self.path.push(position);
+        self.waypoints.extend(self.model.waypoint());
+
+        Ok(position)
+    }
+
+    /// Follow each instruction in order, stopping at the first invalid one
+    pub fn run(&mut self, instructions: &[Instruction]) -> Result<Position, String> {
+        for instruction in instructions {
+            self.execute(instruction)?;
         }
+
+        Ok(self.position())
     }
 
-    next
+    /// Return the manhattan distance from the start position
+    pub fn manhattan_distance(&self) -> u32 {
+        let (start, (x, y)) = (self.path[0], self.position());
+        start.0.abs_diff(x) + start.1.abs_diff(y)
+    }
+
+    /// Return the total manhattan distance sailed
+    pub fn distance_traveled(&self) -> u64 {
+        self.path
+            .windows(2)
+            .map(|pair| u64::from(pair[0].0.abs_diff(pair[1].0) + pair[0].1.abs_diff(pair[1].1)))
+            .sum()
+    }
+
+    /// Return the south-west and north-east corners of the smallest box containing the path
+    pub fn bounding_box(&self) -> (Position, Position) {
+        bounding_box(&self.path).unwrap_or_default()
+    }
 }
 
-/// return the manhattan distance from the start position
-// behavior on structs is notably less flexible than pure functions with struct parameters
-pub fn two(file_path: &str) -> usize {
+/// follow the instructions in a file and return the ship
+fn sail<M: NavigationModel>(file_path: &str, model: M) -> Ship<M> {
     let file_content = read_file(file_path);
     let instructions = deserialize(&file_content).unwrap();
-    let nav = instructions
-        .iter()
-        .fold(Nav2::default(), process_instruction);
-    let (x, y) = nav.position;
+    let mut ship = Ship::new(model);
+    ship.run(&instructions)
+        .expect("Invalid navigation instruction");
 
-    (x.abs() + y.abs()) as usize
+    ship
+}
+
+/// return the manhattan distance from the start position
+pub fn one(file_path: &str) -> usize {
+    sail(file_path, HeadingModel::default()).manhattan_distance() as usize
+}
+
+/// return the manhattan distance from the start position
+pub fn two(file_path: &str) -> usize {
+    sail(file_path, WaypointModel::default()).manhattan_distance() as usize
 }
 
 #[cfg(test)]
@@ -244,6 +354,58 @@ mod test {
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    #[test]
+    fn turns() {
+        let msg = "should turn by any multiple of 90 degrees";
+        let actual = Direction::East.change_heading(-90);
+        assert_eq!(actual, Ok(Direction::North), "{}", msg);
+        let actual = Direction::East.change_heading(450);
+        assert_eq!(actual, Ok(Direction::South), "{}", msg);
+        let actual = rotate_position((10, 4), -270);
+        assert_eq!(actual, Ok((4, -10)), "{}", msg);
+
+        let msg = "should reject other turns";
+        let expected =
+            Err("Invalid turn of 45 degrees, turns must be a multiple of 90".to_string());
+        assert_eq!(Direction::East.change_heading(45), expected, "{}", msg);
+        let mut ship = Ship::new(WaypointModel::default());
+        let instructions = deserialize("F10\nL45\nF10").unwrap();
+        let expected =
+            Err("Invalid turn of -45 degrees, turns must be a multiple of 90".to_string());
+        assert_eq!(ship.run(&instructions), expected, "{}", msg);
+        assert_eq!(ship.path(), &[(0, 0), (100, 10)], "{}", msg);
+    }
+
+    #[test]
+    fn records_path() {
+        let msg = "should record each position of the ship";
+        let ship = sail("input/12-t.txt", HeadingModel::default());
+        let expected = [(0, 0), (10, 0), (10, 3), (17, 3), (17, 3), (17, -8)];
+        assert_eq!(ship.path(), &expected, "{}", msg);
+        assert_eq!(ship.bounding_box(), ((0, -8), (17, 3)), "{}", msg);
+        assert_eq!(ship.distance_traveled(), 31, "{}", msg);
+
+        let ship = sail("input/12-t.txt", WaypointModel::default());
+        let expected = [
+            (0, 0),
+            (100, 10),
+            (100, 10),
+            (170, 38),
+            (170, 38),
+            (214, -72),
+        ];
+        assert_eq!(ship.path(), &expected, "{}", msg);
+        assert_eq!(ship.bounding_box(), ((0, -72), (214, 38)), "{}", msg);
+        assert_eq!(ship.distance_traveled(), 362, "{}", msg);
+        assert_eq!(ship.model().waypoint, (4, -10), "{}", msg);
+
+        let msg = "should record the waypoint alongside each position";
+        let expected = [(10, 1), (10, 1), (10, 4), (10, 4), (4, -10), (4, -10)];
+        assert_eq!(ship.waypoint_track(), &expected, "{}", msg);
+        let ship = sail("input/12-t.txt", HeadingModel::default());
+        assert!(ship.waypoint_track().is_empty(), "{}", msg);
+    }
+
     #[test]
     fn part_one() {
         let msg = "should return the manhattan distance from the start position";
@@ -0,0 +1,202 @@
+//! Plots of ship routes
+//!
+//! A [`Plot`] draws a track of positions, north up, as an SVG document or a scaled ASCII canvas.
+//! The start of the track is marked `S` and the end `E`.
+//!
+//! ```
+//! use advent_2020::day_12::Plot;
+//!
+//! let plot = Plot::new(&[(0, 0), (2, 0), (2, 2)]);
+//! assert_eq!(plot.to_ascii(3, 3), "..E\n..#\nS##\n");
+//! ```
+use std::fs;
+use std::path::Path;
+
+use super::{bounding_box, NavigationModel, Position, Ship};
+
+/// A track of positions to draw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plot {
+    points: Vec<Position>,
+}
+
+impl Plot {
+    /// Create a plot of `points`, in order
+    pub fn new(points: &[Position]) -> Self {
+        Self {
+            points: points.to_vec(),
+        }
+    }
+
+    /// Create a plot of the route a ship took
+    pub fn route<M: NavigationModel>(ship: &Ship<M>) -> Self {
+        Self::new(ship.path())
+    }
+
+    /// Create a plot of the waypoint's track relative to a ship, if it navigates by one
+    pub fn waypoint_track<M: NavigationModel>(ship: &Ship<M>) -> Option<Self> {
+        Some(ship.waypoint_track())
+            .filter(|track| !track.is_empty())
+            .map(Self::new)
+    }
+
+    /// Render the plot as an SVG document
+    pub fn to_svg(&self) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = bounding_box(&self.points).unwrap_or_default();
+        // leave a margin for the markers, and flip y so north is up
+        let margin = ((max_x - min_x).max(max_y - min_y) / 20).max(1);
+        let radius = margin as f64 / 2.0;
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, -y))
+            .collect();
+        let marker = |point: Option<&Position>, color: &str| {
+            point.map_or(String::new(), |(x, y)| {
+                format!(
+                    r#"  <circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                    x, -y, radius, color
+                ) + "\n"
+            })
+        };
+
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+                "\n",
+                r#"  <polyline points="{}" fill="none" stroke="black" stroke-width="1" vector-effect="non-scaling-stroke"/>"#,
+                "\n{}{}</svg>\n"
+            ),
+            min_x - margin,
+            -max_y - margin,
+            max_x - min_x + 2 * margin,
+            max_y - min_y + 2 * margin,
+            points.join(" "),
+            marker(self.points.first(), "green"),
+            marker(self.points.last(), "red"),
+        )
+    }
+
+    /// Write the plot to an SVG file
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_svg())
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    }
+
+    /// Render the plot on a canvas of at most `width` by `height` characters. Both axes share a
+    /// scale, so the canvas shrinks along the shorter side of the track
+    pub fn to_ascii(&self, width: usize, height: usize) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = match bounding_box(&self.points) {
+            Some(bounds) if width > 0 && height > 0 => bounds,
+            _ => return String::new(),
+        };
+        let (span_x, span_y) = ((max_x - min_x) as f64, (max_y - min_y) as f64);
+        let scale = [(width, span_x), (height, span_y)]
+            .iter()
+            .filter(|(_, span)| *span > 0.0)
+            .map(|(size, span)| (*size - 1) as f64 / span)
+            .fold(f64::INFINITY, f64::min);
+        let scale = if scale.is_finite() { scale } else { 0.0 };
+
+        let cols = (span_x * scale).round() as usize + 1;
+        let rows = (span_y * scale).round() as usize + 1;
+        let cell = |(x, y): Position| {
+            let col = ((x - min_x) as f64 * scale).round() as isize;
+            let row = ((max_y - y) as f64 * scale).round() as isize;
+            (col, row)
+        };
+
+        let mut canvas = vec![vec!['.'; cols]; rows];
+        for pair in self.points.windows(2) {
+            let ((from_col, from_row), (to_col, to_row)) = (cell(pair[0]), cell(pair[1]));
+            let steps = (to_col - from_col)
+                .abs()
+                .max((to_row - from_row).abs())
+                .max(1);
+            for step in 0..=steps {
+                let col = from_col + (to_col - from_col) * step / steps;
+                let row = from_row + (to_row - from_row) * step / steps;
+                canvas[row as usize][col as usize] = '#';
+            }
+        }
+        for (point, marker) in [(self.points.first(), 'S'), (self.points.last(), 'E')] {
+            if let Some(point) = point {
+                let (col, row) = cell(*point);
+                canvas[row as usize][col as usize] = marker;
+            }
+        }
+
+        canvas
+            .iter()
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day_12::{HeadingModel, WaypointModel};
+
+    #[test]
+    fn plots_ascii() {
+        let msg = "should scale the track onto the canvas";
+        let plot = Plot::new(&[(0, 0), (10, 0)]);
+        assert_eq!(plot.to_ascii(6, 3), "S####E\n", "{}", msg);
+
+        let msg = "should draw the example route";
+        let ship = super::super::sail("input/12-t.txt", HeadingModel::default());
+        let expected = "\
+..........########
+..........#......#
+..........#......#
+S##########......#
+.................#
+.................#
+.................#
+.................#
+.................#
+.................#
+.................#
+.................E
+";
+        assert_eq!(Plot::route(&ship).to_ascii(40, 12), expected, "{}", msg);
+
+        let msg = "should draw the waypoint's track around the ship";
+        let ship = super::super::sail("input/12-t.txt", WaypointModel::default());
+        let expected = "\
+......#
+......#
+......#
+.....#S
+.....#.
+....#..
+....#..
+...#...
+...#...
+..#....
+..#....
+.#.....
+.#.....
+E......
+";
+        let plot = Plot::waypoint_track(&ship).unwrap();
+        assert_eq!(plot.to_ascii(40, 14), expected, "{}", msg);
+        let ship = super::super::sail("input/12-t.txt", HeadingModel::default());
+        assert_eq!(Plot::waypoint_track(&ship), None, "{}", msg);
+    }
+
+    #[test]
+    fn plots_svg() {
+        let msg = "should render the track with north up and start and end markers";
+        let plot = Plot::new(&[(0, 0), (2, 0), (2, 2)]);
+        let expected = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -3 4 4">
+  <polyline points="0,0 2,0 2,-2" fill="none" stroke="black" stroke-width="1" vector-effect="non-scaling-stroke"/>
+  <circle cx="0" cy="0" r="0.5" fill="green"/>
+  <circle cx="2" cy="-2" r="0.5" fill="red"/>
+</svg>
+"#;
+        assert_eq!(plot.to_svg(), expected, "{}", msg);
+    }
+}
@@ -1,8 +1,11 @@
 //! Solutions to 2020 day 13
 //! --- Day 13: Shuttle Search ---
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::str::FromStr;
 
 use crate::day_1::read_file;
+use crate::math::{crt, CrtError};
 
 /// parse a string of comma separated values into a `Vec` of `T`, discarding failures
 pub fn parse_csv_lossy<T>(text: &str) -> Vec<T>
@@ -14,93 +17,161 @@ where
         .collect()
 }
 
-/// parse a string of comma separated values into a `Vec` of `Option<T>`
-fn parse_csv<T>(text: &str) -> Vec<Option<T>>
-where
-    T: FromStr,
-{
-    text.split(',')
-        .map(|text| T::from_str(text.trim()).ok())
-        .collect()
+/// A bus leaving the station
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Departure {
+    /// Departure timestamp
+    pub time: u64,
+    /// ID of the bus, which is also the number of minutes between its departures
+    pub bus: u64,
 }
 
-/// return ID of the earliest bus you can take to the airport multiplied by the number of minutes
-/// you'll need to wait for that bus
-pub fn one(file_path: &str) -> u32 {
-    let file_content = read_file(file_path);
-    let (departure, bus_schedules) = file_content
-        .split_once("\n")
-        .expect("Unable to parse notes");
-    let departure: u32 = departure.parse().expect("Unable to parse departure time");
-    let bus_schedules = parse_csv_lossy(bus_schedules);
-
-    let mut bus_schedules = bus_schedules
-        .iter()
-        .map(|id| {
-            let wait_time = id - (departure % id);
-            (id, wait_time)
-        })
-        .collect::<Vec<_>>();
-    bus_schedules.sort_unstable_by(|a, b| a.1.cmp(&b.1));
-    bus_schedules
-        .iter()
-        .take(1)
-        .map(|(id, wait_time)| *id * *wait_time)
-        .sum()
+/// The shuttle notes: the earliest timestamp you could depart, and the bus list with `None` for
+/// buses that are out of service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    earliest: u64,
+    buses: Vec<Option<u64>>,
 }
 
-/// find least common multiple
-fn lcm(a: usize, b: usize) -> usize {
-    if a == 0 || b == 0 {
-        return 0;
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(notes: &str) -> Result<Self, Self::Err> {
+        let (earliest, buses) = notes
+            .trim()
+            .split_once('\n')
+            .ok_or_else(|| "Missing bus list".to_string())?;
+        let earliest = earliest
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid departure time '{}'", earliest))?;
+        let buses = buses
+            .split(',')
+            .map(|bus| match bus.trim() {
+                "x" => Ok(None),
+                id => match id.parse() {
+                    Ok(0) | Err(_) => Err(format!("Invalid bus ID '{}'", id)),
+                    Ok(id) => Ok(Some(id)),
+                },
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { earliest, buses })
     }
+}
 
-    let min = a.min(b);
-    let max = a.max(b);
-    if max % min == 0 {
-        return max;
+impl Schedule {
+    /// Return the earliest timestamp you could depart
+    pub fn earliest(&self) -> u64 {
+        self.earliest
     }
 
-    let mut lcm = max;
-    while lcm % min != 0 {
-        lcm += max;
+    /// Iterate over the IDs of the buses in service
+    pub fn buses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buses.iter().flatten().copied()
     }
 
-    lcm
-}
+    /// Return the first `count` departures at or after `time`, in order of time then bus ID
+    pub fn next_departures(&self, time: u64, count: usize) -> Vec<Departure> {
+        // the next departure of each bus, earliest first
+        let mut upcoming: BinaryHeap<_> = self
+            .buses()
+            .map(|bus| {
+                let wait = (bus - time % bus) % bus;
+                Reverse(Departure {
+                    time: time + wait,
+                    bus,
+                })
+            })
+            .collect();
 
-fn find_timestamp(schedule_str: &str) -> usize {
-    let bus_schedules = parse_csv::<usize>(schedule_str)
-        .iter()
-        .enumerate()
-        .filter_map(|(idx, bus_id)| bus_id.and_then(|bus_id| Some((idx, bus_id))))
-        .collect::<Vec<_>>();
+        let mut departures = Vec::with_capacity(count);
+        while departures.len() < count {
+            let Reverse(departure) = match upcoming.pop() {
+                Some(next) => next,
+                // no buses in service
+                None => break,
+            };
+            departures.push(departure);
+            upcoming.push(Reverse(Departure {
+                time: departure.time + departure.bus,
+                ..departure
+            }));
+        }
 
-    let (_, first) = bus_schedules.first().unwrap();
+        departures
+    }
 
-    let mut step = *first;
-    let mut t_zero = *first;
+    /// Return the first departure at or after `time`, or an error if no buses are in service
+    pub fn next_departure(&self, time: u64) -> Result<Departure, String> {
+        self.next_departures(time, 1)
+            .first()
+            .copied()
+            .ok_or_else(|| "No buses in service".to_string())
+    }
 
-    for (bus_idx, bus_id) in bus_schedules.iter().skip(1) {
-        while (t_zero + bus_idx) % *bus_id != 0 {
-            t_zero += step;
+    /// Return the earliest timestamp `t` such that each `(bus, offset)` bus departs at `t +
+    /// offset`. Fails if a bus isn't in service, the constraints can never all be met, or the
+    /// timestamp doesn't fit in a `u128`
+    pub fn earliest_aligned(&self, constraints: &[(u64, u64)]) -> Result<u128, String> {
+        // buses in service always have a non-zero ID
+        if let Some((bus, _)) = constraints
+            .iter()
+            .find(|(bus, _)| !self.buses().any(|id| id == *bus))
+        {
+            return Err(format!("Bus {} isn't in service", bus));
         }
 
-        step = lcm(step, *bus_id);
+        // departing `offset` minutes after t means t == -offset (mod bus)
+        let congruences = constraints
+            .iter()
+            .map(|(bus, offset)| {
+                let (bus, offset) = (u128::from(*bus), u128::from(*offset));
+                ((bus - offset % bus) % bus, bus)
+            })
+            .collect::<Vec<_>>();
+
+        match crt(&congruences) {
+            Ok((timestamp, _)) => Ok(timestamp),
+            Err(CrtError::Overflow) => Err("Common timestamp is too large".to_string()),
+            Err(_) => Err("Bus schedules have no common timestamp".to_string()),
+        }
     }
 
-    t_zero
+    /// Return the earliest timestamp such that each bus in service departs at an offset matching
+    /// its position in the list. Fails like [`earliest_aligned`](Self::earliest_aligned)
+    pub fn earliest_in_list_order(&self) -> Result<u128, String> {
+        let constraints = self
+            .buses
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bus)| bus.map(|bus| (bus, idx as u64)))
+            .collect::<Vec<_>>();
+
+        self.earliest_aligned(&constraints)
+    }
+}
+
+/// return ID of the earliest bus you can take to the airport multiplied by the number of minutes
+/// you'll need to wait for that bus
+pub fn one(file_path: &str) -> u32 {
+    let schedule: Schedule = read_file(file_path).parse().expect("Unable to parse notes");
+    let departure = schedule
+        .next_departure(schedule.earliest())
+        .expect("Unable to find a departure");
+
+    (departure.bus * (departure.time - schedule.earliest())) as u32
 }
 
 /// return the earliest timestamp such that all of the listed bus IDs depart at offsets
 /// matching their positions in the list
 pub fn two(file_path: &str) -> usize {
-    let file_content = read_file(file_path);
-    let (_, bus_schedules) = file_content
-        .split_once("\n")
-        .expect("Unable to parse notes");
+    let schedule: Schedule = read_file(file_path).parse().expect("Unable to parse notes");
 
-    find_timestamp(bus_schedules)
+    schedule
+        .earliest_in_list_order()
+        .unwrap_or_else(|err| panic!("{}", err)) as usize
 }
 
 #[cfg(test)]
@@ -115,38 +186,101 @@ mod test {
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    /// find the timestamp for a bus list, in list order
+    fn find_timestamp(buses: &str) -> Result<u128, String> {
+        let schedule: Schedule = format!("0\n{}", buses).parse().unwrap();
+        schedule.earliest_in_list_order()
+    }
+
+    #[test]
+    fn parses_notes() {
+        let msg = "should parse the shuttle notes";
+        let expected = Schedule {
+            earliest: 939,
+            buses: vec![Some(7), Some(13), None, Some(59)],
+        };
+        let actual = "939\n7,13,x,59".parse();
+        assert_eq!(actual, Ok(expected), "{}", msg);
+
+        let msg = "should reject invalid notes";
+        let expected = Err("Invalid bus ID '0'".to_string());
+        let actual = "939\n7,0".parse::<Schedule>();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Err("Missing bus list".to_string());
+        let actual = "939".parse::<Schedule>();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn next_departures() {
+        let msg = "should return the next departures in order";
+        let schedule: Schedule = "0\n7,x,5".parse().unwrap();
+        let expected = vec![
+            Departure { time: 10, bus: 5 },
+            Departure { time: 14, bus: 7 },
+            Departure { time: 15, bus: 5 },
+            Departure { time: 20, bus: 5 },
+            Departure { time: 21, bus: 7 },
+        ];
+        let actual = schedule.next_departures(10, 5);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should return the next departure";
+        let expected = Ok(Departure { time: 10, bus: 5 });
+        assert_eq!(schedule.next_departure(10), expected, "{}", msg);
+
+        let msg = "should return no departures when no buses are in service";
+        let schedule: Schedule = "0\nx,x".parse().unwrap();
+        assert_eq!(schedule.next_departures(10, 5), vec![], "{}", msg);
+        let expected = Err("No buses in service".to_string());
+        assert_eq!(schedule.next_departure(10), expected, "{}", msg);
+    }
+
     #[test]
-    fn least_common_multiple() {
-        let msg = "should return the least common multiple";
-        let expected = 90;
-        let actual = lcm(18, 30);
+    fn aligns_departures() {
+        let msg = "should return the earliest timestamp meeting arbitrary offsets";
+        let schedule: Schedule = "0\n3,5".parse().unwrap();
+        // t + 1 is a multiple of 3, t + 3 is a multiple of 5
+        let expected = Ok(2);
+        let actual = schedule.earliest_aligned(&[(3, 1), (5, 3)]);
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 84;
-        let actual = lcm(21, 28);
+        let msg = "should reject buses that aren't in service";
+        let expected = Err("Bus 7 isn't in service".to_string());
+        assert_eq!(schedule.earliest_aligned(&[(7, 0)]), expected, "{}", msg);
+        let expected = Err("Bus 0 isn't in service".to_string());
+        assert_eq!(schedule.earliest_aligned(&[(0, 1)]), expected, "{}", msg);
+
+        let msg = "should reject inconsistent offsets";
+        let schedule: Schedule = "0\n4,6".parse().unwrap();
+        let expected = Err("Bus schedules have no common timestamp".to_string());
+        let actual = schedule.earliest_in_list_order();
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 140;
-        let actual = lcm(14, 20);
+        let msg = "should reject a timestamp too large for a u128";
+        let expected = Err("Common timestamp is too large".to_string());
+        let actual =
+            find_timestamp("18446744073709551557,18446744073709551533,18446744073709551521");
         assert_eq!(actual, expected, "{}", msg);
     }
 
     #[test]
     fn finds_timestamp() {
         let msg = "should return the earliest timestamp such that all of the listed bus IDs depart at offsets matching their positions in the list";
-        let expected = 3417;
+        let expected = Ok(3417);
         let actual = find_timestamp("17,x,13,19");
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 754018;
+        let expected = Ok(754018);
         let actual = find_timestamp("67,7,59,61");
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 1261476;
+        let expected = Ok(1261476);
         let actual = find_timestamp("67,7,x,59,61");
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 1202161486;
+        let expected = Ok(1202161486);
         let actual = find_timestamp("1789,37,47,1889");
         assert_eq!(actual, expected, "{}", msg);
     }
@@ -1,129 +1,412 @@
 //! Solutions to 2020 day 14
 //! --- Day 14: Docking Data ---
-use std::collections::HashMap;
+//!
+//! Rather than expanding floating bits into every address they cover, memory stores each write as
+//! a [`Pattern`] of addresses. A later write to overlapping addresses subtracts its pattern from
+//! the earlier ones, so the sum of memory can be computed from pattern sizes alone.
 use std::str::FromStr;
 
+use parser::lexer::{p_keyword, p_kind, p_number, p_punct, Lexer, TokenKind};
+
 use crate::day_1::read_file;
 
 mod two;
-pub use two::two;
-
-/// Operation to perform for a single bit of a bitmask
-#[derive(Clone, Debug, PartialEq)]
-enum MaskOp {
-    // set a one bit at the contained offset
-    One(usize),
-    // set a zero bit at the contained offset
-    Zero(usize),
+pub use two::{two, AddressDecoder};
+
+/// Number of bits in an address or value
+const WORD_SIZE: usize = 36;
+
+/// A bitmask, split into the bits it sets to one, the bits it sets to zero and the floating bits
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Mask {
+    /// bits marked '1'
+    pub ones: u64,
+    /// bits marked '0'
+    pub zeros: u64,
+    /// bits marked 'X'
+    pub floating: u64,
 }
 
-// parse a [`MaskOp`] from a str
-fn parse_op_str(value: &str, offset: usize) -> Result<MaskOp, String> {
-    let result = match value {
-        "0" => MaskOp::Zero(offset),
-        "1" => MaskOp::One(offset),
-        _ => {
-            return Err(format!("Parse failure: invalid character '{}'", value));
+impl FromStr for Mask {
+    type Err = String;
+
+    fn from_str(mask_str: &str) -> Result<Self, Self::Err> {
+        if mask_str.len() != WORD_SIZE {
+            return Err(format!(
+                "Parse failure: mask has {} bits, expected {}",
+                mask_str.len(),
+                WORD_SIZE
+            ));
         }
-    };
 
-    Ok(result)
+        mask_str.chars().try_fold(Mask::default(), |mask, ch| {
+            let mask = Mask {
+                ones: mask.ones << 1,
+                zeros: mask.zeros << 1,
+                floating: mask.floating << 1,
+            };
+            match ch {
+                '1' => Ok(Mask {
+                    ones: mask.ones | 1,
+                    ..mask
+                }),
+                '0' => Ok(Mask {
+                    zeros: mask.zeros | 1,
+                    ..mask
+                }),
+                'X' => Ok(Mask {
+                    floating: mask.floating | 1,
+                    ..mask
+                }),
+                _ => Err(format!("Parse failure: invalid character '{}'", ch)),
+            }
+        })
+    }
 }
 
-type Mask = Vec<MaskOp>;
-
-// parse a [`Mask`] from a str
-fn parse_mask(mask_str: &str) -> Mask {
-    let mask_len = mask_str.len();
-    mask_str
-        .split("")
-        .enumerate()
-        .filter_map(|(idx, op_str)| {
-            (!op_str.is_empty())
-                .then(|| {})
-                .and_then(|_| parse_op_str(op_str, mask_len - idx).ok())
-        })
-        .collect()
+/// A set of addresses: every address matching `value` on the bits outside of `floating`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    /// fixed bits. Always zero on the floating bits
+    value: u64,
+    /// bits that can be either zero or one
+    floating: u64,
 }
 
-// apply a bit mask to a number and return the result
-fn apply_mask(mask: &[MaskOp], value: usize) -> usize {
-    const ONE: usize = 1;
-    mask.iter().fold(value, |acc, op| match op {
-        MaskOp::Zero(offset) => acc & !(ONE << offset),
-        MaskOp::One(offset) => acc | (ONE << offset),
-    })
+impl Pattern {
+    /// Create a pattern of addresses matching `value` everywhere except the `floating` bits
+    pub fn new(value: u64, floating: u64) -> Self {
+        Self {
+            value: value & !floating,
+            floating,
+        }
+    }
+
+    /// Create a pattern matching a single address
+    pub fn exact(address: u64) -> Self {
+        Self::new(address, 0)
+    }
+
+    /// Return the number of addresses in the pattern. Wider than an address, since every bit may
+    /// float
+    pub fn len(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    /// A pattern always contains at least one address
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Iterate over the addresses in the pattern in ascending order
+    pub fn addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        // step through every subset of the floating bits
+        let mut subset = Some(0u64);
+        std::iter::from_fn(move || {
+            let current = subset?;
+            let next = current.wrapping_sub(self.floating) & self.floating;
+            subset = if next == 0 { None } else { Some(next) };
+            Some(self.value | current)
+        })
+    }
+
+    /// Return the addresses in both patterns, if there are any
+    pub fn intersect(&self, other: &Pattern) -> Option<Pattern> {
+        let fixed = !self.floating & !other.floating;
+        if (self.value ^ other.value) & fixed != 0 {
+            return None;
+        }
+
+        Some(Pattern::new(
+            self.value | other.value,
+            self.floating & other.floating,
+        ))
+    }
+
+    /// Return disjoint patterns covering the addresses in this pattern but not in `other`
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        let overlap = match self.intersect(other) {
+            Some(overlap) => overlap,
+            None => return vec![*self],
+        };
+
+        // fix each bit that floats here but not in the overlap, one at a time. Fixing it the
+        // opposite way to the overlap splits off a piece that can't overlap, fixing it the same
+        // way narrows the remainder towards the overlap itself
+        let mut pieces = vec![];
+        let mut remainder = *self;
+        let mut split_bits = self.floating & !overlap.floating;
+        while split_bits != 0 {
+            let bit = split_bits & split_bits.wrapping_neg();
+            split_bits &= !bit;
+
+            let floating = remainder.floating & !bit;
+            pieces.push(Pattern::new(
+                remainder.value | (!overlap.value & bit),
+                floating,
+            ));
+            remainder = Pattern::new(remainder.value | (overlap.value & bit), floating);
+        }
+
+        pieces
+    }
 }
 
-type MemOp = (usize, usize);
+/// Turns a write instruction into the addresses and value to store, according to the current mask
+pub trait Decoder {
+    /// Return the addresses written to, and the value written, by `mem[address] = value`
+    fn decode(&self, mask: &Mask, address: u64, value: u64) -> (Pattern, u64);
+}
 
-// parse a [memory operation](MemOp) from a str
-fn parse_mem_op(mem_str: &str) -> MemOp {
-    let (mem_idx, value) = mem_str
-        .split_once(" = ")
-        .expect("Failed to parse instruction");
-    let value = value.parse().expect("Failed to parse memory value");
-    let (_, mem_idx) = mem_idx.split_at(4);
-    let (mem_idx, _) = mem_idx.split_at(mem_idx.len() - 1);
-    let mem_idx = mem_idx.parse().expect("Failed to parse memory index");
+/// Version 1 of the decoder chip: the mask overwrites bits of the value, floating bits leave the
+/// value unchanged
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValueDecoder;
 
-    (mem_idx, value)
+impl Decoder for ValueDecoder {
+    fn decode(&self, mask: &Mask, address: u64, value: u64) -> (Pattern, u64) {
+        (Pattern::exact(address), (value | mask.ones) & !mask.zeros)
+    }
 }
 
 /// Initialization program instruction
-#[derive(Debug)]
-enum Instruction {
-    Memory(MemOp),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// update the bitmask
     Mask(Mask),
+    /// write a value to an address
+    Memory {
+        /// address written to
+        address: u64,
+        /// value written
+        value: u64,
+    },
 }
 
 impl FromStr for Instruction {
     type Err = String;
 
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let (instr_type, value) = line
-            .split_once(" = ")
-            .ok_or("Failed to parse instruction")?;
-        if instr_type.starts_with("mas") {
-            return Ok(Instruction::Mask(parse_mask(value)));
+        // a mask like `10XX1` lexes as a single word rather than a number and an identifier
+        let tokens = Lexer::new().with_alphanumeric_words(true).tokenize(line);
+        let to_error = |(label, err): (String, String)| {
+            format!(
+                "Failed to parse instruction '{}': expected {}, {}",
+                line, label, err
+            )
+        };
+
+        let (remaining, instruction) = match tokens.first() {
+            // `mem[8] = 11`
+            Some(token) if token.text == "mem" => p_keyword("mem")
+                .and_then(p_punct('['))
+                .and_then(p_number::<u64>())
+                .and_then(p_punct(']'))
+                .and_then(p_punct('='))
+                .and_then(p_number::<u64>())
+                .map(
+                    |(((((_mem, _), address), _), _), value)| Instruction::Memory {
+                        address,
+                        value,
+                    },
+                )
+                .parse(&tokens)
+                .map_err(to_error)?,
+            // `mask = X1XX0`
+            Some(token) if token.text == "mask" => {
+                let (remaining, (_, mask)) = p_keyword("mask")
+                    .and_then(p_punct('='))
+                    .and_then(p_kind(TokenKind::Ident).or_else(p_kind(TokenKind::Number)))
+                    .parse(&tokens)
+                    .map_err(to_error)?;
+                (remaining, Instruction::Mask(mask.text.parse()?))
+            }
+            _ => {
+                return Err(format!(
+                    "Failed to parse instruction '{}': expected mem or mask",
+                    line
+                ))
+            }
+        };
+
+        match remaining.first() {
+            None => Ok(instruction),
+            Some(token) => Err(format!("Unexpected {:?} after instruction", token)),
+        }
+    }
+}
+
+/// Docking program memory, holding each write as a pattern of addresses that no later write
+/// overlaps
+#[derive(Debug, Clone)]
+pub struct Memory<D> {
+    decoder: D,
+    mask: Mask,
+    /// disjoint address patterns and the value stored at each of their addresses
+    cells: Vec<(Pattern, u64)>,
+}
+
+impl<D: Decoder> Memory<D> {
+    /// Create empty memory using the provided decoder
+    pub fn new(decoder: D) -> Self {
+        Self {
+            decoder,
+            mask: Mask::default(),
+            cells: vec![],
         }
-        if instr_type.starts_with("mem") {
-            return Ok(Instruction::Memory(parse_mem_op(line)));
+    }
+
+    /// Execute a single instruction
+    pub fn execute(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Mask(mask) => self.mask = *mask,
+            Instruction::Memory { address, value } => {
+                let (pattern, value) = self.decoder.decode(&self.mask, *address, *value);
+                self.write(pattern, value);
+            }
         }
+    }
 
-        Err("Failed to parse instruction".to_string())
+    /// Store `value` at every address in `pattern`
+    pub fn write(&mut self, pattern: Pattern, value: u64) {
+        self.cells = self
+            .cells
+            .iter()
+            .flat_map(|(existing, existing_value)| {
+                existing
+                    .subtract(&pattern)
+                    .into_iter()
+                    .map(move |piece| (piece, *existing_value))
+            })
+            .collect();
+        // nothing to remember for zeros, since unwritten memory is zero too
+        if value != 0 {
+            self.cells.push((pattern, value));
+        }
     }
-}
 
-/// returns the sum of the values in memory after executing a the supplied initialization program
-pub fn one(file_path: &str) -> usize {
-    let serialized = read_file(file_path);
-    let mut mask: Mask = vec![];
+    /// Return the value stored at `address`
+    pub fn get(&self, address: u64) -> u64 {
+        let address = Pattern::exact(address);
+        self.cells
+            .iter()
+            .find(|(pattern, _)| pattern.intersect(&address).is_some())
+            .map_or(0, |(_, value)| *value)
+    }
+
+    /// Return the sum of every value in memory. The cells are disjoint, so this can't overflow
+    pub fn sum(&self) -> u128 {
+        self.cells
+            .iter()
+            .map(|(pattern, value)| pattern.len() * u128::from(*value))
+            .sum()
+    }
+}
 
+/// run an initialization program and return the sum of the values left in memory
+fn run<D: Decoder>(decoder: D, serialized: &str) -> u128 {
+    let mut memory = Memory::new(decoder);
     serialized
         .lines()
         .map(|line| line.parse().expect("Failed to parse instruction"))
-        // may not need all of the values in a contiguous array, so just map used indexes and values
-        .fold(HashMap::new(), |mut memory, instruction| {
-            match instruction {
-                Instruction::Mask(new_mask) => {
-                    mask = new_mask.to_vec();
-                }
-                Instruction::Memory(mem_op) => {
-                    memory.insert(mem_op.0, apply_mask(&mask, mem_op.1));
-                }
-            }
+        .for_each(|instruction| memory.execute(&instruction));
 
-            memory
-        })
-        .values()
-        .sum()
+    memory.sum()
+}
+
+/// returns the sum of the values in memory after executing a the supplied initialization program
+pub fn one(file_path: &str) -> u128 {
+    run(ValueDecoder, &read_file(file_path))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn parses_mask() {
+        let msg = "should split a mask into ones, zeros and floating bits";
+        let expected = Mask {
+            ones: 0b1000000,
+            zeros: 0b10,
+            floating: (1 << 36) - 1 - 0b1000010,
+        };
+        let actual = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X".parse();
+        assert_eq!(actual, Ok(expected), "{}", msg);
+
+        let msg = "should reject invalid masks";
+        assert!("XX".parse::<Mask>().is_err(), "{}", msg);
+        assert!("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX2X"
+            .parse::<Mask>()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_instructions() {
+        let msg = "should parse memory writes and masks";
+        let expected = Instruction::Memory {
+            address: 8,
+            value: 11,
+        };
+        assert_eq!("mem[8] = 11".parse(), Ok(expected), "{}", msg);
+        let mask = "10XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX1X";
+        let expected = Instruction::Mask(mask.parse().unwrap());
+        assert_eq!(format!("mask = {}", mask).parse(), Ok(expected), "{}", msg);
+
+        let msg = "should reject malformed instructions";
+        assert!("mem[8] = 11 12".parse::<Instruction>().is_err(), "{}", msg);
+        assert!("mem[x] = 11".parse::<Instruction>().is_err(), "{}", msg);
+        let split_mask = "mask = 10XXXXXXXXXXXXXXXX XXXXXXXXXXXXXXXX1X";
+        assert!(split_mask.parse::<Instruction>().is_err(), "{}", msg);
+        let expected = Err(
+            "Failed to parse instruction 'mem[x] = 11': expected number, Unexpected Ident \"x\" at 0:4"
+                .to_string(),
+        );
+        assert_eq!("mem[x] = 11".parse::<Instruction>(), expected, "{}", msg);
+    }
+
+    #[test]
+    fn subtracts_patterns() {
+        let msg = "should return the addresses not covered by the other pattern";
+        let pattern = Pattern::new(0b1000, 0b111);
+        let other = Pattern::new(0b1010, 0b001);
+        let pieces = pattern.subtract(&other);
+
+        let mut actual: Vec<_> = pieces.iter().flat_map(Pattern::addresses).collect();
+        actual.sort_unstable();
+        let expected = vec![0b1000, 0b1001, 0b1100, 0b1101, 0b1110, 0b1111];
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(pieces.iter().map(Pattern::len).sum::<u128>(), 6, "{}", msg);
+
+        let msg = "should leave patterns that don't overlap alone";
+        let other = Pattern::new(0b0000, 0b111);
+        assert_eq!(pattern.subtract(&other), vec![pattern], "{}", msg);
+    }
+
+    #[test]
+    fn many_floating_bits() {
+        let msg = "should sum memory without expanding floating addresses";
+        let mut memory = Memory::new(ValueDecoder);
+        memory.write(Pattern::new(0, (1 << 36) - 1), 1);
+        memory.write(Pattern::new(0, (1 << 35) - 1), 2);
+        memory.write(Pattern::exact(7), 0);
+        let expected = (1 << 35) + 2 * ((1 << 35) - 1);
+        assert_eq!(memory.sum(), expected, "{}", msg);
+        assert_eq!(memory.get(7), 0, "{}", msg);
+        assert_eq!(memory.get(1 << 35), 1, "{}", msg);
+
+        let msg = "should sum memory wider than an address";
+        let mut memory = Memory::new(ValueDecoder);
+        memory.write(Pattern::new(0, u64::MAX), u64::MAX);
+        let expected = (1 << 64) * u128::from(u64::MAX);
+        assert_eq!(memory.sum(), expected, "{}", msg);
+        let mut memory = Memory::new(ValueDecoder);
+        memory.write(Pattern::new(0, (1 << 36) - 1), (1 << 30) - 1);
+        memory.write(Pattern::exact(1 << 40), u64::MAX);
+        let expected = (1 << 36) * ((1 << 30) - 1) + u128::from(u64::MAX);
+        assert_eq!(memory.sum(), expected, "{}", msg);
+    }
+
     #[test]
     fn part_one() {
         let msg = "should sum the values in memory";
@@ -5,31 +5,138 @@ use std::collections::HashMap;
 use crate::day_1::read_file;
 use crate::day_13::parse_csv_lossy;
 
-fn step(initial: Vec<u32>, steps: u32) -> u32 {
-    let mut map: HashMap<u32, (u32, u32)> = HashMap::new();
-    let mut last: u32 = 0;
-    initial.iter().enumerate().for_each(|(idx, num)| {
-        let turn = idx as u32 + 1;
-        map.insert(*num, (turn, turn));
-        last = *num;
-    });
-
-    let next = initial.len() as u32 + 1;
-    let mut next_num = 0;
-    for step in next..=steps {
-        let previous_turns = *map.get(&last).unwrap_or(&(step, step));
-        // next number to speak is the difference between the turn number when it was last spoken
-        // and the turn number of the time it was most recently spoken before then
-        next_num = previous_turns.1 - previous_turns.0;
-
-        // update entry for this turn's number
-        let prev = map.get(&next_num).copied().unwrap_or((step, step));
-        map.insert(next_num, (prev.1, step));
-
-        last = next_num;
+/// The elves' memory game. Iterating yields the number spoken on each turn, starting with turn 1.
+///
+/// After the starting numbers, each turn considers the most recently spoken number: if it had
+/// never been spoken before the next number is 0, otherwise it is the number of turns since it was
+/// previously spoken.
+#[derive(Debug, Clone)]
+pub struct MemoryGame {
+    start: Vec<u32>,
+    /// number of turns played
+    turn: u32,
+    /// most recently spoken number
+    last: u32,
+    /// turn each number was spoken, not counting the most recent turn. 0 if never spoken
+    last_seen: Vec<u32>,
+    /// turn each starting number too large for `last_seen` was spoken, not counting the most
+    /// recent turn
+    start_overflow: HashMap<u32, u32>,
+}
+
+impl MemoryGame {
+    /// Create a game with the provided starting numbers
+    pub fn new(start: Vec<u32>) -> Self {
+        Self::with_capacity(start, 0)
+    }
+
+    /// Create a game with room to play `turns` turns without reallocating
+    pub fn with_capacity(start: Vec<u32>, turns: u32) -> Self {
+        // a number spoken after the starting numbers is always less than the turn it's spoken on
+        Self {
+            start,
+            turn: 0,
+            last: 0,
+            last_seen: vec![0; turns as usize],
+            start_overflow: HashMap::new(),
+        }
+    }
+
+    /// Return the number of turns played so far
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Return the turn `number` was last spoken on, if it has been spoken
+    pub fn last_spoken(&self, number: u32) -> Option<u32> {
+        if self.turn > 0 && number == self.last {
+            return Some(self.turn);
+        }
+        self.previous_turn(number)
     }
 
-    next_num
+    /// Play until turn `turn` and return the number spoken on it, or None if that turn has already
+    /// been played
+    pub fn seek(&mut self, turn: u32) -> Option<u32> {
+        if turn <= self.turn {
+            return None;
+        }
+        let remaining = (turn - self.turn) as usize;
+        self.nth(remaining - 1)
+    }
+
+    /// Play until `number` is spoken when it had already been spoken before, within `limit` turns,
+    /// and return the turn it was repeated on
+    pub fn first_repeat(&mut self, number: u32, limit: u32) -> Option<u32> {
+        while self.turn < limit {
+            // the previous turn is only overwritten once the next turn is played
+            if self.next()? == number && self.previous_turn(number).is_some() {
+                return Some(self.turn);
+            }
+        }
+
+        None
+    }
+
+    /// return the turn `number` was spoken on before the most recent turn
+    fn previous_turn(&self, number: u32) -> Option<u32> {
+        match self.last_seen.get(number as usize) {
+            Some(turn) if *turn != 0 => Some(*turn),
+            // skip hashing on the common path
+            _ if self.start_overflow.is_empty() => None,
+            _ => self.start_overflow.get(&number).copied(),
+        }
+    }
+
+    /// record that `number` was spoken on `turn`
+    fn remember(&mut self, number: u32, turn: u32) {
+        let idx = number as usize;
+        if idx >= self.last_seen.len() {
+            // only starting numbers can be spoken on a turn at or below their value
+            if idx >= turn as usize {
+                self.start_overflow.insert(number, turn);
+                return;
+            }
+            let len = (idx + 1).max(self.last_seen.len() * 2);
+            self.last_seen.resize(len, 0);
+        }
+
+        self.last_seen[idx] = turn;
+    }
+}
+
+impl Iterator for MemoryGame {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let turn = self.turn;
+        if turn == u32::MAX {
+            return None;
+        }
+
+        let spoken = match self.start.get(turn as usize) {
+            Some(number) => *number,
+            None => match self.previous_turn(self.last) {
+                Some(previous) => turn - previous,
+                None => 0,
+            },
+        };
+        // the last number moves out of the most recent turn
+        if turn > 0 {
+            self.remember(self.last, turn);
+        }
+
+        self.last = spoken;
+        self.turn += 1;
+        Some(spoken)
+    }
+}
+
+/// return the number spoken on turn `steps`
+fn step(initial: Vec<u32>, steps: u32) -> u32 {
+    MemoryGame::with_capacity(initial, steps)
+        .seek(steps)
+        .expect("Game has no turns left")
 }
 
 /// return the 2020th number spoken
@@ -84,6 +191,57 @@ mod test {
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    #[test]
+    fn spoken_numbers() {
+        let msg = "should yield the spoken numbers in order";
+        let expected = vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0];
+        let actual: Vec<_> = MemoryGame::new(vec![0, 3, 6]).take(10).collect();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should accept starting numbers of any length";
+        let expected = vec![5, 0, 0, 1, 0, 2, 0, 2, 2, 1];
+        let actual: Vec<_> = MemoryGame::new(vec![5]).take(10).collect();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = vec![0, 0, 1, 0, 2, 0, 2, 2, 1, 6];
+        let actual: Vec<_> = MemoryGame::new(vec![]).take(10).collect();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn seeks() {
+        let msg = "should play until the requested turn";
+        let mut game = MemoryGame::new(vec![0, 3, 6]);
+        assert_eq!(game.seek(4), Some(0), "{}", msg);
+        assert_eq!(game.seek(9), Some(4), "{}", msg);
+        assert_eq!(game.turn(), 9, "{}", msg);
+        assert_eq!(game.seek(9), None, "{}", msg);
+
+        let msg = "should handle starting numbers larger than the turn count";
+        let mut game = MemoryGame::new(vec![100_000, 7]);
+        let expected = vec![0, 0, 1, 0, 2];
+        let actual: Vec<_> = game.by_ref().skip(2).take(5).collect();
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(game.last_spoken(100_000), Some(1), "{}", msg);
+
+        let msg = "should not allocate for every number up to a huge starting number";
+        let mut game = MemoryGame::new(vec![3_000_000_000, 3_000_000_000]);
+        let expected = vec![3_000_000_000, 3_000_000_000, 1, 0, 0, 1];
+        let actual: Vec<_> = game.by_ref().take(6).collect();
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(game.last_spoken(3_000_000_000), Some(2), "{}", msg);
+        assert_eq!(game.last_spoken(2_999_999_999), None, "{}", msg);
+    }
+
+    #[test]
+    fn first_repeat() {
+        let msg = "should return the turn a number is first repeated";
+        let mut game = MemoryGame::new(vec![0, 3, 6]);
+        assert_eq!(game.first_repeat(3, 2020), Some(5), "{}", msg);
+        assert_eq!(game.first_repeat(4, 2020), Some(24), "{}", msg);
+        assert_eq!(game.first_repeat(6, 10), None, "{}", msg);
+    }
+
     #[test]
     #[ignore]
     fn part_two() {
@@ -1,105 +1,144 @@
 //! Solutions to 2020 day 16
 //! --- Day 16: Ticket Translation ---
 use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use parser::three::lib::{
+    eof, keep_first, keep_second, many, one_or_more_chars, p_char, p_string, satisfy, ParseErr,
+    Parser,
+};
 
 use crate::day_1::read_file;
-use crate::day_13::parse_csv_lossy;
 
-pub use two::two;
 mod two;
+mod validator;
+pub use two::two;
+pub use validator::{ColumnMapping, Step, TicketReport, TicketValidator, ValueReport};
 
-type Ticket = Vec<u32>;
+/// the values on a ticket, in column order
+pub type Ticket = Vec<u32>;
 
-/// parse a set of comma-separated values into a list of Tickets
-fn parse_tickets(input: &str) -> Vec<Ticket> {
-    input.lines().skip(1).map(parse_csv_lossy).collect()
+/// parse a ticket from comma-separated values
+fn parse_ticket(line: &str) -> Result<Ticket, String> {
+    line.split(',')
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Failed to parse ticket value '{}'", value))
+        })
+        .collect()
 }
 
-type Field = (String, Vec<RangeInclusive<u32>>);
-
-/// parse list of fields that exist somewhere on the ticket
+/// A ticket field, and the ranges of values valid for it
 //
 // rules for ticket fields specify a list of fields that exist somewhere on the ticket and the valid
 // ranges of values for each field.
-fn parse_fields(input: &str) -> Vec<Field> {
-    input
-        .lines()
-        .map(|line| {
-            let (name, ranges) = line
-                .split_once(":")
-                .unwrap_or_else(|| panic!("Failed to parse range '{}'", line));
-            let ranges = ranges
-                .split(" or ")
-                .map(|range| {
-                    let (min, max) = range
-                        .trim()
-                        .split_once("-")
-                        .unwrap_or_else(|| panic!("Failed to parse range '{}'", line));
-                    let min = min.parse::<u32>().unwrap();
-                    let max = max.parse::<u32>().unwrap();
-
-                    min..=max
-                })
-                .collect::<Vec<_>>();
-            (name.to_string(), ranges)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// field name
+    pub name: String,
+    /// valid ranges of values
+    pub ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl Rule {
+    /// true if `value` is in one of the rule's ranges
+    pub fn matches(&self, value: u32) -> bool {
+        self.ranges.iter().any(|range| range.contains(&value))
+    }
+}
+
+/// parse a rule like `class: 1-3 or 5-7`
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (name, ranges) = line
+            .split_once(":")
+            .ok_or_else(|| format!("Failed to parse rule '{}'", line))?;
+        let ranges = ranges
+            .split(" or ")
+            .map(|range| {
+                let (min, max) = range
+                    .trim()
+                    .split_once("-")
+                    .ok_or_else(|| format!("Failed to parse range '{}'", range.trim()))?;
+                let bound = |bound: &str| {
+                    bound
+                        .parse::<u32>()
+                        .map_err(|_| format!("Failed to parse bound '{}'", bound))
+                };
+
+                Ok(bound(min)?..=bound(max)?)
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            ranges,
         })
-        .collect()
+    }
 }
 
-struct TicketInfo {
+/// The notes taken about the tickets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicketInfo {
     /// rules for ticket fields
-    fields: Vec<Field>,
+    pub fields: Vec<Rule>,
     /// the numbers on your ticket
-    own_ticket: Ticket,
+    pub own_ticket: Ticket,
     /// the numbers on other nearby tickets
-    nearby_tickets: Vec<Ticket>,
+    pub nearby_tickets: Vec<Ticket>,
 }
 
-fn parse_input(input: &str) -> TicketInfo {
-    let parts: Vec<_> = input.split("\n\n").collect();
-    let fields = parse_fields(parts[0]);
-    let own_ticket = parse_tickets(parts[1]).first().unwrap().to_vec();
-    let nearby_tickets = parse_tickets(parts[2]);
+/// parse a whole line with `parse`. Its errors point at the start of the line
+fn p_line<'a, T: 'a>(parse: impl Fn(&str) -> Result<T, String> + 'a, label: &str) -> Parser<'a, T> {
+    let line = one_or_more_chars(satisfy(|ch| ch != '\n', label.to_string()));
+    keep_first(line.try_map(move |line| parse(&line)), p_char('\n')).with_label(label.to_string())
+}
+
+/// Parse the rules, your ticket and the nearby tickets, in that order
+pub fn parse_notes(input: &str) -> Result<TicketInfo, ParseErr<'_>> {
+    let rule = || p_line(|line| line.parse::<Rule>(), "rule");
+    let ticket = || p_line(parse_ticket, "ticket");
 
-    TicketInfo {
+    // a line that fails to parse ends the list before it, so parse it again for the error
+    let rules = keep_first(many(rule()), p_char('\n').or_else(rule().map(|_| '\n')));
+    let own_ticket = keep_first(
+        keep_second(p_string("your ticket:\n"), ticket()),
+        p_char('\n'),
+    );
+    let nearby_tickets = keep_first(
+        keep_second(p_string("nearby tickets:\n"), many(ticket())),
+        keep_second(many(p_char('\n')), eof().or_else(ticket().map(|_| ()))),
+    );
+
+    let (_, ((fields, own_ticket), nearby_tickets)) = rules
+        .and_then(own_ticket)
+        .and_then(nearby_tickets)
+        .parse(input)?;
+
+    Ok(TicketInfo {
         fields,
         own_ticket,
         nearby_tickets,
-    }
+    })
 }
 
-/// return the invalid values (values that are not in any of the specified ranges) in a list of
-/// tickets
-fn get_nearby_errors(fields: &[RangeInclusive<u32>], tickets: &[Ticket]) -> Vec<u32> {
-    tickets
-        .iter()
-        .flat_map(|ticket| {
-            ticket.iter().filter_map(|value| {
-                if fields.iter().any(|range| range.contains(value)) {
-                    return None;
-                }
-                Some(*value)
-            })
-        })
-        .collect()
+fn parse_input(input: &str) -> TicketInfo {
+    parse_notes(input).unwrap_or_else(|err| panic!("{}", err))
 }
 
 /// return the sum of the invalid values on nearby tickets, ignoring your own
 pub fn one(file_path: &str) -> u32 {
     let contents = read_file(file_path);
-    // parse file
     let input = parse_input(&contents);
-    let fields = input
-        .fields
-        .iter()
-        // discard unused data
-        .flat_map(|(_name, ranges)| ranges)
-        .cloned()
-        .collect::<Vec<_>>();
+    let validator = TicketValidator::new(input.fields);
 
-    // error check nearby tickets
-    get_nearby_errors(&fields, &input.nearby_tickets)
+    input
+        .nearby_tickets
         .iter()
+        .flat_map(|ticket| validator.check(ticket).invalid_values())
         .sum()
 }
 
@@ -107,6 +146,53 @@ pub fn one(file_path: &str) -> u32 {
 mod test {
     use super::*;
 
+    #[test]
+    fn parses_rules() {
+        let msg = "should parse a rule";
+        let rule: Rule = "class: 1-3 or 5-7".parse().unwrap();
+        assert_eq!(rule.name, "class", "{}", msg);
+        assert_eq!(rule.ranges, vec![1..=3, 5..=7], "{}", msg);
+        assert!(rule.matches(6) && !rule.matches(4), "{}", msg);
+
+        let msg = "should report bad rules";
+        let expected = Err("Failed to parse bound 'x'".to_string());
+        assert_eq!("class: 1-x".parse::<Rule>(), expected, "{}", msg);
+    }
+
+    #[test]
+    fn parses_notes() {
+        let msg = "should parse rules and tickets";
+        let notes = parse_notes(&read_file("input/16-t.txt")).unwrap();
+        assert_eq!(notes.fields.len(), 3, "{}", msg);
+        assert_eq!(notes.own_ticket, vec![7, 1, 14], "{}", msg);
+        assert_eq!(notes.nearby_tickets.len(), 4, "{}", msg);
+
+        let msg = "should point at the line that fails to parse";
+        let input = "class: 1-3\n\nyour ticket:\n7\n\nnearby tickets:\n7\n4,x\n";
+        let expected = "error: Error parsing ticket
+ --> 16-1.txt:8:1
+  |
+8 | 4,x
+  | ^ Failed to parse ticket value 'x'
+";
+        let actual = parse_notes(input)
+            .unwrap_err()
+            .to_diagnostic()
+            .with_file_name("16-1.txt")
+            .render(input);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let input = "class: 1-3\nrow: 1-x\n\nyour ticket:\n7\n";
+        let err = parse_notes(input).unwrap_err().to_diagnostic();
+        assert_eq!(err.message(), "Error parsing rule", "{}", msg);
+        assert_eq!(
+            err.labels()[0].message,
+            "Failed to parse bound 'x'",
+            "{}",
+            msg
+        );
+    }
+
     #[test]
     fn part_one() {
         let msg =
@@ -0,0 +1,426 @@
+//! Ticket validation
+//!
+//! A [`TicketValidator`] checks tickets against a set of field rules, reporting the rules each value
+//! could satisfy, and works out which column holds each field along with the reasoning behind it.
+//!
+//! ```
+//! use advent_2020::day_16::{Rule, TicketValidator};
+//!
+//! let rules = ["class: 0-1 or 4-19", "row: 0-5 or 8-19", "seat: 0-13 or 16-19"];
+//! let validator = TicketValidator::new(rules.iter().map(|rule| rule.parse().unwrap()).collect());
+//!
+//! let report = validator.check(&[3, 20, 18]);
+//! assert_eq!(report.invalid_values(), vec![20]);
+//! assert_eq!(report.to_string(), "column 0: 3 matches row, seat\ncolumn 1: 20 matches no rule\ncolumn 2: 18 matches class, row, seat\n");
+//!
+//! let mapping = validator.map_columns(&[vec![3, 9, 18], vec![15, 1, 5], vec![5, 14, 9]]).unwrap();
+//! assert_eq!(mapping.column("seat"), Some(2));
+//! ```
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use super::{Rule, Ticket};
+use crate::assignment::{explain, AssignmentError, Deduction};
+
+/// The rules a single ticket value could satisfy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueReport<'a> {
+    /// column of the value on the ticket
+    pub column: usize,
+    /// the value
+    pub value: u32,
+    /// names of the rules the value is valid for
+    pub rules: Vec<&'a str>,
+}
+
+impl ValueReport<'_> {
+    /// true if the value satisfies at least one rule
+    pub fn is_valid(&self) -> bool {
+        !self.rules.is_empty()
+    }
+}
+
+impl fmt::Display for ValueReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {} matches ", self.column, self.value)?;
+        if self.is_valid() {
+            write!(f, "{}", self.rules.join(", "))
+        } else {
+            write!(f, "no rule")
+        }
+    }
+}
+
+/// The outcome of checking every value on a ticket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicketReport<'a> {
+    /// a report for each value, in column order
+    pub values: Vec<ValueReport<'a>>,
+    /// number of values a ticket should have, one per rule
+    pub fields: usize,
+}
+
+impl TicketReport<'_> {
+    /// true if the ticket has one value per rule, and every value satisfies at least one rule
+    pub fn is_valid(&self) -> bool {
+        self.has_one_value_per_rule() && self.values.iter().all(ValueReport::is_valid)
+    }
+
+    /// true if the ticket has exactly one value per rule
+    pub fn has_one_value_per_rule(&self) -> bool {
+        self.values.len() == self.fields
+    }
+
+    /// Return the values that don't satisfy any rule
+    pub fn invalid_values(&self) -> Vec<u32> {
+        self.values
+            .iter()
+            .filter(|report| !report.is_valid())
+            .map(|report| report.value)
+            .collect()
+    }
+}
+
+/// One report per line, then the number of values if it's wrong
+impl fmt::Display for TicketReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for report in &self.values {
+            writeln!(f, "{}", report)?;
+        }
+        if !self.has_one_value_per_rule() {
+            writeln!(
+                f,
+                "expected {} values, got {}",
+                self.fields,
+                self.values.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A step in working out which column holds each field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// the ticket isn't used, because it doesn't have one value per rule
+    WrongLength {
+        /// index of the ticket among those checked
+        ticket: usize,
+        /// number of values on the ticket
+        values: usize,
+        /// number of rules
+        expected: usize,
+    },
+    /// the column can't hold the rule's field, because a valid ticket's value there breaks it
+    Excluded {
+        /// ticket column
+        column: usize,
+        /// rule name
+        rule: String,
+        /// index of the ticket among those checked
+        ticket: usize,
+        /// the ticket's value in the column
+        value: u32,
+    },
+    /// the rule is the only one left for the column
+    Forced {
+        /// ticket column
+        column: usize,
+        /// rule name
+        rule: String,
+    },
+    /// the rule is the column's only match that leaves every other column a rule
+    Matched {
+        /// ticket column
+        column: usize,
+        /// rule name
+        rule: String,
+    },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::WrongLength {
+                ticket,
+                values,
+                expected,
+            } => write!(
+                f,
+                "ticket {} is ignored: it has {} values, expected {}",
+                ticket, values, expected
+            ),
+            Step::Excluded {
+                column,
+                rule,
+                ticket,
+                value,
+            } => write!(
+                f,
+                "column {} isn't {}: ticket {} has {}",
+                column, rule, ticket, value
+            ),
+            Step::Forced { column, rule } => {
+                write!(f, "column {} is {}: no other rule is left", column, rule)
+            }
+            Step::Matched { column, rule } => write!(
+                f,
+                "column {} is {}: every other column still gets a rule",
+                column, rule
+            ),
+        }
+    }
+}
+
+/// The column holding each field, and how it was worked out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    columns: BTreeMap<String, usize>,
+    steps: Vec<Step>,
+}
+
+impl ColumnMapping {
+    /// Return the column holding the field named `name`
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.get(name).copied()
+    }
+
+    /// Return the column holding each field, by field name
+    pub fn columns(&self) -> &BTreeMap<String, usize> {
+        &self.columns
+    }
+
+    /// Return the reasoning, with every exclusion before the columns it settles
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+}
+
+/// The reasoning, one step per line
+impl fmt::Display for ColumnMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks tickets against a set of field rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicketValidator {
+    rules: Vec<Rule>,
+}
+
+impl TicketValidator {
+    /// Create a validator for tickets with one field per rule
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Return the field rules
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Return the names of the rules `value` is valid for
+    pub fn matching_rules(&self, value: u32) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(value))
+            .map(|rule| rule.name.as_str())
+            .collect()
+    }
+
+    /// Report the rules each value on `ticket` could satisfy
+    pub fn check(&self, ticket: &[u32]) -> TicketReport<'_> {
+        TicketReport {
+            values: ticket
+                .iter()
+                .enumerate()
+                .map(|(column, value)| ValueReport {
+                    column,
+                    value: *value,
+                    rules: self.matching_rules(*value),
+                })
+                .collect(),
+            fields: self.rules.len(),
+        }
+    }
+
+    /// Work out which column holds each field. Only valid tickets are used, and each must fit the
+    /// rule for every column. Tickets without one value per rule are noted in the steps
+    pub fn map_columns(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<ColumnMapping, AssignmentError<usize, String>> {
+        let mut steps = Vec::new();
+        let mut candidates: HashMap<usize, HashSet<String>> = (0..self.rules.len())
+            .map(|column| {
+                let names = self.rules.iter().map(|rule| rule.name.clone()).collect();
+                (column, names)
+            })
+            .collect();
+
+        for (idx, ticket) in tickets.iter().enumerate() {
+            let report = self.check(ticket);
+            if !report.has_one_value_per_rule() {
+                steps.push(Step::WrongLength {
+                    ticket: idx,
+                    values: ticket.len(),
+                    expected: self.rules.len(),
+                });
+                continue;
+            }
+            if !report.is_valid() {
+                continue;
+            }
+            for (column, value) in ticket.iter().enumerate() {
+                for rule in self.rules.iter().filter(|rule| !rule.matches(*value)) {
+                    if candidates.entry(column).or_default().remove(&rule.name) {
+                        steps.push(Step::Excluded {
+                            column,
+                            rule: rule.name.clone(),
+                            ticket: idx,
+                            value: *value,
+                        });
+                    }
+                }
+            }
+        }
+
+        let (columns, deductions) = explain(candidates)?;
+        steps.extend(deductions.into_iter().map(|deduction| match deduction {
+            Deduction::Forced(column, rule) => Step::Forced { column, rule },
+            Deduction::Matched(column, rule) => Step::Matched { column, rule },
+        }));
+
+        Ok(ColumnMapping {
+            columns: columns
+                .into_iter()
+                .map(|(column, name)| (name, column))
+                .collect(),
+            steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day_1::read_file;
+    use crate::day_16::parse_input;
+
+    fn example(file_path: &str) -> (TicketValidator, Vec<Ticket>) {
+        let contents = read_file(file_path);
+        let input = parse_input(&contents);
+
+        (TicketValidator::new(input.fields), input.nearby_tickets)
+    }
+
+    #[test]
+    fn checks_tickets() {
+        let msg = "should report the invalid values on each ticket";
+        let (validator, tickets) = example("input/16-t.txt");
+        let invalid: Vec<_> = tickets
+            .iter()
+            .map(|ticket| validator.check(ticket).invalid_values())
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![vec![], vec![4], vec![55], vec![12]],
+            "{}",
+            msg
+        );
+
+        let msg = "should report the rules each value could satisfy";
+        let report = validator.check(&tickets[0]);
+        assert!(report.is_valid(), "{}", msg);
+        assert_eq!(report.values[0].rules, vec!["class", "row"], "{}", msg);
+        assert_eq!(report.values[1].rules, vec!["class"], "{}", msg);
+        assert_eq!(report.values[2].rules, vec!["seat"], "{}", msg);
+
+        let msg = "should report tickets without one value per rule";
+        let report = validator.check(&[7, 3]);
+        assert!(!report.is_valid(), "{}", msg);
+        assert_eq!(report.invalid_values(), vec![], "{}", msg);
+        assert_eq!(
+            report.to_string().lines().last(),
+            Some("expected 3 values, got 2"),
+            "{}",
+            msg
+        );
+        assert!(!validator.check(&[7, 3, 47, 1]).is_valid(), "{}", msg);
+    }
+
+    #[test]
+    fn maps_columns() {
+        let msg = "should map each field to its column";
+        let (validator, tickets) = example("input/16-t2.txt");
+        let mapping = validator.map_columns(&tickets).unwrap();
+        let expected: BTreeMap<_, _> = vec![("class", 1), ("row", 0), ("seat", 2)]
+            .into_iter()
+            .map(|(name, column)| (name.to_string(), column))
+            .collect();
+        assert_eq!(mapping.columns(), &expected, "{}", msg);
+
+        let msg = "should explain the mapping";
+        let excluded = Step::Excluded {
+            column: 0,
+            rule: "class".to_string(),
+            ticket: 0,
+            value: 3,
+        };
+        assert_eq!(mapping.steps()[0], excluded, "{}", msg);
+        assert_eq!(
+            excluded.to_string(),
+            "column 0 isn't class: ticket 0 has 3",
+            "{}",
+            msg
+        );
+        let forced = Step::Forced {
+            column: 2,
+            rule: "seat".to_string(),
+        };
+        assert_eq!(mapping.steps().last(), Some(&forced), "{}", msg);
+        assert_eq!(mapping.to_string().lines().count(), 6, "{}", msg);
+
+        let msg = "should ignore invalid tickets";
+        let mut with_invalid = tickets.clone();
+        with_invalid.push(vec![20, 20, 20]);
+        assert_eq!(
+            validator.map_columns(&with_invalid).as_ref(),
+            Ok(&mapping),
+            "{}",
+            msg
+        );
+
+        let msg = "should note tickets without one value per rule";
+        let mut with_short = tickets.clone();
+        with_short.push(vec![3, 9]);
+        let actual = validator.map_columns(&with_short).unwrap();
+        assert_eq!(actual.columns(), mapping.columns(), "{}", msg);
+        let wrong_length = Step::WrongLength {
+            ticket: 3,
+            values: 2,
+            expected: 3,
+        };
+        assert!(actual.steps().contains(&wrong_length), "{}", msg);
+        assert_eq!(
+            wrong_length.to_string(),
+            "ticket 3 is ignored: it has 2 values, expected 3",
+            "{}",
+            msg
+        );
+
+        let msg = "should report columns that can't be told apart";
+        let actual = validator.map_columns(&tickets[..1]);
+        assert!(
+            matches!(actual, Err(AssignmentError::Ambiguous(_))),
+            "{}",
+            msg
+        );
+    }
+}
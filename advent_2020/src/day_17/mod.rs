@@ -1,148 +1,233 @@
 //! Solutions to 2020 day 17
 //! --- Day 17: Conway Cubes ---
-use std::collections::HashSet;
-
+//!
+//! The pocket dimension can have any number of dimensions `N` >= 2. The starting slice lies flat
+//! in the first two, so the state stays symmetric in the rest: flipping the sign of an extra axis
+//! or swapping two extra axes gives the same set of active cubes. Only one [`Point`] from each
+//! group of symmetric points is stored, which keeps 5, 6 and more dimensions fast.
+//!
+//! ```
+//! use advent_2020::day_17::PocketDimension;
+//!
+//! let mut pocket: PocketDimension<5> = ".#.\n..#\n###".parse().unwrap();
+//! pocket.run(1);
+//! assert_eq!(pocket.active_count(), 83);
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::automaton::{Lattice, Topology};
 use crate::day_1::read_file;
 
 mod two;
 pub use two::two;
 
-type Triple = (isize, isize, isize);
-
-/// map a line of serialized conway cube states to the set of active coordinates
-fn to_active(enumerated_line: (usize, &str)) -> HashSet<Triple> {
-    let (y, line) = enumerated_line;
-    line.chars()
-        .enumerate()
-        .fold(HashSet::new(), |mut acc, (x, character)| match character {
-            '#' => {
-                acc.insert((x as isize, y as isize, 0));
-                acc
-            }
-            _ => acc,
-        })
-}
+/// A cube position in N dimensional space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point<const N: usize>(pub [isize; N]);
+
+impl<const N: usize> Point<N> {
+    /// Return the symmetric point used to represent this one: the extra axes as absolute values
+    /// in ascending order
+    pub fn canonical(self) -> Self {
+        let mut coordinates = self.0;
+        if N > 2 {
+            let extra = &mut coordinates[2..];
+            extra
+                .iter_mut()
+                .for_each(|coordinate| *coordinate = coordinate.abs());
+            extra.sort_unstable();
+        }
+
+        Self(coordinates)
+    }
 
-/// parse initial conway cube state from string
-fn parse_state<T, F>(into: F, serialized: &str) -> HashSet<T>
-where
-    F: FnMut((usize, &str)) -> HashSet<T>,
-    T: Eq + std::hash::Hash + Clone,
-{
-    serialized
-        .lines()
-        .enumerate()
-        .map(into)
-        .reduce(|acc, next| &acc | &next)
-        .unwrap()
+    /// Return the number of distinct points symmetric to this canonical point, including itself
+    pub fn weight(&self) -> usize {
+        if N <= 2 {
+            return 1;
+        }
+        let extra = &self.0[2..];
+
+        // every non zero coordinate can be flipped
+        let flips = 1 << extra.iter().filter(|coordinate| **coordinate != 0).count();
+
+        // distinct orderings of the extra coordinates: n! / (repeats_1! * repeats_2! * ...)
+        let factorial = |n: usize| (1..=n).product::<usize>();
+        let repeats =
+            extra
+                .windows(2)
+                .fold((1, 1), |(product, run), pair| match pair[0] == pair[1] {
+                    true => (product, run + 1),
+                    false => (product * factorial(run), 1),
+                });
+        let orderings = factorial(extra.len()) / (repeats.0 * factorial(repeats.1));
+
+        flips * orderings
+    }
+
+    /// Return the 3^N - 1 points touching this one
+    pub fn neighbors(&self) -> impl Iterator<Item = Self> {
+        Lattice::<N>.neighbors(self.0).into_iter().map(Point)
+    }
 }
 
-/// Returns a Some with the count of neighbors, if count is < max. Otherwise returns None.
-fn count_neigbors(state: &HashSet<Triple>, cube: &Triple, max: usize) -> Option<usize> {
-    let mut count = 0;
-    for x in (cube.0 - 1)..=(cube.0 + 1) {
-        for y in (cube.1 - 1)..=(cube.1 + 1) {
-            for z in (cube.2 - 1)..=(cube.2 + 1) {
-                let current_cube = (x, y, z);
-
-                // skip the central cube
-                if current_cube == *cube {
-                    continue;
-                }
+/// The active cubes of an N dimensional pocket dimension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PocketDimension<const N: usize> {
+    /// canonical active points
+    active: HashSet<Point<N>>,
+    cycles: usize,
+}
 
-                if state.contains(&current_cube) {
-                    count += 1;
-                    // exit early if we've exceeded the neighbor limit
-                    if count > max {
-                        return None;
-                    }
-                }
+impl<const N: usize> PocketDimension<N> {
+    /// Run a single cycle according to the following rules:
+    ///
+    /// - If a cube is active and exactly 2 or 3 of its neighbors are also active,
+    ///   the cube remains active.
+    ///
+    ///   Otherwise, the cube becomes inactive.
+    /// - If a cube is inactive but exactly 3 of its neighbors are active,
+    ///   the cube becomes active.
+    ///
+    ///   Otherwise, the cube remains inactive.
+    pub fn cycle(&mut self) {
+        // Each active point stands for `weight` symmetric points. Adding that weight to the
+        // canonical form of each neighbor counts every active neighbor of every point symmetric
+        // to the neighbor, so dividing by the neighbor's weight gives the count for one of them.
+        let mut counts: HashMap<Point<N>, usize> = HashMap::new();
+        for point in &self.active {
+            let weight = point.weight();
+            for neighbor in point.neighbors() {
+                *counts.entry(neighbor.canonical()).or_insert(0) += weight;
             }
         }
+
+        self.active = counts
+            .into_iter()
+            .filter(|(point, count)| {
+                let active = self.active.contains(point);
+                matches!((active, count / point.weight()), (true, 2) | (_, 3))
+            })
+            .map(|(point, _)| point)
+            .collect();
+        self.cycles += 1;
+    }
+
+    /// Run the provided number of cycles
+    pub fn run(&mut self, cycles: usize) {
+        for _ in 0..cycles {
+            self.cycle();
+        }
     }
 
-    Some(count)
+    /// Return the number of cycles run so far
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    /// Return the number of active cubes
+    pub fn active_count(&self) -> usize {
+        self.active.iter().map(Point::weight).sum()
+    }
+
+    /// true if the cube at `point` is active
+    pub fn is_active(&self, point: Point<N>) -> bool {
+        self.active.contains(&point.canonical())
+    }
 }
 
-/// Returns the next set of active conway cubes according to the following rules:
-///
-/// - If a cube is active and exactly 2 or 3 of its neighbors are also active,
-///   the cube remains active.
-///
-///   Otherwise, the cube becomes inactive.
-/// - If a cube is inactive but exactly 3 of its neighbors are active,
-///   the cube becomes active.
-///
-///   Otherwise, the cube remains inactive.
-fn next_state(state: &HashSet<Triple>) -> HashSet<Triple> {
-    const MAX_NEIGHBORS: usize = 3;
-    let mut next = HashSet::new();
-    for active in state {
-        // check all 27 (3^3, for 3 dimensions) positions
-        for x in (active.0 - 1)..=(active.0 + 1) {
-            for y in (active.1 - 1)..=(active.1 + 1) {
-                for z in (active.2 - 1)..=(active.2 + 1) {
-                    let current_cube = (x, y, z);
-
-                    if let Some(count) = count_neigbors(state, &current_cube, MAX_NEIGHBORS) {
-                        if current_cube == *active && (2..3).contains(&count) {
-                            // stays active if exactly 2 or 3 of its neighbors are also active
-                            next.insert(*active);
-                        } else if count == 3 {
-                            // becomes active if exactly 3 of its neighbors are active
-                            next.insert(current_cube);
-                        }
+impl<const N: usize> FromStr for PocketDimension<N> {
+    type Err = String;
+
+    /// Parse the starting slice, with '#' for active cubes and '.' for inactive ones
+    fn from_str(slice: &str) -> Result<Self, Self::Err> {
+        if N < 2 {
+            return Err(format!("Can't fit a 2D slice in {} dimensions", N));
+        }
+
+        let mut active = HashSet::new();
+        for (y, line) in slice.lines().enumerate() {
+            for (x, character) in line.chars().enumerate() {
+                match character {
+                    '#' => {
+                        let mut coordinates = [0; N];
+                        coordinates[0] = x as isize;
+                        coordinates[1] = y as isize;
+                        active.insert(Point(coordinates));
                     }
+                    '.' => {}
+                    _ => return Err(format!("Invalid cube state '{}'", character)),
                 }
             }
         }
-    }
 
-    next
+        Ok(Self { active, cycles: 0 })
+    }
 }
 
 /// Count the number of cubes in the active state after the sixth cycle
 pub fn one(file_path: &str) -> usize {
     let input = read_file(file_path);
-    let mut state = parse_state(to_active, &input);
-
-    for _ in 0..6 {
-        state = next_state(&state);
-    }
+    let mut pocket: PocketDimension<3> = input.parse().unwrap();
+    pocket.run(6);
 
-    state.len()
+    pocket.active_count()
 }
 
 #[cfg(test)]
 mod test {
+    use crate::automaton::{Automaton, LifeRule, SparseAutomaton};
+
     use super::*;
 
     #[test]
     fn parse_cube_state() {
-        let msg =
-            "should map a line of serialized conway cube states to the set of active coordinates";
-        let expected = vec![(1, 0, 0)].into_iter().collect();
-        let actual = to_active((0, ".#."));
+        let msg = "should parse the starting slice into the set of active points";
+        let expected = vec![Point([1, 0, 0])].into_iter().collect();
+        let actual = ".#.".parse::<PocketDimension<3>>().unwrap().active;
         assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should reject invalid slices";
+        assert!(".x.".parse::<PocketDimension<3>>().is_err(), "{}", msg);
+        assert!("#".parse::<PocketDimension<1>>().is_err(), "{}", msg);
     }
 
     #[test]
-    fn counts_neighbors() {
-        let msg = "should return the next set of active cubes";
-        let initial = vec![(1, 0, 0), (0, 2, 0), (1, 2, 0), (2, 2, 0), (2, 1, 0)]
-            .into_iter()
-            .collect();
-        let expected = Some(1);
-        let actual = count_neigbors(&initial, &(1, 0, 0), 3);
+    fn symmetry() {
+        let msg = "should map points to a canonical point and count its symmetric points";
+        let expected = Point([1, 2, 0, 1, 3]);
+        let actual = Point([1, 2, -3, 0, -1]).canonical();
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = Some(3);
-        let actual = count_neigbors(&initial, &(2, 1, 0), 3);
-        assert_eq!(actual, expected, "{}", msg);
+        // 3 orderings * 2 flips of each non zero coordinate
+        assert_eq!(Point([0, 0, 0, 1, 1]).weight(), 12, "{}", msg);
+        // 6 orderings * 4 flips
+        assert_eq!(Point([0, 0, 0, 1, 2]).weight(), 24, "{}", msg);
+        assert_eq!(Point([0, 0, 0, 0, 0]).weight(), 1, "{}", msg);
+        assert_eq!(Point([5, 5]).weight(), 1, "{}", msg);
+    }
 
-        let expected = Some(1);
-        let actual = count_neigbors(&initial, &(1, 0, 0), 3);
-        assert_eq!(actual, expected, "{}", msg);
+    #[test]
+    fn matches_full_simulation() {
+        let msg = "should give the same result as simulating every point";
+        let slice = ".#.\n..#\n###";
+
+        let mut pocket: PocketDimension<5> = slice.parse().unwrap();
+        pocket.run(3);
+
+        let initial = slice.parse::<PocketDimension<5>>().unwrap().active;
+        let mut full: SparseAutomaton<_, _> = Automaton::new(
+            Lattice::<5>,
+            LifeRule::conway(),
+            initial.into_iter().map(|point| point.0),
+        );
+        full.run(3);
+
+        assert_eq!(pocket.active_count(), full.population(), "{}", msg);
+        for cell in full.live_cells() {
+            assert!(pocket.is_active(Point(cell)), "{}", msg);
+        }
     }
 
     #[test]
@@ -1,5 +1,8 @@
 //! Solutions to 2020 day 18 problems
 //! --- Day 18: Operation Order ---
+use parser::lexer::{p_number, p_punct, Lexer, Token};
+use parser::three::Parser;
+
 use crate::day_1::read_file;
 
 mod two;
@@ -44,92 +47,76 @@ struct Expression {
     rhs: Box<Operand>,
 }
 
-// recursive [`Expression`] parsing helper
-fn parse_expr(mut lhs: Option<Operand>, string: &str) -> Result<(&str, Operand), String> {
-    if string.is_empty() {
-        return lhs
-            .map(|lhs| (string, lhs))
-            .ok_or_else(|| "Missing LHS operand".to_string());
+type ParseResult<'a> = Result<(&'a [Token<'a>], Operand), String>;
+
+/// describe a failed token parser
+fn to_error((label, err): (String, String)) -> String {
+    format!("Expected {}: {}", label, err)
+}
+
+/// parse an operator token
+fn parse_op<'a>() -> Parser<'a, Token<'a>, Op> {
+    p_punct('+')
+        .map(|_| Op::Add)
+        .or_else(p_punct('*').map(|_| Op::Mult))
+}
+
+/// parse a number or a parenthesized sub expression
+fn parse_operand<'a>(tokens: &'a [Token<'a>], precedence: fn(&Op) -> u8) -> ParseResult<'a> {
+    if let Ok((remaining, num)) = p_number::<usize>().parse(tokens) {
+        return Ok((remaining, Operand::Number(num)));
     }
 
-    let mut rhs: Option<Operand> = None;
-    let mut op: Option<Op> = None;
-    for (idx, character) in string.char_indices() {
-        if let (Some(lhs), Some(op), Some(rhs)) = (&lhs, &op, &rhs) {
-            return parse_expr(
-                Some(Operand::Expr(Expression {
-                    lhs: lhs.clone().into(),
-                    rhs: rhs.clone().into(),
-                    op: op.clone(),
-                })),
-                &string[idx..],
-            );
-        }
+    let (remaining, _) = p_punct('(').parse(tokens).map_err(to_error)?;
+    let (remaining, expr) = parse_expr(remaining, 0, precedence)?;
+    let (remaining, _) = p_punct(')').parse(remaining).map_err(to_error)?;
 
-        match character {
-            ')' => {
-                // end of expression
-                return lhs
-                    .map(|lhs| (&string[idx + 1..], lhs))
-                    .ok_or_else(|| "Missing LHS operand".to_string());
-            }
-            '(' => {
-                // new expression
-                let (remaining, expr) = parse_expr(None, &string[idx + 1..])?;
-
-                if lhs.is_none() {
-                    return parse_expr(Some(expr), remaining);
-                }
-                if rhs.is_none() {
-                    return parse_expr(
-                        Some(Operand::Expr(Expression {
-                            lhs: lhs.unwrap().into(),
-                            rhs: expr.into(),
-                            op: op.unwrap(),
-                        })),
-                        remaining,
-                    );
-                }
-            }
-            character if character.is_digit(10) => {
-                // part of a number
-                // NOTE: input contains only 0-9
-                let num = character.to_digit(10).unwrap() as usize;
-                if lhs.is_none() {
-                    lhs = Some(Operand::Number(num));
-                } else if rhs.is_none() {
-                    rhs = Some(Operand::Number(num));
-                }
-            }
-            '*' => {
-                op = Some(Op::Mult);
-            }
-            '+' => {
-                op = Some(Op::Add);
-            }
-            _ => {}
+    Ok((remaining, expr))
+}
+
+// recursive [`Expression`] parsing helper. Operators binding at least as tightly as
+// `min_precedence` are folded from left to right
+fn parse_expr<'a>(
+    tokens: &'a [Token<'a>],
+    min_precedence: u8,
+    precedence: fn(&Op) -> u8,
+) -> ParseResult<'a> {
+    let (mut tokens, mut lhs) = parse_operand(tokens, precedence)?;
+
+    while let Ok((remaining, op)) = parse_op().parse(tokens) {
+        if precedence(&op) < min_precedence {
+            break;
         }
+        let (remaining, rhs) = parse_expr(remaining, precedence(&op) + 1, precedence)?;
+        lhs = Operand::Expr(Expression {
+            lhs: lhs.into(),
+            op,
+            rhs: rhs.into(),
+        });
+        tokens = remaining;
     }
 
-    Ok((
-        "",
-        Operand::Expr(Expression {
-            lhs: lhs.unwrap().into(),
-            rhs: rhs.unwrap().into(),
-            op: op.unwrap(),
-        }),
-    ))
+    Ok((tokens, lhs))
 }
 
-/// parse an [`Expression`] from a string
-fn from_str(string: &str) -> Result<Expression, String> {
-    let (_remaining, expr) = parse_expr(None, string)?;
+/// parse an [`Expression`] from a string, using `precedence` to rank operators
+fn parse_with(string: &str, precedence: fn(&Op) -> u8) -> Result<Expression, String> {
+    let tokens = Lexer::new().tokenize(string);
+    let (remaining, expr) = parse_expr(&tokens, 0, precedence)?;
+    if let Some(token) = remaining.first() {
+        return Err(format!("Unexpected {:?} after expression", token));
+    }
     match expr {
         Operand::Expr(result) => Ok(result),
-        _ => Err("Should be unreachable".to_string()),
+        Operand::Number(_) => Err(format!("'{}' has no operator", string)),
     }
 }
 
+/// parse an [`Expression`] from a string, where every operator has the same precedence
+fn from_str(string: &str) -> Result<Expression, String> {
+    parse_with(string, |_| 0)
+}
+
 /// Return the result of evaluating an expression
 fn evaluate(expr: Expression) -> usize {
     let left = match *expr.lhs {
@@ -234,6 +221,22 @@ mod test {
         };
         let actual = from_str("1 + (2 * 3)").unwrap();
         assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should parse numbers with several digits";
+        let expected = Expression {
+            lhs: Operand::Number(12).into(),
+            op: Op::Mult,
+            rhs: Operand::Number(345).into(),
+        };
+        assert_eq!(from_str("12*345"), Ok(expected), "{}", msg);
+
+        let msg = "should report unbalanced parentheses";
+        let expected = Err("Expected ): No more input".to_string());
+        assert_eq!(from_str("1 + (2 * 3"), expected, "{}", msg);
+
+        let msg = "should report tokens left after the expression";
+        let expected = Err("Unexpected Punct \")\" at 0:6 after expression".to_string());
+        assert_eq!(from_str("1 + 2 ) 3"), expected, "{}", msg);
     }
 
     #[test]
@@ -7,10 +7,11 @@ use std::str::FromStr;
 use regex::Regex;
 
 use crate::day_1::read_file;
+use crate::grid::Grid;
 
 type TileId = usize;
 type Borders = [Vec<char>; 4];
-type Image = Vec<Vec<char>>;
+type Image = Grid<char>;
 
 /// vertical flip, horizontal flip, rotation
 type Orientation = (bool, bool, usize);
@@ -61,7 +62,7 @@ impl FromStr for Tile {
         Ok(Self {
             id,
             borders: [top, right, bottom, left],
-            body,
+            body: Grid::from_rows(body).map_err(|_e| "Tile body is not rectangular")?,
             neighbors: [None; 4],
         })
     }
@@ -218,72 +219,20 @@ fn get_side(
     neigh[side]
 }
 
-/// rotate a grid (2d vec) clockwise `units` times
-fn rotate_grid<T: Clone>(grid: Vec<Vec<T>>, units: usize) -> Vec<Vec<T>> {
-    match units % 4 {
-        1 => {
-            let mut result = vec![];
-            for c in 0..grid[0].len() {
-                let mut row = vec![];
-                for line in grid.iter().rev() {
-                    let cell = line[c].clone();
-                    row.push(cell);
-                }
-                result.push(row);
-            }
-
-            result
-        }
-        2 => grid
-            .into_iter()
-            .rev()
-            .map(|row| row.into_iter().rev().collect())
-            .collect(),
-        3 => {
-            let mut result = vec![];
-            for c in (0..grid[0].len()).rev() {
-                let mut row = vec![];
-                for line in grid.iter() {
-                    let cell = line[c].clone();
-                    row.push(cell);
-                }
-                result.push(row);
-            }
-
-            result
-        }
-        _ => grid,
-    }
-}
-
-/// flip a grid (2d vec) on its vertical and/or horizontal axes
-pub fn flip_grid<I, T: Clone>(grid: I, vertical: bool, horizontal: bool) -> Vec<Vec<T>>
-where
-    I: IntoIterator<Item = Vec<T>>,
-    <I as IntoIterator>::IntoIter: DoubleEndedIterator,
-{
-    if vertical {
-        grid.into_iter()
-            .rev()
-            .map(|row| {
-                if horizontal {
-                    row.into_iter().rev().collect()
-                } else {
-                    row
-                }
-            })
-            .collect()
+/// flip a grid on its vertical and/or horizontal axes, then rotate it clockwise `rotation` times
+fn orient(grid: &Image, vertical: bool, horizontal: bool, rotation: usize) -> Image {
+    let grid = if vertical {
+        grid.flip_vertical()
     } else {
-        grid.into_iter()
-            .map(|row| {
-                if horizontal {
-                    row.into_iter().rev().collect()
-                } else {
-                    row
-                }
-            })
-            .collect()
-    }
+        grid.clone()
+    };
+    let grid = if horizontal {
+        grid.flip_horizontal()
+    } else {
+        grid
+    };
+
+    grid.rotate(rotation)
 }
 
 fn next_orient(
@@ -311,7 +260,7 @@ fn next_orient(
 fn get_row(
     tile_map: &mut HashMap<TileId, Tile>,
     top_left: OrientedTile,
-) -> (Option<OrientedTile>, Image) {
+) -> (Option<OrientedTile>, Vec<Vec<char>>) {
     let Tile {
         mut neighbors,
         body,
@@ -333,8 +282,7 @@ fn get_row(
     );
 
     // orient body
-    let row = flip_grid(body, glob_vert, glob_horz);
-    let mut row = rotate_grid(row, absolute_rotation);
+    let mut row = orient(&body, glob_vert, glob_horz, absolute_rotation).into_rows();
 
     while let Some((next_id, next_o)) =
         get_side(&neighbors, glob_vert, glob_horz, absolute_rotation, 1)
@@ -347,11 +295,10 @@ fn get_row(
         glob_horz = h;
 
         // orient next body tile
-        let next_body = flip_grid(next_tile.body, glob_vert, glob_horz);
-        let next_body = rotate_grid(next_body, absolute_rotation);
+        let next_body = orient(&next_tile.body, glob_vert, glob_horz, absolute_rotation);
         // append to row
-        for (idx, line) in row.iter_mut().enumerate() {
-            line.extend(next_body[idx].iter());
+        for (line, next_line) in row.iter_mut().zip(next_body.rows()) {
+            line.extend(next_line);
         }
 
         neighbors = next_tile.neighbors;
@@ -373,7 +320,7 @@ fn assemble(tile_map: &mut HashMap<TileId, Tile>, top_left: OrientedTile) -> Ima
         result.extend(row);
     }
 
-    result
+    Grid::from_rows(result).expect("Assembled image is not rectangular")
 }
 
 fn monster_indexes(row_offset: usize, col_offset: usize) -> Vec<(usize, usize)> {
@@ -398,14 +345,14 @@ fn monster_indexes(row_offset: usize, col_offset: usize) -> Vec<(usize, usize)>
 
 /// returns an image oriented with monsters located, and a list of all indexes that contain part of
 /// a monster
-fn find_monsters(image: &[Vec<char>]) -> (String, Vec<(usize, usize)>) {
+fn find_monsters(image: &Image) -> (String, Vec<(usize, usize)>) {
     const MONSTER_WIDTH: usize = 20;
     let monster_re: Regex =
         Regex::new("(.{18}#).\n(#.{4}(?:#{2}.{4}){2}#{3})\n(.#(?:.{2}#){5})").unwrap();
 
-    fn halp(monster_re: &Regex, image: &[Vec<char>]) -> Option<(String, Vec<(usize, usize)>)> {
+    fn halp(monster_re: &Regex, image: &Image) -> Option<(String, Vec<(usize, usize)>)> {
         let mut result = vec![];
-        let len = image.len();
+        let len = image.height();
 
         // check every flip variation
         for v_flip in 0..=1 {
@@ -419,11 +366,11 @@ fn find_monsters(image: &[Vec<char>]) -> (String, Vec<(usize, usize)>) {
                     while col < (len - MONSTER_WIDTH) {
                         let mut f;
                         let mut r;
-                        let image_iter: &mut dyn Iterator<Item = &Vec<char>> = if v_flip {
-                            r = image[row..(row + 3)].iter().rev();
+                        let image_iter: &mut dyn Iterator<Item = &[char]> = if v_flip {
+                            r = image.rows().skip(row).take(3).rev();
                             &mut r
                         } else {
-                            f = image[row..(row + 3)].iter();
+                            f = image.rows().skip(row).take(3);
                             &mut f
                         };
 
@@ -451,11 +398,11 @@ fn find_monsters(image: &[Vec<char>]) -> (String, Vec<(usize, usize)>) {
                 if !result.is_empty() {
                     let mut f;
                     let mut r;
-                    let image_iter: &mut dyn Iterator<Item = &Vec<char>> = if v_flip {
-                        r = image.iter().rev();
+                    let image_iter: &mut dyn Iterator<Item = &[char]> = if v_flip {
+                        r = image.rows().rev();
                         &mut r
                     } else {
-                        f = image.iter();
+                        f = image.rows();
                         &mut f
                     };
                     let img_str = image_iter
@@ -478,7 +425,7 @@ fn find_monsters(image: &[Vec<char>]) -> (String, Vec<(usize, usize)>) {
 
     halp(&monster_re, image).unwrap_or_else(|| {
         // rotate
-        let img_rot = rotate_grid(image.to_vec(), 1);
+        let img_rot = image.rotate(1);
         halp(&monster_re, &img_rot).unwrap()
     })
 }
@@ -503,8 +450,9 @@ fn print_monsters(image: &str, monster_indices: &[(usize, usize)]) {
 }
 
 #[allow(dead_code)]
-fn print_tiles(image: &[Vec<char>], tile_width: usize) {
-    for tile in image.chunks(tile_width) {
+fn print_tiles(image: &Image, tile_width: usize) {
+    let rows: Vec<_> = image.rows().collect();
+    for tile in rows.chunks(tile_width) {
         for row in tile {
             for tile in row.chunks(tile_width) {
                 print!("{} ", String::from_iter(tile));
@@ -545,38 +493,8 @@ pub fn two(file_path: &str) -> usize {
 
 #[cfg(test)]
 mod test {
-    use std::iter::FromIterator;
-
     use super::*;
 
-    #[test]
-    fn rotates_grid() {
-        let grid = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let expected = vec![vec!['c', 'a'], vec!['d', 'b']];
-        let actual = rotate_grid(grid, 1);
-        assert_eq!(actual, expected);
-
-        let grid = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let expected = vec![vec!['d', 'c'], vec!['b', 'a']];
-        let actual = rotate_grid(grid, 2);
-        assert_eq!(actual, expected);
-
-        let grid = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let expected = vec![vec!['b', 'd'], vec!['a', 'c']];
-        let actual = rotate_grid(grid, 3);
-        assert_eq!(actual, expected);
-
-        let grid = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let expected = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let actual = rotate_grid(grid, 0);
-        assert_eq!(actual, expected);
-
-        let grid = vec![vec!['a', 'b'], vec!['c', 'd']];
-        let expected = vec![vec!['c', 'a'], vec!['d', 'b']];
-        let actual = rotate_grid(grid, 5);
-        assert_eq!(actual, expected);
-    }
-
     #[test]
     fn orients() {
         let msg = "should return next orientation";
@@ -669,9 +587,9 @@ mod test {
 
         let (top_left, mut tiles) = find_neighbors(tiles);
         let actual = assemble(&mut tiles, (top_left.unwrap(), Default::default()))
-            .into_iter()
+            .rows()
             .rev()
-            .map(String::from_iter)
+            .map(|row| row.iter().collect::<String>())
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -0,0 +1,257 @@
+//! Allergen solver
+//!
+//! Each allergen is found in exactly one ingredient, and each ingredient contains at most one
+//! allergen. An allergen's candidates are the ingredients common to every food listing it. The
+//! solver resolves the candidates with [`resolve`], which also tells a unique assignment from an
+//! ambiguous or contradictory one.
+//!
+//! ```
+//! use advent_2020::day_21::{parse_foods, AllergenSolver, Outcome};
+//!
+//! let foods = parse_foods("a b (contains dairy)\nb c (contains dairy, fish)\nc (contains fish)").unwrap();
+//! let solver = AllergenSolver::new(foods);
+//! assert_eq!(solver.safe_appearances(), 1);
+//!
+//! let expected = vec![("dairy", "b"), ("fish", "c")].into_iter().collect();
+//! assert_eq!(solver.solve(), Outcome::Unique(expected));
+//! ```
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::assignment::{resolve, AssignmentError};
+
+/// A food's ingredients, and the allergens it's known to contain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Food<'a> {
+    /// ingredients in the food
+    pub ingredients: BTreeSet<&'a str>,
+    /// allergens listed for the food. Some allergens may not be listed
+    pub allergens: BTreeSet<&'a str>,
+}
+
+impl<'a> Food<'a> {
+    /// parse a food from a line like `mxmxvkd kfcds (contains dairy, fish)`
+    pub fn parse(line: &'a str) -> Result<Self, String> {
+        let (ingredients, allergens) = match line.split_once(" (contains ") {
+            Some((ingredients, allergens)) => {
+                let allergens = allergens
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Missing ')' after allergens in '{}'", line))?;
+                (ingredients, allergens.split(", ").collect())
+            }
+            None => (line, BTreeSet::new()),
+        };
+        let ingredients: BTreeSet<_> = ingredients.split_whitespace().collect();
+
+        if ingredients.is_empty() {
+            return Err(format!("No ingredients in '{}'", line));
+        }
+        if allergens.contains("") {
+            return Err(format!("Empty allergen in '{}'", line));
+        }
+
+        Ok(Self {
+            ingredients,
+            allergens,
+        })
+    }
+}
+
+/// parse one food per line
+pub fn parse_foods(input: &str) -> Result<Vec<Food<'_>>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| Food::parse(line).map_err(|err| format!("Line {}: {}", idx + 1, err)))
+        .collect()
+}
+
+/// Ingredient containing each allergen
+pub type Assignment<'a> = BTreeMap<&'a str, &'a str>;
+
+/// Result of solving for the allergens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<'a> {
+    /// exactly one assignment fits the foods
+    Unique(Assignment<'a>),
+    /// more than one assignment fits, shown by two that differ
+    Ambiguous(Vec<Assignment<'a>>),
+    /// no assignment fits. Lists allergens that can't be given an ingredient of their own
+    Contradictory(Vec<&'a str>),
+}
+
+/// Solver matching allergens to the ingredients containing them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllergenSolver<'a> {
+    foods: Vec<Food<'a>>,
+    /// ingredients that could contain each allergen
+    candidates: BTreeMap<&'a str, BTreeSet<&'a str>>,
+}
+
+impl<'a> AllergenSolver<'a> {
+    /// Create a solver, intersecting the ingredients of every food listing each allergen
+    pub fn new(foods: Vec<Food<'a>>) -> Self {
+        let mut candidates: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for food in &foods {
+            for allergen in &food.allergens {
+                candidates
+                    .entry(allergen)
+                    .and_modify(|ingredients| {
+                        ingredients.retain(|ingredient| food.ingredients.contains(ingredient))
+                    })
+                    .or_insert_with(|| food.ingredients.clone());
+            }
+        }
+
+        Self { foods, candidates }
+    }
+
+    /// Return the foods being solved
+    pub fn foods(&self) -> &[Food<'a>] {
+        &self.foods
+    }
+
+    /// Return the ingredients that could contain each allergen
+    pub fn candidates(&self) -> &BTreeMap<&'a str, BTreeSet<&'a str>> {
+        &self.candidates
+    }
+
+    /// Return the ingredients that can't contain any allergen
+    pub fn safe_ingredients(&self) -> BTreeSet<&'a str> {
+        let unsafe_ingredients: HashSet<_> = self.candidates.values().flatten().collect();
+
+        self.foods
+            .iter()
+            .flat_map(|food| food.ingredients.iter().copied())
+            .filter(|ingredient| !unsafe_ingredients.contains(ingredient))
+            .collect()
+    }
+
+    /// Return the number of times safe ingredients appear in foods
+    pub fn safe_appearances(&self) -> usize {
+        let safe = self.safe_ingredients();
+
+        self.foods
+            .iter()
+            .map(|food| food.ingredients.intersection(&safe).count())
+            .sum()
+    }
+
+    /// Match every allergen to an ingredient
+    pub fn solve(&self) -> Outcome<'a> {
+        let candidates = self
+            .candidates
+            .iter()
+            .map(|(allergen, ingredients)| (*allergen, ingredients.iter().copied().collect()))
+            .collect();
+
+        match resolve(candidates) {
+            Ok(assignment) => Outcome::Unique(assignment.into_iter().collect()),
+            Err(AssignmentError::Ambiguous(examples)) => Outcome::Ambiguous(
+                examples
+                    .into_iter()
+                    .map(|example| example.into_iter().collect())
+                    .collect(),
+            ),
+            Err(AssignmentError::Unsatisfiable(allergens)) => Outcome::Contradictory(allergens),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let msg = "should parse foods";
+        let food = Food::parse("a b (contains dairy, fish)").unwrap();
+        assert_eq!(
+            food.ingredients,
+            vec!["a", "b"].into_iter().collect(),
+            "{}",
+            msg
+        );
+        assert_eq!(
+            food.allergens,
+            vec!["dairy", "fish"].into_iter().collect(),
+            "{}",
+            msg
+        );
+        assert!(Food::parse("a b").unwrap().allergens.is_empty(), "{}", msg);
+
+        let msg = "should report bad foods";
+        let expected = Err("Line 2: Missing ')' after allergens in 'b (contains fish'".to_string());
+        assert_eq!(
+            parse_foods("a (contains soy)\nb (contains fish"),
+            expected,
+            "{}",
+            msg
+        );
+        let expected = Err("Line 1: No ingredients in ' (contains soy)'".to_string());
+        assert_eq!(parse_foods(" (contains soy)"), expected, "{}", msg);
+    }
+
+    #[test]
+    fn solves() {
+        let msg = "should find the unique assignment";
+        let input = crate::day_1::read_file("input/21-t.txt");
+        let solver = AllergenSolver::new(parse_foods(&input).unwrap());
+        let expected = vec![("dairy", "mxmxvkd"), ("fish", "sqjhc"), ("soy", "fvjkl")];
+        assert_eq!(
+            solver.solve(),
+            Outcome::Unique(expected.into_iter().collect()),
+            "{}",
+            msg
+        );
+
+        let msg = "should show two assignments when several fit";
+        let foods = parse_foods(
+            "a b c (contains dairy, fish)\n\
+             b c d (contains fish, soy)\n\
+             e (contains nuts)\n\
+             e f",
+        )
+        .unwrap();
+        let solver = AllergenSolver::new(foods);
+        assert_eq!(
+            solver.safe_ingredients(),
+            vec!["f"].into_iter().collect(),
+            "{}",
+            msg
+        );
+        match solver.solve() {
+            Outcome::Ambiguous(examples) => {
+                assert_eq!(examples.len(), 2, "{}", msg);
+                assert_ne!(examples[0], examples[1], "{}", msg);
+                assert_eq!(examples[0].get("nuts"), Some(&"e"), "{}", msg);
+                assert_eq!(examples[1].len(), 4, "{}", msg);
+            }
+            outcome => panic!("{}, got {:?}", msg, outcome),
+        }
+    }
+
+    #[test]
+    fn contradictions() {
+        let msg = "should report allergens without a candidate";
+        let solver =
+            AllergenSolver::new(parse_foods("a (contains dairy)\nb (contains dairy)").unwrap());
+        assert_eq!(
+            solver.solve(),
+            Outcome::Contradictory(vec!["dairy"]),
+            "{}",
+            msg
+        );
+
+        let msg = "should report allergens sharing too few candidates";
+        let foods = parse_foods("a b (contains dairy, fish, soy)\nc (contains nuts)").unwrap();
+        let solver = AllergenSolver::new(foods);
+        // any one of the three could be left out, the last in order is
+        assert_eq!(
+            solver.solve(),
+            Outcome::Contradictory(vec!["soy"]),
+            "{}",
+            msg
+        );
+    }
+}
@@ -1,39 +1,382 @@
 //! Solutions to 2020 day 22 problems
 //! --- Day 22: Crab Combat ---
-use std::collections::VecDeque;
-use std::num::ParseIntError;
+//!
+//! [`Combat`] plays a game for any number of players under a [`Ruleset`]. Each round, every player
+//! with cards draws their top card. The ruleset decides whether a sub-game picks the round's
+//! winner, otherwise the highest card wins. The winner puts their own card on the bottom of their
+//! deck, followed by the other drawn cards from highest to lowest. Players drop out when their deck
+//! runs out, and the last player holding cards wins.
+//!
+//! Loop detection compares rolling hashes of the decks rather than the decks themselves, and the
+//! winner of each distinct sub-game is remembered, so recursive games never replay a sub-game.
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::day_1::read_file;
+use crate::math::mulmod;
 
 mod two;
-pub use two::two;
+pub use two::{two, Recursive};
 
 /// A deck of playing cards
-type Deck = VecDeque<usize>;
+pub type Deck = VecDeque<usize>;
 
-/// Combat game state
-#[derive(Debug)]
-enum Game {
-    /// Game with rounds remaining to be played
-    InProgress((Deck, Deck)),
-    /// Game that has been won
-    Complete(Deck),
+/// Decides how rounds of [`Combat`] are played
+pub trait Ruleset {
+    /// true if a game ends in a win for the first player still holding cards when the decks
+    /// repeat an arrangement from an earlier round of the same game
+    fn detects_loops(&self) -> bool {
+        false
+    }
+
+    /// true if the round should be decided by a sub-game. `decks` are the decks of the players
+    /// who drew `cards`, after drawing
+    fn triggers_sub_game(&self, _decks: &[&Deck], _cards: &[usize]) -> bool {
+        false
+    }
+
+    /// Return the decks for a sub-game: as many cards from the top of each deck as the card the
+    /// player drew
+    fn sub_game_decks(&self, decks: &[&Deck], cards: &[usize]) -> Vec<Deck> {
+        decks
+            .iter()
+            .zip(cards)
+            .map(|(deck, card)| deck.iter().copied().take(*card).collect())
+            .collect()
+    }
+
+    /// Return the winner of a sub-game between `decks` if the rules make it certain without
+    /// playing the sub-game
+    fn sub_game_winner(&self, _decks: &[Deck]) -> Option<usize> {
+        None
+    }
+}
+
+/// The highest card always wins the round
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Classic;
+
+impl Ruleset for Classic {}
+
+/// Plays a sub-game whenever `trigger` returns true for the decks and drawn cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Custom<F> {
+    trigger: F,
+    detects_loops: bool,
+}
+
+impl<F> Custom<F>
+where
+    F: Fn(&[&Deck], &[usize]) -> bool,
+{
+    /// Create a ruleset that plays sub-games on `trigger`, and optionally detects loops
+    pub fn new(trigger: F, detects_loops: bool) -> Self {
+        Self {
+            trigger,
+            detects_loops,
+        }
+    }
+}
+
+impl<F> Ruleset for Custom<F>
+where
+    F: Fn(&[&Deck], &[usize]) -> bool,
+{
+    fn detects_loops(&self) -> bool {
+        self.detects_loops
+    }
+
+    fn triggers_sub_game(&self, decks: &[&Deck], cards: &[usize]) -> bool {
+        (self.trigger)(decks, cards)
+    }
+}
+
+/// The outcome of a game, with statistics covering every sub-game it played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameReport {
+    /// index of the winning player
+    pub winner: usize,
+    /// the winning player's deck
+    pub deck: Deck,
+    /// the winning deck's score
+    pub score: usize,
+    /// rounds played in the top-level game
+    pub rounds: usize,
+    /// rounds played across the game and all of its sub-games
+    pub total_rounds: usize,
+    /// number of sub-games played out, not counting those decided by a shortcut or memo hit
+    pub sub_games: usize,
+    /// deepest level of sub-game nesting. Zero when no sub-games were played
+    pub max_depth: usize,
+    /// number of games ended by a repeated arrangement of decks
+    pub loop_hits: usize,
+    /// number of sub-games whose winner was remembered from an identical earlier sub-game
+    pub memo_hits: usize,
+    /// number of sub-games the ruleset decided without playing
+    pub shortcuts: usize,
+}
+
+/// statistics and remembered sub-game winners collected while playing
+#[derive(Debug, Default)]
+struct Context {
+    total_rounds: usize,
+    sub_games: usize,
+    max_depth: usize,
+    loop_hits: usize,
+    memo_hits: usize,
+    shortcuts: usize,
+    /// winner of each sub-game played, by the hash of its starting decks
+    winners: HashMap<u64, usize>,
+}
+
+/// Modulus for rolling deck hashes, the Mersenne prime 2^61 - 1
+const HASH_MODULUS: u64 = (1 << 61) - 1;
+/// Base for rolling deck hashes
+const HASH_BASE: u64 = 1_000_003;
+
+/// A deck alongside a polynomial hash of its cards, updated as cards are drawn and won
+#[derive(Debug, Clone)]
+struct Hand {
+    cards: Deck,
+    /// sum of (card + 1) * BASE^(cards below it), modulo the hash modulus
+    hash: u64,
 }
 
-impl Game {
-    /// create a new game of Combat
-    pub fn new(deck1: Deck, deck2: Deck) -> Self {
-        Self::InProgress((deck1, deck2))
+impl Hand {
+    fn new(cards: Deck) -> Self {
+        let mut hand = Self {
+            cards: Deck::with_capacity(cards.len()),
+            hash: 0,
+        };
+        cards.into_iter().for_each(|card| hand.push(card));
+        hand
+    }
+
+    /// draw the top card. `powers` holds powers of the hash base, up to the deck size
+    fn draw(&mut self, powers: &[u64]) -> Option<usize> {
+        let card = self.cards.pop_front()?;
+        let term = hash_mulmod(card as u64 + 1, powers[self.cards.len()]);
+        self.hash = (self.hash + HASH_MODULUS - term) % HASH_MODULUS;
+        Some(card)
+    }
+
+    /// put a card on the bottom of the deck
+    fn push(&mut self, card: usize) {
+        self.hash = (hash_mulmod(self.hash, HASH_BASE) + card as u64 + 1) % HASH_MODULUS;
+        self.cards.push_back(card);
+    }
+}
+
+/// multiply modulo the hash modulus
+fn hash_mulmod(a: u64, b: u64) -> u64 {
+    mulmod(a.into(), b.into(), HASH_MODULUS.into()) as u64
+}
+
+/// combine each hand's hash and size into a key for the whole arrangement
+fn arrangement_key(hands: &[Hand]) -> u64 {
+    hands.iter().fold(0, |key: u64, hand| {
+        // splitmix64 finalizer, so hands can't cancel each other out
+        let mut z = (key ^ hand.hash ^ ((hand.cards.len() as u64) << 61))
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// A game of Combat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Combat<R> {
+    ruleset: R,
+    decks: Vec<Deck>,
+    /// most rounds any single game may last
+    round_limit: Option<usize>,
+    /// powers of the hash base, one for each card
+    powers: Vec<u64>,
+}
+
+impl<R: Ruleset> Combat<R> {
+    /// Create a game between players holding `decks`. There must be at least two players, and no
+    /// card may appear twice
+    pub fn new(decks: Vec<Deck>, ruleset: R) -> Result<Self, String> {
+        if decks.len() < 2 {
+            return Err(format!(
+                "Combat needs at least 2 players, got {}",
+                decks.len()
+            ));
+        }
+        let mut seen = HashSet::new();
+        if let Some(card) = decks.iter().flatten().find(|card| !seen.insert(**card)) {
+            return Err(format!("Card {} appears more than once", card));
+        }
+
+        let powers = (0..seen.len())
+            .scan(1, |power, _| {
+                let current = *power;
+                *power = hash_mulmod(*power, HASH_BASE);
+                Some(current)
+            })
+            .collect();
+
+        Ok(Self {
+            ruleset,
+            decks,
+            round_limit: None,
+            powers,
+        })
+    }
+
+    /// Fail any game, or sub-game, that lasts more than `limit` rounds
+    pub fn with_round_limit(mut self, limit: usize) -> Self {
+        self.round_limit = Some(limit);
+        self
+    }
+
+    /// Play the game to completion
+    pub fn play(&self) -> Result<GameReport, String> {
+        let mut stats = Context::default();
+        let hands = self.decks.iter().cloned().map(Hand::new).collect();
+        let (winner, deck, rounds) = self.play_game(hands, 0, &mut stats)?;
+
+        Ok(GameReport {
+            winner,
+            score: get_score(&deck),
+            deck,
+            rounds,
+            total_rounds: stats.total_rounds,
+            sub_games: stats.sub_games,
+            max_depth: stats.max_depth,
+            loop_hits: stats.loop_hits,
+            memo_hits: stats.memo_hits,
+            shortcuts: stats.shortcuts,
+        })
+    }
+
+    /// play a game at the given sub-game depth, returning the winner, their deck and the number
+    /// of rounds played
+    fn play_game(
+        &self,
+        mut hands: Vec<Hand>,
+        depth: usize,
+        stats: &mut Context,
+    ) -> Result<(usize, Deck, usize), String> {
+        stats.max_depth = stats.max_depth.max(depth);
+        let mut previous_arrangements = HashSet::new();
+        let mut rounds = 0;
+
+        loop {
+            let mut holding = hands
+                .iter()
+                .enumerate()
+                .filter(|(_, hand)| !hand.cards.is_empty());
+            match (holding.next(), holding.next()) {
+                (None, _) => return Err("No player holds any cards".to_string()),
+                (Some((winner, _)), None) => {
+                    return Ok((winner, hands.swap_remove(winner).cards, rounds))
+                }
+                _ => {}
+            }
+
+            // a repeated arrangement ends the game in a win for the first player still in it,
+            // which is player 1 in a two player game
+            if self.ruleset.detects_loops()
+                && !previous_arrangements.insert(arrangement_key(&hands))
+            {
+                stats.loop_hits += 1;
+                let winner = hands
+                    .iter()
+                    .position(|hand| !hand.cards.is_empty())
+                    .ok_or("No player holds any cards")?;
+                return Ok((winner, hands.swap_remove(winner).cards, rounds));
+            }
+
+            if self.round_limit.is_some_and(|limit| rounds >= limit) {
+                return Err(format!("No winner after {} rounds", rounds));
+            }
+            rounds += 1;
+            stats.total_rounds += 1;
+            self.play_round(&mut hands, depth, stats)?;
+        }
+    }
+
+    /// play a single round, moving the drawn cards to the winner's deck
+    fn play_round(
+        &self,
+        hands: &mut [Hand],
+        depth: usize,
+        stats: &mut Context,
+    ) -> Result<(), String> {
+        let (players, mut cards): (Vec<usize>, Vec<usize>) = hands
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(player, hand)| hand.draw(&self.powers).map(|card| (player, card)))
+            .unzip();
+
+        let drawn_decks: Vec<&Deck> = players.iter().map(|player| &hands[*player].cards).collect();
+        let winner = if self.ruleset.triggers_sub_game(&drawn_decks, &cards) {
+            let sub_decks = self.ruleset.sub_game_decks(&drawn_decks, &cards);
+            self.sub_game_winner(sub_decks, depth + 1, stats)?
+        } else {
+            (0..cards.len())
+                .max_by_key(|idx| cards[*idx])
+                .ok_or("No cards drawn")?
+        };
+
+        // the winner's card goes first, followed by the rest from highest to lowest
+        let winning_card = cards.swap_remove(winner);
+        cards.sort_unstable_by(|a, b| b.cmp(a));
+        let hand = &mut hands[players[winner]];
+        hand.push(winning_card);
+        cards.into_iter().for_each(|card| hand.push(card));
+
+        Ok(())
+    }
+
+    /// return the winner of a sub-game, playing it only if the ruleset can't decide it and it
+    /// hasn't been played before
+    fn sub_game_winner(
+        &self,
+        decks: Vec<Deck>,
+        depth: usize,
+        stats: &mut Context,
+    ) -> Result<usize, String> {
+        if let Some(winner) = self.ruleset.sub_game_winner(&decks) {
+            stats.shortcuts += 1;
+            return Ok(winner);
+        }
+
+        let hands: Vec<Hand> = decks.into_iter().map(Hand::new).collect();
+        let key = arrangement_key(&hands);
+        if let Some(winner) = stats.winners.get(&key) {
+            stats.memo_hits += 1;
+            return Ok(*winner);
+        }
+
+        stats.sub_games += 1;
+        let (winner, _, _) = self.play_game(hands, depth, stats)?;
+        stats.winners.insert(key, winner);
+        Ok(winner)
     }
 }
 
 /// parse a deck from a str
-fn parse(input: &str) -> Result<VecDeque<usize>, ParseIntError> {
-    input.lines().skip(1).map(|line| line.parse()).collect()
+fn parse(input: &str) -> Result<Deck, String> {
+    input
+        .lines()
+        .skip(1)
+        .map(|line| {
+            line.parse()
+                .map_err(|_| format!("Failed to parse card '{}'", line))
+        })
+        .collect()
+}
+
+/// parse each player's deck from a str
+fn parse_decks(input: &str) -> Result<Vec<Deck>, String> {
+    input.trim().split("\n\n").map(parse).collect()
 }
 
 /// returns a deck's score
-fn get_score(deck: &[usize]) -> usize {
+fn get_score(deck: &Deck) -> usize {
     let size = deck.len();
 
     deck.iter()
@@ -41,47 +384,19 @@ fn get_score(deck: &[usize]) -> usize {
         .fold(0, |acc, (idx, value)| acc + (value * (size - idx)))
 }
 
-/// play a round of 'Combat' with the two provided decks
-fn play_round((mut deck1, mut deck2): (Deck, Deck)) -> Game {
-    deck1.pop_front().map(|card1| {
-        deck2.pop_front().map(|card2| {
-            if card1 > card2 {
-                deck1.extend([card1, card2]);
-            } else {
-                deck2.extend([card2, card1]);
-            }
-        })
-    });
+/// play a game from the decks in a file, returning the report
+fn play_file<R: Ruleset>(file_path: &str, ruleset: R) -> GameReport {
+    let input = read_file(file_path);
+    let decks = parse_decks(&input).expect("Failed to parse input decks");
 
-    if deck1.is_empty() {
-        return Game::Complete(deck2);
-    }
-    if deck2.is_empty() {
-        return Game::Complete(deck1);
-    }
-    Game::InProgress((deck1, deck2))
+    Combat::new(decks, ruleset)
+        .and_then(|combat| combat.play())
+        .expect("Failed to play Combat")
 }
 
 /// returns the winning score from a game of 'Combat'
 pub fn one(file_path: &str) -> usize {
-    let input = read_file(file_path);
-    let mut decks: Vec<_> = input
-        .split("\n\n")
-        .map(parse)
-        .collect::<Result<Vec<_>, _>>()
-        .expect("Failed to parse input decks");
-
-    let mut game = Game::new(decks.remove(0), decks.remove(0));
-    loop {
-        match game {
-            Game::Complete(winning_deck) => {
-                return get_score(&Vec::from(winning_deck));
-            }
-            Game::InProgress(next) => {
-                game = play_round(next);
-            }
-        }
-    }
+    play_file(file_path, Classic).score
 }
 
 #[cfg(test)]
@@ -92,13 +407,90 @@ mod test {
     fn scoring() {
         let msg = "should calculate a deck's score";
 
-        let deck = vec![3, 2, 10, 6, 8, 5, 9, 4, 7, 1];
+        let deck = vec![3, 2, 10, 6, 8, 5, 9, 4, 7, 1].into();
 
         let expected = 306;
         let actual = get_score(&deck);
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    #[test]
+    fn reports() {
+        let msg = "should report the outcome of the game";
+        let report = play_file("input/22-t.txt", Classic);
+        let expected = GameReport {
+            winner: 1,
+            deck: vec![3, 2, 10, 6, 8, 5, 9, 4, 7, 1].into(),
+            score: 306,
+            rounds: 29,
+            total_rounds: 29,
+            sub_games: 0,
+            max_depth: 0,
+            loop_hits: 0,
+            memo_hits: 0,
+            shortcuts: 0,
+        };
+        assert_eq!(report, expected, "{}", msg);
+    }
+
+    #[test]
+    fn plays_many_players() {
+        let msg = "should play until one of several players holds every card";
+        let decks = vec![vec![1, 6].into(), vec![2, 5].into(), vec![3, 4].into()];
+        let report = Combat::new(decks, Classic).unwrap().play().unwrap();
+        assert_eq!(report.winner, 0, "{}", msg);
+        assert_eq!(report.deck, Deck::from(vec![6, 3, 5, 2, 4, 1]), "{}", msg);
+        assert_eq!(report.rounds, 5, "{}", msg);
+
+        let msg = "should reject invalid games";
+        let decks = vec![vec![1, 2].into(), vec![2, 3].into()];
+        let expected = Err("Card 2 appears more than once".to_string());
+        assert_eq!(Combat::new(decks, Classic), expected, "{}", msg);
+        let expected = Err("Combat needs at least 2 players, got 1".to_string());
+        assert_eq!(
+            Combat::new(vec![vec![1].into()], Classic),
+            expected,
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn custom_rules() {
+        let msg = "should play sub-games when the custom trigger says so";
+        // a sub-game decides every round the first player would lose
+        let lowest_wins = Custom::new(|_: &[&Deck], cards: &[usize]| cards[0] < cards[1], false);
+        let decks = vec![vec![1, 4].into(), vec![2, 3].into()];
+        let report = Combat::new(decks, lowest_wins).unwrap().play().unwrap();
+        assert_eq!(report.winner, 0, "{}", msg);
+        assert_eq!(report.sub_games, 1, "{}", msg);
+        assert_eq!(report.max_depth, 1, "{}", msg);
+
+        let msg = "should fail games that don't end";
+        let endless = Custom::new(|_: &[&Deck], _: &[usize]| false, false);
+        let decks = vec![vec![43, 19].into(), vec![2, 29, 14].into()];
+        let combat = Combat::new(decks, endless).unwrap().with_round_limit(1000);
+        let expected = Err("No winner after 1000 rounds".to_string());
+        assert_eq!(combat.play(), expected, "{}", msg);
+
+        let msg = "should fail sub-games without any cards";
+        let always = Custom::new(|_: &[&Deck], _: &[usize]| true, false);
+        let decks = vec![vec![5].into(), vec![3].into()];
+        let combat = Combat::new(decks, always).unwrap().with_round_limit(100);
+        let expected = Err("No player holds any cards".to_string());
+        assert_eq!(combat.play(), expected, "{}", msg);
+    }
+
+    #[test]
+    fn hashes_hands() {
+        let msg = "should update a hand's hash as cards move";
+        let combat = Combat::new(vec![vec![1, 2, 3].into(), vec![4, 5].into()], Classic).unwrap();
+        let mut hand = Hand::new(vec![1, 2, 3].into());
+        assert_eq!(hand.draw(&combat.powers), Some(1), "{}", msg);
+        hand.push(9);
+        assert_eq!(hand.hash, Hand::new(vec![2, 3, 9].into()).hash, "{}", msg);
+    }
+
     #[test]
     fn part_one() {
         let msg = "should calculate the winning player's score";
@@ -1,129 +1,103 @@
 //! Solutions to 2020 day 22 problems part two
 //! --- Day 22: Crab Combat ---
-use std::collections::HashSet;
+use super::{play_file, Deck, Ruleset};
 
-use crate::day_1::read_file;
-
-use super::{get_score, parse, Deck};
-
-/// Completed game state, as a (winner index, winning deck) pair
-type GameResult = (usize, Deck);
-
-/// Recursive Combat game state
-#[derive(Debug)]
-enum Game {
-    /// Game with rounds remaining to be played
-    InProgress {
-        decks: (Deck, Deck),
-        previous_decks: (HashSet<Deck>, HashSet<Deck>),
-    },
-    /// Game that has been won
-    Complete(GameResult),
-}
-
-impl Game {
-    /// create a new game of Combat
-    pub fn new(deck1: Deck, deck2: Deck) -> Self {
-        Self::InProgress {
-            decks: (deck1, deck2),
-            previous_decks: Default::default(),
-        }
-    }
-
-    /// play rounds until the game is complete
-    pub fn resolve(mut self) -> GameResult {
-        loop {
-            match self {
-                Self::InProgress {
-                    decks,
-                    previous_decks,
-                } => {
-                    // if there was a previous round in this game that had exactly the same cards in
-                    // the same order in the same players' decks, the game instantly ends in a win
-                    // for player 1.
-                    if previous_decks.0.contains(&decks.0) || previous_decks.1.contains(&decks.1) {
-                        return (1, decks.0);
-                    }
-
-                    self = play_round(previous_decks, decks);
-                }
-                Self::Complete(result) => {
-                    return result;
-                }
-            }
-        }
-    }
-}
-
-/// play a round of 'Recursive Combat' with the two provided decks
+/// Recursive Combat
 ///
 /// See [AoC 2020 Day 22 part 2](https://adventofcode.com/2020/day/22#part2) for game rules
-fn play_round(
-    mut previous_decks: (HashSet<Deck>, HashSet<Deck>),
-    (mut deck1, mut deck2): (Deck, Deck),
-) -> Game {
-    previous_decks.0.insert(deck1.clone());
-    previous_decks.1.insert(deck2.clone());
-
-    // draw top cards
-    deck1.pop_front().map(|card1| {
-        deck2.pop_front().map(|card2| {
-            if deck1.len() >= card1 && deck2.len() >= card2 {
-                // the winner of the round is determined by playing a new sub-game
-                let subdeck1 = deck1.iter().copied().take(card1).collect();
-                let subdeck2 = deck2.iter().copied().take(card2).collect();
-                let sub_game = Game::new(subdeck1, subdeck2);
-
-                match sub_game.resolve() {
-                    (1, _) => {
-                        deck1.extend([card1, card2]);
-                    }
-                    (2, _) => {
-                        deck2.extend([card2, card1]);
-                    }
-                    _ => panic!("Invalid game result"),
-                }
-            } else {
-                // the winner of the round is the player with the higher-value card.
-                if card1 > card2 {
-                    deck1.extend([card1, card2]);
-                } else {
-                    deck2.extend([card2, card1]);
-                }
-            }
-        })
-    });
-
-    if deck1.is_empty() {
-        return Game::Complete((2, deck2));
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Recursive;
+
+impl Ruleset for Recursive {
+    // if there was a previous round in this game that had exactly the same cards in the same
+    // order in the same players' decks, the game instantly ends in a win for player 1
+    fn detects_loops(&self) -> bool {
+        true
     }
-    if deck2.is_empty() {
-        return Game::Complete((1, deck1));
+
+    // if every player has at least as many cards remaining in their deck as the value of the card
+    // they just drew, the winner of the round is determined by playing a new sub-game
+    fn triggers_sub_game(&self, decks: &[&Deck], cards: &[usize]) -> bool {
+        decks
+            .iter()
+            .zip(cards)
+            .all(|(deck, card)| deck.len() >= *card)
     }
 
-    Game::InProgress {
-        decks: (deck1, deck2),
-        previous_decks,
+    // with distinct cards, a sub-game's highest card is at least as large as its number of cards.
+    // Recursing on it would take more cards than the sub-game holds, so it always wins its round.
+    // Whoever holds it can't run out of cards, and if that is player 1 they win either way
+    fn sub_game_winner(&self, decks: &[Deck]) -> Option<usize> {
+        let (_, holder) = decks
+            .iter()
+            .enumerate()
+            .flat_map(|(player, deck)| deck.iter().map(move |card| (*card, player)))
+            .max()?;
+
+        Some(0).filter(|_| holder == 0)
     }
 }
 
-/// returns the winning score from a game of 'Combat'
+/// returns the winning score from a game of 'Recursive Combat'
 pub fn two(file_path: &str) -> usize {
-    let input = read_file(file_path);
-    let mut decks: Vec<_> = input
-        .split("\n\n")
-        .map(parse)
-        .collect::<Result<Vec<_>, _>>()
-        .expect("Failed to parse input decks");
-    let game = Game::new(decks.remove(0), decks.remove(0));
-    let (_winner, winning_deck) = game.resolve();
-
-    get_score(&Vec::from(winning_deck))
+    play_file(file_path, Recursive).score
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::day_22::Combat;
+
+    #[test]
+    fn reports() {
+        let msg = "should report sub-games";
+        let report = play_file("input/22-t.txt", Recursive);
+        assert_eq!(report.winner, 1, "{}", msg);
+        assert_eq!(report.rounds, 17, "{}", msg);
+        assert_eq!(report.sub_games, 3, "{}", msg);
+        assert_eq!(report.shortcuts + report.memo_hits, 1, "{}", msg);
+        assert_eq!(report.max_depth, 2, "{}", msg);
+        assert_eq!(report.loop_hits, 0, "{}", msg);
+
+        let msg = "should end looping games in a win for player 1";
+        let decks = vec![vec![43, 19].into(), vec![2, 29, 14].into()];
+        let report = Combat::new(decks, Recursive).unwrap().play().unwrap();
+        assert_eq!(report.winner, 0, "{}", msg);
+        assert_eq!(report.loop_hits, 1, "{}", msg);
+
+        let msg = "should end looping games in a win for the first player still holding cards";
+        let decks = vec![
+            vec![1, 4].into(),
+            vec![9, 5, 3, 2].into(),
+            vec![7, 8, 6].into(),
+        ];
+        let report = Combat::new(decks, Recursive).unwrap().play().unwrap();
+        assert_eq!(report.winner, 1, "{}", msg);
+        assert_eq!(report.loop_hits, 1, "{}", msg);
+        assert!(!report.deck.is_empty(), "{}", msg);
+    }
+
+    #[test]
+    fn large_decks() {
+        let msg = "should finish games with large decks quickly";
+        // shuffle the cards with a linear congruential generator
+        let mut cards: Vec<usize> = (1..=60).collect();
+        let mut seed: u64 = 2020;
+        for idx in (1..cards.len()).rev() {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            cards.swap(idx, (seed >> 33) as usize % (idx + 1));
+        }
+        let (first, second) = cards.split_at(30);
+        let decks = vec![
+            first.iter().copied().collect(),
+            second.iter().copied().collect(),
+        ];
+        let report = Combat::new(decks, Recursive).unwrap().play().unwrap();
+        assert_eq!(report.deck.len(), 60, "{}", msg);
+        assert!(report.shortcuts > 0, "{}", msg);
+    }
 
     #[test]
     fn part_two() {
@@ -5,89 +5,208 @@ use crate::day_1::read_file;
 mod two;
 pub use two::two;
 
-type State = Vec<usize>;
-
-/// parse cup state from str
-fn parse(input: &str) -> Result<Vec<usize>, String> {
+/// parse cup labels from str
+fn parse(input: &str) -> Result<Vec<u32>, String> {
     input
         .trim()
         .chars()
         .map(|ch| {
             ch.to_digit(10)
-                .map(|d| d as usize)
                 .ok_or(format!("Failed to parse digit {}", ch))
         })
         .collect()
 }
 
-/// execute a single step of the game and return the new state
+/// A single move of the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    /// Label of the current cup when the move started
+    pub current: u32,
+    /// Labels of the picked up cups, in clockwise order
+    pub picked_up: Vec<u32>,
+    /// Label of the cup the picked up cups were placed after
+    pub destination: u32,
+}
+
+/// A circle of cups, labelled 1 to the number of cups
 ///
-/// Next cup is always at index 0
-fn step(mut state: State) -> State {
-    let current = 0;
-    let removed: Vec<_> = state.splice((current + 1)..(current + 4), []).collect();
-
-    // destination cup: the cup with a label equal to the current cup's label minus one.
-    // If the destination label is not in the current set,
-    //   - subtract one until a label is found OR
-    //   - if the value goes below the lowest value on any cup's label, select the highest value label
-    //     instead.
-    let mut dest_label = state[current] - 1;
-    let mut dest = None;
-    while dest_label > 0 && dest == None {
-        dest = state
+/// Each cup's clockwise neighbor is stored in a flat `Vec` indexed by label, so a move only
+/// relinks a few cups no matter how many there are. Iterating plays one move at a time, yielding
+/// each [`Move`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CupRing {
+    /// label of the cup clockwise of each label. index 0 is unused
+    next: Vec<u32>,
+    /// label of the current cup
+    current: u32,
+    /// number of cups picked up each move
+    pick_up: usize,
+    /// picked up cups for the move being made
+    picked_up: Vec<u32>,
+    /// number of moves made
+    moves: usize,
+}
+
+impl CupRing {
+    /// Create a ring of `cup_count` cups, starting with `labels` clockwise from the current cup,
+    /// followed by the remaining labels in ascending order. Each move picks up `pick_up` cups.
+    pub fn new(labels: &[u32], cup_count: u32, pick_up: usize) -> Result<Self, String> {
+        if (labels.len() as u64) > u64::from(cup_count) {
+            return Err(format!(
+                "{} labels don't fit in {} cups",
+                labels.len(),
+                cup_count
+            ));
+        }
+        // the current cup and a destination cup can't be picked up
+        if pick_up + 2 > cup_count as usize {
+            return Err(format!("Can't pick up {} of {} cups", pick_up, cup_count));
+        }
+
+        let mut seen = vec![false; labels.len() + 1];
+        for label in labels {
+            match seen.get_mut(*label as usize) {
+                Some(seen) if *label > 0 && !*seen => *seen = true,
+                _ => return Err(format!("Invalid or repeated label {}", label)),
+            }
+        }
+
+        let order: Vec<u32> = labels
             .iter()
-            .enumerate()
-            .find(|(_, label)| **label == dest_label)
-            .map(|(idx, _)| idx);
-        dest_label -= 1;
+            .copied()
+            .chain((labels.len() as u32 + 1)..=cup_count)
+            .collect();
+        let mut next = vec![0; cup_count as usize + 1];
+        for pair in order.windows(2) {
+            next[pair[0] as usize] = pair[1];
+        }
+        next[order[order.len() - 1] as usize] = order[0];
+
+        Ok(Self {
+            next,
+            current: order[0],
+            pick_up,
+            picked_up: Vec::with_capacity(pick_up),
+            moves: 0,
+        })
     }
 
-    if dest.is_none() {
-        dest = state
-            .iter()
-            .enumerate()
-            .max_by(|a, b| a.1.cmp(b.1))
-            .map(|(idx, _)| idx);
+    /// Return the number of cups in the ring
+    pub fn cup_count(&self) -> u32 {
+        self.next.len() as u32 - 1
     }
-    let dest = dest.unwrap();
-    state.splice(dest + 1..dest + 1, removed);
 
-    // select a new current cup: the cup which is immediately clockwise of the current cup.
-    state.rotate_left(1);
+    /// Return the label of the current cup
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// Return the number of moves made so far
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Make the provided number of moves
+    pub fn play(&mut self, moves: usize) {
+        for _ in 0..moves {
+            self.make_move();
+        }
+    }
+
+    /// Iterate over the labels clockwise of `label`, not including `label` itself
+    pub fn labels_after(&self, label: u32) -> impl Iterator<Item = u32> + '_ {
+        let mut cursor = label;
+        (1..self.cup_count()).map(move |_| {
+            cursor = self.next[cursor as usize];
+            cursor
+        })
+    }
+
+    /// Return every label clockwise, starting with the current cup
+    pub fn labels(&self) -> Vec<u32> {
+        std::iter::once(self.current)
+            .chain(self.labels_after(self.current))
+            .collect()
+    }
+
+    /// Starting after the cup labeled 1, collects the other cups' labels clockwise into a single
+    /// string with no extra characters
+    pub fn labels_after_one(&self) -> String {
+        self.labels_after(1)
+            .map(|label| label.to_string())
+            .collect()
+    }
 
-    state
+    /// Return the product of the labels of the `count` cups clockwise of cup 1
+    pub fn product_after_one(&self, count: usize) -> u64 {
+        self.labels_after(1).take(count).map(u64::from).product()
+    }
+
+    /// make a move, returning the destination cup
+    fn make_move(&mut self) -> u32 {
+        let current = self.current;
+
+        // The crab picks up the cups clockwise of the current cup
+        self.picked_up.clear();
+        let mut last = current;
+        for _ in 0..self.pick_up {
+            last = self.next[last as usize];
+            self.picked_up.push(last);
+        }
+
+        // destination cup: the cup with a label equal to the current cup's label minus one.
+        // If the destination label is one of the picked up cups,
+        //   - subtract one until a label is found OR
+        //   - if the value goes below the lowest value on any cup's label, wrap around to the
+        //     highest value label instead.
+        let below = |label: u32| match label {
+            1 => self.cup_count(),
+            label => label - 1,
+        };
+        let mut destination = below(current);
+        while self.picked_up.contains(&destination) {
+            destination = below(destination);
+        }
+
+        // place the picked up cups immediately clockwise of the destination cup
+        if let Some(first) = self.picked_up.first() {
+            self.next[current as usize] = self.next[last as usize];
+            self.next[last as usize] = self.next[destination as usize];
+            self.next[destination as usize] = *first;
+        }
+
+        // select a new current cup: the cup which is immediately clockwise of the current cup.
+        self.current = self.next[current as usize];
+        self.moves += 1;
+
+        destination
+    }
 }
 
-/// Starting after the cup labeled 1, collects the other cups' labels clockwise into a single string
-/// with no extra characters
-fn format_result(mut state: State) -> String {
-    let one_idx = state
-        .iter()
-        .enumerate()
-        .find(|(_, label)| **label == 1)
-        .map(|(idx, _)| idx)
-        .unwrap();
-    state.rotate_left(one_idx);
-
-    state
-        .into_iter()
-        .skip(1)
-        .map(|digit| digit.to_string())
-        .collect()
+impl Iterator for CupRing {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current;
+        let destination = self.make_move();
+
+        Some(Move {
+            current,
+            picked_up: self.picked_up.clone(),
+            destination,
+        })
+    }
 }
 
 /// should return the ordered cup labels after cup 1 following 100 steps
 pub fn one(file_path: &str) -> String {
     const STEPS: usize = 100;
     let input = read_file(file_path);
-    let mut cups = parse(&input).expect("Failed to parse initial cup state");
+    let labels = parse(&input).expect("Failed to parse initial cup state");
+    let mut cups = CupRing::new(&labels, labels.len() as u32, 3).expect("Invalid cup labels");
 
-    for _ in 0..STEPS {
-        cups = step(cups);
-    }
-
-    format_result(cups)
+    cups.play(STEPS);
+    cups.labels_after_one()
 }
 
 #[cfg(test)]
@@ -98,18 +217,43 @@ mod test {
     fn steps() {
         let msg = "should return the ordered cup labels after a step";
         let initial = vec![3, 8, 9, 1, 2, 5, 4, 6, 7];
+        let mut cups = CupRing::new(&initial, 9, 3).unwrap();
+        let expected = Move {
+            current: 3,
+            picked_up: vec![8, 9, 1],
+            destination: 2,
+        };
+        assert_eq!(cups.next(), Some(expected), "{}", msg);
         let expected = vec![2, 8, 9, 1, 5, 4, 6, 7, 3];
-        let actual = step(initial);
-        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(cups.labels(), expected, "{}", msg);
 
         let msg = "should return the ordered cup labels after 10 steps";
-        let initial = vec![3, 8, 9, 1, 2, 5, 4, 6, 7];
+        let mut cups = CupRing::new(&initial, 9, 3).unwrap();
+        cups.play(10);
         let expected = vec![8, 3, 7, 4, 1, 9, 2, 6, 5];
-        let mut actual = initial;
-        for _ in 0..10 {
-            actual = step(actual);
-        }
-        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(cups.labels(), expected, "{}", msg);
+        assert_eq!(cups.labels_after_one(), "92658374", "{}", msg);
+    }
+
+    #[test]
+    fn configures() {
+        let msg = "should fill in the remaining cups and pick up the configured number of cups";
+        let mut cups = CupRing::new(&[2, 1], 5, 2).unwrap();
+        assert_eq!(cups.labels(), vec![2, 1, 3, 4, 5], "{}", msg);
+        let expected = Move {
+            current: 2,
+            picked_up: vec![1, 3],
+            destination: 5,
+        };
+        assert_eq!(cups.next(), Some(expected), "{}", msg);
+        assert_eq!(cups.labels(), vec![4, 5, 1, 3, 2], "{}", msg);
+        assert_eq!(cups.product_after_one(2), 6, "{}", msg);
+
+        let msg = "should reject invalid configurations";
+        assert!(CupRing::new(&[1, 1], 5, 3).is_err(), "{}", msg);
+        assert!(CupRing::new(&[1, 3], 5, 3).is_err(), "{}", msg);
+        assert!(CupRing::new(&[1, 2, 3], 2, 3).is_err(), "{}", msg);
+        assert!(CupRing::new(&[1, 2, 3], 4, 3).is_err(), "{}", msg);
     }
 
     #[test]
@@ -6,6 +6,7 @@ use std::str::FromStr;
 
 use parser::three::lib::{choice, one_or_more, p_char};
 
+use crate::automaton::{Automaton, HexLattice, LifeRule, SparseAutomaton};
 use crate::day_1::read_file;
 
 /// Hexagonal tile neighbor direction
@@ -70,77 +71,18 @@ pub fn one(file_path: &str) -> usize {
         .count()
 }
 
-/// count active neighbors for a given position
-fn count_neighbors(map: &HashSet<Direction>, pos: Direction) -> usize {
-    let neighbors = [
-        // w
-        pos + Direction(-1, 0),
-        // nw
-        pos + Direction(0, -1),
-        // sw
-        pos + Direction(-1, 1),
-        // e
-        pos + Direction(1, 0),
-        // ne
-        pos + Direction(1, -1),
-        // se
-        pos + Direction(0, 1),
-    ];
-
-    let mut count = 0;
-    for neighbor in neighbors {
-        if map.contains(&neighbor) {
-            count += 1;
-        }
-    }
-
-    count
-}
-
-/// update a tile set according to the daily rules
-fn flip_tiles(map: HashSet<Direction>) -> HashSet<Direction> {
-    let mut result = HashSet::new();
-
-    fn helper(map: &HashSet<Direction>, pos: Direction, is_black: bool) -> Option<Direction> {
-        let count = count_neighbors(map, pos);
-        // Any black tile with zero or more than 2 black tiles immediately adjacent to it is flipped
-        // to white.
-        if (is_black && (1..=2).contains(&count) )
-            // Any white tile with exactly 2 black tiles immediately adjacent to it is flipped to
-            // black.
-        || count == 2
-        {
-            Some(pos)
-        } else {
-            None
-        }
-    }
-
-    for pos in map.iter() {
-        let neighbors = [
-            // self
-            *pos,
-            // w
-            *pos + Direction(-1, 0),
-            // nw
-            *pos + Direction(0, -1),
-            // sw
-            *pos + Direction(-1, 1),
-            // e
-            *pos + Direction(1, 0),
-            // ne
-            *pos + Direction(1, -1),
-            // se
-            *pos + Direction(0, 1),
-        ];
-
-        let active = neighbors
-            .iter()
-            .filter_map(|pos| helper(&map, *pos, map.contains(pos)));
-        result.extend(active);
-    }
-
-    result
+/// flip tiles for the provided number of days, returning the number of black tiles
+///
+/// - Any black tile with zero or more than 2 black tiles immediately adjacent to it is flipped to
+///   white.
+/// - Any white tile with exactly 2 black tiles immediately adjacent to it is flipped to black.
+fn flip_tiles(black_tiles: HashSet<Direction>, days: usize) -> usize {
+    let rule = LifeRule::new(2..=2, 1..=2);
+    let cells = black_tiles.into_iter().map(|Direction(q, r)| (q, r));
+    let mut automaton: SparseAutomaton<_, _> = Automaton::new(HexLattice, rule, cells);
+    automaton.run(days);
+
+    automaton.population()
 }
 
 /// returns the number of black tiles after 100 days of flips
@@ -151,16 +93,12 @@ pub fn two(file_path: &str) -> usize {
         .map(Direction::from_str)
         .collect::<Result<Vec<_>, _>>()
         .unwrap();
-    let mut tile_map: HashSet<Direction> = assemble(instructions)
+    let tile_map: HashSet<Direction> = assemble(instructions)
         .into_iter()
         .filter_map(|(pos, is_black)| if is_black { Some(pos) } else { None })
         .collect();
 
-    for _ in 0..100 {
-        tile_map = flip_tiles(tile_map);
-    }
-
-    tile_map.len()
+    flip_tiles(tile_map, 100)
 }
 
 #[cfg(test)]
@@ -187,6 +125,18 @@ mod test {
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    #[test]
+    fn flips_tiles() {
+        let msg = "should count the black tiles after flipping";
+        // a line of three tiles survives, and each white tile touching two of them flips
+        let tiles = vec![Direction(-1, 0), Direction(0, 0), Direction(1, 0)]
+            .into_iter()
+            .collect();
+        let expected = 7;
+        let actual = flip_tiles(tiles, 1);
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
     #[test]
     fn part_two() {
         let msg = "should count the number black tiles";
@@ -1,65 +1,102 @@
 //! Solutions to 2020 day 24 problems
 //! --- Day 25: Combo Breaker ---
 use crate::day_1::read_file;
+use crate::math::{discrete_log, modpow, DiscreteLog};
+
+/// The parameters of a card and door handshake
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    subject: u64,
+    modulus: u64,
+    /// table for finding loop sizes of `subject`, when it's coprime to the modulus
+    table: Option<DiscreteLog>,
+}
 
-/// transform a subject number
-fn transform(subject: usize, value: Option<usize>) -> usize {
-    let mut result = value.unwrap_or(1);
-
-    // - Set the value to itself multiplied by the subject number.
-    result *= subject;
-    // - Set the value to the remainder after dividing the value by 20201227.
-    result % 20201227
+impl Default for Handshake {
+    /// The handshake used by the resort's cards and doors
+    fn default() -> Self {
+        Self::new(7, 20201227).expect("The resort's modulus is valid")
+    }
 }
 
-/// returns a fn that calculates loop size for a given target value, using the provided subject num
-fn size_finder(subject: usize) -> impl FnMut(usize) -> usize {
-    let mut cache = vec![];
+impl Handshake {
+    /// Create a handshake that transforms `subject` modulo `modulus`
+    pub fn new(subject: u64, modulus: u64) -> Result<Self, String> {
+        if modulus < 2 {
+            return Err(format!("Modulus must be at least 2, got {}", modulus));
+        }
+
+        Ok(Self {
+            subject,
+            modulus,
+            table: DiscreteLog::new(subject.into(), modulus.into()),
+        })
+    }
+
+    /// Transform a subject number: start with the value 1, then loop size times, set the value to
+    /// itself multiplied by the subject number, then to the remainder after dividing the value by
+    /// the modulus
+    pub fn transform(&self, subject: u64, loop_size: u64) -> u64 {
+        modpow(subject.into(), loop_size.into(), self.modulus.into()) as u64
+    }
 
-    move |key| {
-        let mut value = 1;
+    /// Return the public key for a secret loop size
+    pub fn public_key(&self, loop_size: u64) -> u64 {
+        self.transform(self.subject, loop_size)
+    }
 
-        for i in 0.. {
-            if let Some(cached) = cache.get(i) {
-                value = *cached;
-            } else {
-                value = transform(subject, Some(value));
-                cache.push(value);
-            }
+    /// Return the smallest loop size that transforms the subject number into `public_key`
+    pub fn loop_size(&self, public_key: u64) -> Result<u64, String> {
+        let no_loop_size = || {
+            format!(
+                "No loop size transforms {} into {} modulo {}",
+                self.subject, public_key, self.modulus
+            )
+        };
+        if public_key >= self.modulus {
+            return Err(no_loop_size());
+        }
 
-            if value == key {
-                return i + 1;
-            }
+        // without a table the subject shares a factor with the modulus, which needs the slower
+        // general search
+        match &self.table {
+            Some(table) => table.solve(public_key.into()),
+            None => discrete_log(self.subject.into(), public_key.into(), self.modulus.into()),
         }
+        .map(|loop_size| loop_size as u64)
+        .ok_or_else(no_loop_size)
+    }
 
-        panic!("Finished an infinite loop");
+    /// Return the encryption key the card and door agree on
+    pub fn encryption_key(
+        &self,
+        card_public_key: u64,
+        door_public_key: u64,
+    ) -> Result<u64, String> {
+        let card_loop_size = self.loop_size(card_public_key)?;
+
+        // the door transforms the card's public key with its own loop size to get the same key
+        Ok(self.transform(door_public_key, card_loop_size))
+    }
+
+    /// Return the encryption key for each `(card, door)` public key pair
+    pub fn crack_all(&self, key_pairs: &[(u64, u64)]) -> Vec<Result<u64, String>> {
+        key_pairs
+            .iter()
+            .map(|(card, door)| self.encryption_key(*card, *door))
+            .collect()
     }
 }
 
 /// returns the encryption key
 pub fn one(file_path: &str) -> usize {
     let input = read_file(file_path);
-    let mut pkey_iter = input.lines().map(|line| line.parse::<usize>().unwrap());
+    let mut pkey_iter = input.lines().map(|line| line.parse::<u64>().unwrap());
     let (card_pkey, door_pkey) = (pkey_iter.next().unwrap(), pkey_iter.next().unwrap());
 
-    let mut loop_size = size_finder(7);
-    let card_loop_size = loop_size(card_pkey);
-    let door_loop_size = loop_size(door_pkey);
-
-    // start with the value 1.
-    // Then, a number of times called the loop size, transform the value
-    let mut encryption_key = 1;
-    if card_loop_size < door_loop_size {
-        for _ in 0..card_loop_size {
-            encryption_key = transform(door_pkey, Some(encryption_key));
-        }
-    } else {
-        for _ in 0..door_loop_size {
-            encryption_key = transform(card_pkey, Some(encryption_key));
-        }
-    }
-
-    encryption_key
+    Handshake::default()
+        .encryption_key(card_pkey, door_pkey)
+        .unwrap() as usize
 }
 
 #[cfg(test)]
@@ -70,27 +107,58 @@ mod test {
     fn transform_subject() {
         let msg = "should transform a subject number according to the encrpytion rules";
         let expected = 5764801;
-        let actual = {
-            let mut result = transform(7, None);
-            for _ in 0..7 {
-                result = transform(7, Some(result));
-            }
-            result
-        };
+        let actual = Handshake::default().public_key(8);
         assert_eq!(actual, expected, "{}", msg);
     }
 
     #[test]
     fn find_loop_size() {
         let msg = "should calculate the loop size for a given key";
-        let expected = 8;
-        let mut loop_size = size_finder(7);
-        let actual = loop_size(5764801);
+        let handshake = Handshake::default();
+        let expected = Ok(8);
+        let actual = handshake.loop_size(5764801);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Ok(11);
+        let actual = handshake.loop_size(17807724);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should return an error when no loop size exists";
+        let handshake = Handshake::new(2, 7).unwrap();
+        let expected = Err("No loop size transforms 2 into 3 modulo 7".to_string());
+        let actual = handshake.loop_size(3);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Err("No loop size transforms 2 into 9 modulo 7".to_string());
+        let actual = handshake.loop_size(9);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Err("No loop size transforms 4 into 2 modulo 8".to_string());
+        let actual = Handshake::new(4, 8).unwrap().loop_size(2);
         assert_eq!(actual, expected, "{}", msg);
 
-        let expected = 11;
-        let mut loop_size = size_finder(7);
-        let actual = loop_size(17807724);
+        let msg = "should find loop sizes when the subject shares a factor with the modulus";
+        assert_eq!(
+            Handshake::new(2, 24).unwrap().loop_size(8),
+            Ok(3),
+            "{}",
+            msg
+        );
+        assert_eq!(Handshake::new(4, 8).unwrap().loop_size(0), Ok(2), "{}", msg);
+
+        let msg = "should reject moduli below 2";
+        let expected = "Modulus must be at least 2, got 1".to_string();
+        assert_eq!(Handshake::new(7, 1).unwrap_err(), expected, "{}", msg);
+        assert!(Handshake::new(7, 0).is_err(), "{}", msg);
+    }
+
+    #[test]
+    fn cracks_key_pairs() {
+        let msg = "should return the encryption key for each pair of public keys";
+        let handshake = Handshake::new(5, 23).unwrap();
+        let key_pairs = [(8, 19), (2, 10), (19, 8)];
+        let expected = vec![Ok(2), Ok(8), Ok(2)];
+        let actual = handshake.crack_all(&key_pairs);
         assert_eq!(actual, expected, "{}", msg);
     }
 
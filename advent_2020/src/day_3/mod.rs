@@ -1,6 +1,7 @@
 //! Solutions to 2020 day 3 problems
 //! Toboggan Trajectory
 use crate::day_1::read_file;
+use crate::grid::Grid;
 
 /// Map square types
 #[derive(Debug, PartialEq)]
@@ -21,15 +22,14 @@ impl From<char> for Terrain {
     }
 }
 
-/// deserializes a 2d vec of [`Terrain`] from the specified file path
-pub fn load_terrain<T>(file_path: &str) -> Vec<Vec<T>>
+/// deserializes a [`Grid`] of [`Terrain`] from the specified file path
+pub fn load_terrain<T>(file_path: &str) -> Grid<T>
 where
     T: From<char>,
 {
     read_file(file_path)
-        .lines()
-        .map(|line| line.chars().map(From::from).collect::<Vec<_>>())
-        .collect()
+        .parse()
+        .unwrap_or_else(|err| panic!("Invalid map data: {}", err))
 }
 
 /// A line slope on a 2 dimensional coordinate system
@@ -48,7 +48,7 @@ impl Slope {
 
 /// Count the number of [`Tree`](Terrain::Tree) squares encountered on this map by following the
 /// provided slope, starting from the top left (0, 0) square
-fn count_trees(map: &[Vec<Terrain>], slope: Slope) -> usize {
+fn count_trees(map: &Grid<Terrain>, slope: Slope) -> usize {
     if slope.1 < 1 {
         panic!(
             "Supplied slope {:?} cannot take you down the mountain",
@@ -59,15 +59,14 @@ fn count_trees(map: &[Vec<Terrain>], slope: Slope) -> usize {
     let mut x = 0;
     let mut y = 0;
     let mut count = 0;
-    let map_height = map.len();
-    let map_width = map[0].len();
 
-    while y < map_height {
-        count += match map[y][x] {
-            Terrain::Tree => 1,
-            Terrain::Open => 0,
+    while y < map.height() {
+        // the pattern repeats to the right
+        count += match map.get_wrapping(y as isize, x as isize) {
+            Some(Terrain::Tree) => 1,
+            _ => 0,
         };
-        x = (x + slope.0) % map_width;
+        x += slope.0;
         y += slope.1;
     }
 
@@ -109,21 +108,23 @@ mod test {
     #[test]
     fn loads_terrain() {
         let msg = "should deserialize a map string";
-        let expected = vec![
+        let expected = Grid::from_rows(vec![
             vec![Terrain::Open, Terrain::Open, Terrain::Tree, Terrain::Tree],
             vec![Terrain::Tree, Terrain::Open, Terrain::Open, Terrain::Open],
-        ];
-        let actual: Vec<Vec<Terrain>> = load_terrain("input/3-t_terrain.txt");
+        ])
+        .unwrap();
+        let actual: Grid<Terrain> = load_terrain("input/3-t_terrain.txt");
         assert_eq!(actual, expected, "{}", msg);
     }
 
     #[test]
     fn counts_trees() {
         let msg = "should count the number of trees for a given slope";
-        let map = vec![
+        let map = Grid::from_rows(vec![
             vec![Terrain::Open, Terrain::Open, Terrain::Tree, Terrain::Tree],
             vec![Terrain::Tree, Terrain::Open, Terrain::Open, Terrain::Open],
-        ];
+        ])
+        .unwrap();
         let expected = 0;
         let actual = count_trees(&map, Slope::new(1, 1));
         assert_eq!(actual, expected, "{}", msg);
@@ -3,6 +3,8 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
 
+use parser::three::lib::{eof, many, one_or_more_chars, p_char, satisfy, spaces, ParseErr, Parser};
+
 use crate::day_1::read_file;
 
 pub use two::two;
@@ -11,14 +13,59 @@ mod two;
 /// a key value pair
 struct KeyValue<'a>(&'a str, &'a str);
 
-impl<'a> TryFrom<&'a str> for KeyValue<'a> {
-    type Error = &'static str;
+/// The `key:value` fields of a single passport
+pub type PassportFields = Vec<(String, String)>;
+
+/// parse a `key:value` field
+fn p_field<'a>() -> Parser<'a, (String, String)> {
+    let key = one_or_more_chars(satisfy(|ch| ch.is_alphanumeric(), "key".to_string()));
+    let value = one_or_more_chars(satisfy(|ch| !ch.is_whitespace(), "value".to_string()));
+
+    key.and_then(p_char(':'))
+        .and_then(value)
+        .map(|((key, _), value)| (key, value))
+        .with_label("field".to_string())
+}
 
-    fn try_from(pair: &'a str) -> Result<Self, Self::Error> {
-        pair.split_once(':')
-            .ok_or("Unable to find delimiter ':'")
-            .map(|(f, b)| KeyValue(f, b))
+/// fail at the start of an unparseable field, showing what was found
+fn p_invalid_field<'a>() -> Parser<'a, ()> {
+    one_or_more_chars(satisfy(|ch| !ch.is_whitespace(), "field".to_string()))
+        .try_map(|found| Err(format!("Expected a key:value field, got '{}'", found)))
+        .with_label("field".to_string())
+}
+
+/// Parse the fields of each passport. Fields are separated by whitespace, and passports by blank
+/// lines
+pub fn parse_passports(input: &str) -> Result<Vec<PassportFields>, ParseErr<'_>> {
+    // keep the whitespace after each field, to find where one passport ends and the next begins
+    let fields = many(p_field().and_then(spaces()));
+    let end = eof().or_else(p_invalid_field());
+    let (_, (fields, _)) = spaces().and_then(fields.and_then(end)).parse(input)?.1;
+
+    let mut passports = vec![vec![]];
+    for (field, gap) in fields {
+        passports.last_mut().unwrap().push(field);
+        if gap.iter().filter(|ch| **ch == '\n').count() > 1 {
+            passports.push(vec![]);
+        }
     }
+    passports.retain(|passport| !passport.is_empty());
+
+    Ok(passports)
+}
+
+/// read the fields of each passport in the supplied file
+fn read_fields(file_path: &str) -> Vec<PassportFields> {
+    let contents = read_file(file_path);
+    parse_passports(&contents).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// borrow a passport's fields as key value pairs
+fn key_values(fields: &[(String, String)]) -> Vec<KeyValue<'_>> {
+    fields
+        .iter()
+        .map(|(key, value)| KeyValue(key, value))
+        .collect()
 }
 
 /// Passport data
@@ -47,17 +94,9 @@ impl TryFrom<Vec<KeyValue<'_>>> for Passport {
 
 /// attempt to deserialize [`Passport`]s from the supplied file path
 fn read_passports(file_path: &str) -> Vec<Result<Passport, &'static str>> {
-    read_file(file_path)
-        // double newline between passport entries
-        .split("\n\n")
-        // create list of key:value pairs
-        .map(|passport_str| {
-            passport_str
-                .split_whitespace()
-                .map(|pair| KeyValue::try_from(pair).unwrap())
-                .collect::<Vec<_>>()
-        })
-        .map(TryFrom::try_from)
+    read_fields(file_path)
+        .iter()
+        .map(|fields| Passport::try_from(key_values(fields)))
         .collect()
 }
 
@@ -81,4 +120,34 @@ mod test {
         let actual = one("input/4-t.txt");
         assert_eq!(actual, expected, "{}", msg);
     }
+
+    #[test]
+    fn parses_passports() {
+        let msg = "should split fields into passports at blank lines";
+        let fields = parse_passports("ecl:gry pid:1\nbyr:1937\n\niyr:2013\n").unwrap();
+        let keys: Vec<Vec<&str>> = fields
+            .iter()
+            .map(|passport| passport.iter().map(|(key, _)| key.as_str()).collect())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![vec!["ecl", "pid", "byr"], vec!["iyr"]],
+            "{}",
+            msg
+        );
+
+        let msg = "should point at a field without a value";
+        let err = parse_passports("ecl:gry\npid hcl:#fff").unwrap_err();
+        let expected = "error: Error parsing field
+ --> 4-1.txt:2:1
+  |
+2 | pid hcl:#fff
+  | ^ Expected a key:value field, got 'pid'
+";
+        let actual = err
+            .to_diagnostic()
+            .with_file_name("4-1.txt")
+            .render("ecl:gry\npid hcl:#fff");
+        assert_eq!(actual, expected, "{}", msg);
+    }
 }
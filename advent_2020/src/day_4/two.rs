@@ -3,7 +3,7 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use super::{read_file, HashMap, KeyValue, Passport, TryFrom};
+use super::{key_values, read_fields, HashMap, KeyValue, Passport};
 
 /// Vec<KeyValue> to Result<Passport> conversion with part two rules
 ///
@@ -187,17 +187,9 @@ struct CountryID();
 
 /// Count the number of valid passports with cid field optional
 pub fn two(file_path: &str) -> usize {
-    read_file(file_path)
-        // double newline between passport entries
-        .split("\n\n")
-        // create list of key:value pairs
-        .map(|passport_str| {
-            passport_str
-                .split_whitespace()
-                .map(|pair| KeyValue::try_from(pair).unwrap())
-                .collect::<Vec<_>>()
-        })
-        .map(try_from)
+    read_fields(file_path)
+        .iter()
+        .map(|fields| try_from(key_values(fields)))
         .filter_map(Result::ok)
         .count()
 }
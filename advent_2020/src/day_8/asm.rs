@@ -0,0 +1,245 @@
+//! Assembler and disassembler for the handheld instruction set
+//!
+//! Source is one instruction per line. A `;` starts a comment, and a line may start with a
+//! `label:`. `jmp` and `nop` arguments can name a label instead of an offset, and are assembled to
+//! the offset from the instruction to the label.
+//!
+//! ```
+//! use advent_2020::day_8::{assemble, Disassembly};
+//!
+//! let program = assemble(
+//!     "start: acc +1 ; count\n\
+//!      nop +0\n\
+//!      jmp start",
+//! )
+//! .unwrap();
+//! let listing = Disassembly::new(&program).annotate(2, "back to start");
+//! assert_eq!(listing.to_string(), "acc +1\nnop +0\njmp -2 ; back to start\n");
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use parser::lexer::TokenKind;
+
+use super::{lex_instruction, Instruction, Program};
+
+/// return true if `name` can be used as a label
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// split a source line into its label, if any, and its instruction, if any
+fn split_line(line: &str) -> Result<(Option<&str>, Option<&str>), String> {
+    let line = line.split(';').next().unwrap_or_default().trim();
+    let (label, instruction) = match line.split_once(':') {
+        Some((label, instruction)) => (Some(label.trim()), instruction.trim()),
+        None => (None, line),
+    };
+    if let Some(label) = label.filter(|label| !is_label(label)) {
+        return Err(format!("Invalid label '{}'", label));
+    }
+
+    Ok((label, Some(instruction).filter(|instr| !instr.is_empty())))
+}
+
+/// parse an instruction, resolving label arguments relative to `pc`
+fn parse_instruction(
+    source: &str,
+    pc: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<Instruction, String> {
+    let (operation, argument) = lex_instruction(source)?;
+    if argument.kind != TokenKind::Ident {
+        return source.parse();
+    }
+
+    let target = labels
+        .get(argument.text)
+        .ok_or(format!("Undefined label '{}'", argument.text))?;
+    let offset = *target as isize - pc as isize;
+    match operation.text {
+        "jmp" => Ok(Instruction::Jmp(offset)),
+        "nop" => Ok(Instruction::Nop(offset)),
+        _ => Err(format!("{} can't take a label argument", operation.text)),
+    }
+}
+
+/// Assemble source into a program
+pub fn assemble(source: &str) -> Result<Program, String> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            split_line(line)
+                .map(|parts| (idx + 1, parts))
+                .map_err(|err| format!("Line {}: {}", idx + 1, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // first pass: find the instruction each label points to
+    let mut labels = HashMap::new();
+    let mut pc = 0;
+    for (line_number, (label, instruction)) in &lines {
+        if let Some(label) = label {
+            if labels.insert(*label, pc).is_some() {
+                return Err(format!("Line {}: Duplicate label '{}'", line_number, label));
+            }
+        }
+        if instruction.is_some() {
+            pc += 1;
+        }
+    }
+
+    // second pass: assemble the instructions
+    lines
+        .iter()
+        .filter_map(|(line_number, (_, instruction))| instruction.map(|instr| (line_number, instr)))
+        .enumerate()
+        .map(|(pc, (line_number, instruction))| {
+            parse_instruction(instruction, pc, &labels)
+                .map_err(|err| format!("Line {}: {}", line_number, err))
+        })
+        .collect()
+}
+
+/// Canonical listing of a program, one instruction per line, with optional annotations written as
+/// comments. The listing assembles back into the same program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disassembly<'a> {
+    program: &'a [Instruction],
+    annotations: BTreeMap<usize, String>,
+}
+
+impl<'a> Disassembly<'a> {
+    /// Create a listing of `program`
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Self {
+            program,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Annotate the instruction at `pc`. Annotations on the same instruction are joined
+    pub fn annotate(mut self, pc: usize, note: impl fmt::Display) -> Self {
+        let annotation = self.annotations.entry(pc).or_default();
+        if !annotation.is_empty() {
+            annotation.push_str(", ");
+        }
+        annotation.push_str(&note.to_string());
+        self
+    }
+
+    /// Annotate each instruction with the number of times it was visited, skipping unvisited ones
+    pub fn annotate_visits(self, visits: &[usize]) -> Self {
+        visits
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .fold(self, |listing, (pc, count)| {
+                listing.annotate(pc, format!("visited {}x", count))
+            })
+    }
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pc, instruction) in self.program.iter().enumerate() {
+            match self.annotations.get(&pc) {
+                Some(annotation) => writeln!(f, "{} ; {}", instruction, annotation)?,
+                None => writeln!(f, "{}", instruction)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::{LoopDetector, Tracer};
+
+    #[test]
+    fn assembles_labels() {
+        let msg = "should resolve labels to relative offsets";
+        let source = "
+            ; the day 8 example, with labels
+            nop +0
+        back:
+            acc +1
+            jmp skip
+        again:
+            acc +3
+            jmp back
+            acc -99
+        skip: acc +1
+            jmp again ; loops forever
+            acc +6
+        ";
+        let expected = super::super::read_program("input/8-t.txt");
+        assert_eq!(assemble(source), Ok(expected), "{}", msg);
+
+        let msg = "should resolve labels after the last instruction";
+        let expected = vec![Instruction::Nop(2), Instruction::Acc(1)];
+        let actual = assemble("nop end\nacc +1\nend:");
+        assert_eq!(actual, Ok(expected), "{}", msg);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let msg = "should report errors with line numbers";
+        let expected = Err("Line 2: Undefined label 'nowhere'".to_string());
+        assert_eq!(assemble("nop +0\njmp nowhere"), expected, "{}", msg);
+
+        let expected = Err("Line 3: Duplicate label 'a'".to_string());
+        assert_eq!(assemble("a:\nnop +0\na: nop +0"), expected, "{}", msg);
+
+        let expected = Err("Line 1: acc can't take a label argument".to_string());
+        assert_eq!(assemble("a: acc a"), expected, "{}", msg);
+
+        let expected = Err("Line 1: Invalid label '1a'".to_string());
+        assert_eq!(assemble("1a: nop +0"), expected, "{}", msg);
+
+        let expected = Err("Line 1: Unrecognized instruction: mul".to_string());
+        assert_eq!(assemble("mul +2"), expected, "{}", msg);
+    }
+
+    #[test]
+    fn disassembles() {
+        let msg = "should round trip a program through its listing";
+        let program = super::super::read_program("input/8-t.txt");
+        let listing = Disassembly::new(&program).to_string();
+        assert_eq!(assemble(&listing), Ok(program.clone()), "{}", msg);
+
+        let msg = "should annotate visit counts";
+        let mut computer = super::super::computer(program.clone());
+        let mut observers = (LoopDetector::default(), Tracer::default());
+        computer.run(&mut observers).unwrap();
+        let mut visits = vec![0; program.len()];
+        for (pc, _) in observers.1.executed() {
+            visits[*pc] += 1;
+        }
+
+        let expected = "\
+nop +0 ; visited 1x
+acc +1 ; visited 1x, loop start
+jmp +4 ; visited 1x
+acc +3 ; visited 1x
+jmp -3 ; visited 1x
+acc -99
+acc +1 ; visited 1x
+jmp -4 ; visited 1x
+acc +6
+";
+        let actual = Disassembly::new(&program)
+            .annotate_visits(&visits)
+            .annotate(computer.state().pc, "loop start")
+            .to_string();
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(assemble(&actual), Ok(program), "{}", msg);
+    }
+}
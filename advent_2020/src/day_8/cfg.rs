@@ -0,0 +1,273 @@
+//! Static control-flow analysis of handheld programs
+//!
+//! Every instruction has exactly one successor, so the control-flow graph is a set of paths that
+//! either run off the end of the program, jump outside of it, or end in a loop. Walking it
+//! backwards from the end of the program finds every instruction that terminates, without running
+//! the program at all.
+use std::collections::HashSet;
+
+use super::Instruction;
+
+/// A loop in a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    /// the instructions in the loop, in execution order starting from the lowest
+    pub instructions: Vec<usize>,
+    /// instructions in the loop that execution can enter from outside of it, including the start
+    /// of the program
+    pub entries: Vec<usize>,
+}
+
+/// The control-flow graph of a program. Instruction `len` stands for successful termination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// next instruction after each instruction. None if a jump leaves the program
+    successors: Vec<Option<usize>>,
+    /// next instruction after each jmp or nop, if it were flipped
+    flipped: Vec<Option<usize>>,
+    /// instructions leading to each instruction, including the end of the program
+    predecessors: Vec<Vec<usize>>,
+    /// true for each instruction, including the end of the program, that leads to termination
+    terminates: Vec<bool>,
+}
+
+impl ControlFlowGraph {
+    /// Build the control-flow graph of `program`
+    pub fn new(program: &[Instruction]) -> Self {
+        let len = program.len();
+        let target = |pc: usize, offset: isize| {
+            pc.checked_add_signed(offset)
+                .filter(|target| *target <= len)
+        };
+
+        let (successors, flipped): (Vec<_>, Vec<_>) = program
+            .iter()
+            .enumerate()
+            .map(|(pc, instruction)| match instruction {
+                Instruction::Acc(_) => (Some(pc + 1), None),
+                Instruction::Jmp(offset) => (target(pc, *offset), Some(pc + 1)),
+                Instruction::Nop(offset) => (Some(pc + 1), target(pc, *offset)),
+            })
+            .unzip();
+
+        let mut predecessors = vec![vec![]; len + 1];
+        for (pc, successor) in successors.iter().enumerate() {
+            if let Some(successor) = successor {
+                predecessors[*successor].push(pc);
+            }
+        }
+
+        // reverse reachability from the end of the program
+        let mut terminates = vec![false; len + 1];
+        terminates[len] = true;
+        let mut queue = vec![len];
+        while let Some(pc) = queue.pop() {
+            for predecessor in &predecessors[pc] {
+                if !terminates[*predecessor] {
+                    terminates[*predecessor] = true;
+                    queue.push(*predecessor);
+                }
+            }
+        }
+
+        Self {
+            successors,
+            flipped,
+            predecessors,
+            terminates,
+        }
+    }
+
+    /// Return the number of instructions in the program
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// true if the program has no instructions
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// Return the instruction executed after the one at `pc`, if it is inside the program
+    pub fn successor(&self, pc: usize) -> Option<usize> {
+        self.successors.get(pc).copied().flatten()
+    }
+
+    /// Return the instructions that lead to the one at `pc`
+    pub fn predecessors(&self, pc: usize) -> &[usize] {
+        self.predecessors.get(pc).map_or(&[], Vec::as_slice)
+    }
+
+    /// true if execution starting at `pc` runs off the end of the program
+    pub fn terminates(&self, pc: usize) -> bool {
+        self.terminates.get(pc).copied().unwrap_or(false)
+    }
+
+    /// Return the instructions executed from the start of the program, in order, until it
+    /// terminates, faults, or is about to repeat an instruction
+    pub fn execution_path(&self) -> Vec<usize> {
+        let mut visited = HashSet::new();
+        let mut path = vec![];
+        let mut pc = Some(0).filter(|_| !self.is_empty());
+        while let Some(current) = pc.filter(|pc| *pc < self.len() && visited.insert(*pc)) {
+            path.push(current);
+            pc = self.successors[current];
+        }
+
+        path
+    }
+
+    /// Return the only jmp or nop on the execution path that, when flipped, makes the program
+    /// terminate. None if the program already terminates, or no single flip works
+    pub fn repair(&self) -> Option<usize> {
+        if self.terminates(0) {
+            return None;
+        }
+
+        // a flipped instruction can't be on the path from its new successor to the end, since it
+        // would then already terminate. So that path is unchanged by the flip
+        self.execution_path()
+            .into_iter()
+            .find(|pc| self.flipped[*pc].is_some_and(|target| self.terminates[target]))
+    }
+
+    /// Return the instructions that can never execute
+    pub fn dead_code(&self) -> Vec<usize> {
+        let reachable: HashSet<usize> = self.execution_path().into_iter().collect();
+        (0..self.len())
+            .filter(|pc| !reachable.contains(pc))
+            .collect()
+    }
+
+    /// Return every loop in the program, ordered by their lowest instruction
+    pub fn loops(&self) -> Vec<Loop> {
+        // walk from each instruction, stopping at instructions seen on an earlier walk. Meeting
+        // an instruction from the same walk closes a loop
+        let mut walked_from = vec![None; self.len()];
+        let mut cycles = vec![];
+        for start in 0..self.len() {
+            let mut walk = vec![];
+            let mut pc = Some(start);
+            while let Some(current) = pc.filter(|pc| *pc < self.len()) {
+                match walked_from[current] {
+                    Some(walk_start) if walk_start == start => {
+                        let loop_start = walk.iter().position(|pc| *pc == current).unwrap();
+                        cycles.push(walk.split_off(loop_start));
+                        break;
+                    }
+                    Some(_) => break,
+                    None => {}
+                }
+                walked_from[current] = Some(start);
+                walk.push(current);
+                pc = self.successors[current];
+            }
+        }
+
+        let mut loops: Vec<Loop> = cycles
+            .into_iter()
+            .map(|mut instructions| {
+                let lowest = (0..instructions.len())
+                    .min_by_key(|idx| instructions[*idx])
+                    .unwrap_or(0);
+                instructions.rotate_left(lowest);
+
+                let mut entries: Vec<usize> = instructions
+                    .iter()
+                    .copied()
+                    .filter(|pc| {
+                        *pc == 0
+                            || self.predecessors[*pc]
+                                .iter()
+                                .any(|predecessor| !instructions.contains(predecessor))
+                    })
+                    .collect();
+                entries.sort_unstable();
+
+                Loop {
+                    instructions,
+                    entries,
+                }
+            })
+            .collect();
+        loops.sort_unstable_by_key(|found| found.instructions[0]);
+
+        loops
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day_8::assemble;
+
+    #[test]
+    fn finds_termination() {
+        let msg = "should find the instructions that lead to termination";
+        let program = super::super::read_program("input/8-t.txt");
+        let cfg = ControlFlowGraph::new(&program);
+        let actual: Vec<_> = (0..=program.len()).map(|pc| cfg.terminates(pc)).collect();
+        let expected = vec![
+            false, false, false, false, false, false, false, false, true, true,
+        ];
+        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(cfg.predecessors(1), &[0, 4], "{}", msg);
+    }
+
+    #[test]
+    fn repairs() {
+        let msg = "should find the instruction to flip";
+        let program = super::super::read_program("input/8-t.txt");
+        let cfg = ControlFlowGraph::new(&program);
+        assert_eq!(cfg.repair(), Some(7), "{}", msg);
+
+        let msg = "should not repair a program that terminates";
+        let program = assemble("nop +1\nacc +1").unwrap();
+        assert_eq!(ControlFlowGraph::new(&program).repair(), None, "{}", msg);
+
+        let msg = "should not repair a program no single flip fixes";
+        let program = assemble("a: acc +1\njmp a\njmp a").unwrap();
+        assert_eq!(ControlFlowGraph::new(&program).repair(), None, "{}", msg);
+    }
+
+    #[test]
+    fn finds_dead_code_and_loops() {
+        let msg = "should find instructions that never execute";
+        let program = super::super::read_program("input/8-t.txt");
+        let cfg = ControlFlowGraph::new(&program);
+        assert_eq!(cfg.execution_path(), vec![0, 1, 2, 6, 7, 3, 4], "{}", msg);
+        assert_eq!(cfg.dead_code(), vec![5, 8], "{}", msg);
+
+        let msg = "should find loops and their entry points";
+        let expected = vec![Loop {
+            instructions: vec![1, 2, 6, 7, 3, 4],
+            entries: vec![1, 6],
+        }];
+        assert_eq!(cfg.loops(), expected, "{}", msg);
+
+        let program = assemble(
+            "
+            top: nop +0
+                 jmp skip
+            dead: jmp dead
+            skip: acc +1
+                 jmp top
+                 jmp -4
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::new(&program);
+        let expected = vec![
+            Loop {
+                instructions: vec![0, 1, 3, 4],
+                entries: vec![0, 1],
+            },
+            Loop {
+                instructions: vec![2],
+                entries: vec![],
+            },
+        ];
+        assert_eq!(cfg.loops(), expected, "{}", msg);
+        assert_eq!(cfg.dead_code(), vec![2, 5], "{}", msg);
+    }
+}
@@ -1,13 +1,23 @@
 //! Solutions to 2020 day 8
 //! --- Day 8: Handheld Halting ---
-use std::collections::HashSet;
+use std::fmt;
 use std::str::FromStr;
 
+use parser::lexer::{Lexer, Token, TokenKind};
+
 use crate::day_1::read_file;
+use crate::vm::{self, Control, LoopDetector, Machine, Status};
+
+mod asm;
+mod cfg;
+mod trace;
+pub use asm::{assemble, Disassembly};
+pub use cfg::{ControlFlowGraph, Loop};
+pub use trace::{ExecutionTrace, Replay, TraceEntry};
 
 /// simple computer operating instruction
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Instruction {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction {
     /// increases or decreases the accumulator by the value given in the argument.
     ///
     /// For example, acc +7 would increase the accumulator by 7. The accumulator starts at 0. After
@@ -26,215 +36,124 @@ enum Instruction {
     Nop(isize),
 }
 
+/// split an instruction into its operation and argument tokens
+fn lex_instruction(source: &str) -> Result<(Token<'_>, Token<'_>), String> {
+    let tokens = Lexer::new().with_signed_numbers(true).tokenize(source);
+    match tokens.as_slice() {
+        [operation, argument] if operation.kind == TokenKind::Ident => Ok((*operation, *argument)),
+        [_] => Err("Instruction parse failure: missing argument".to_string()),
+        _ => Err(format!(
+            "Instruction parse failure: expected an operation and an argument in '{}'",
+            source
+        )),
+    }
+}
+
 impl FromStr for Instruction {
     type Err = String;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let (instruction, argument) = value
-            .split_once(" ")
-            .ok_or("Instruction parse failure: missing separator")?;
-        let argument: isize = argument
-            .parse()
-            .map_err(|err| format!("failed to parse argument '{}': {:?}", argument, err))?;
-
-        match instruction {
+        let (operation, argument) = lex_instruction(value)?;
+        let argument: isize = match argument.kind {
+            TokenKind::Number => argument
+                .text
+                .parse()
+                .map_err(|err| format!("failed to parse argument '{}': {:?}", argument.text, err)),
+            _ => Err(format!("failed to parse argument '{}'", argument.text)),
+        }?;
+
+        match operation.text {
             "acc" => Ok(Self::Acc(argument)),
             "jmp" => Ok(Self::Jmp(argument)),
             "nop" => Ok(Self::Nop(argument)),
-            _ => Err(format!("Unrecognized instruction: {}", instruction)),
+            _ => Err(format!("Unrecognized instruction: {}", operation.text)),
         }
     }
 }
 
-/// a list of instructions that together comprise a program
-type Program = Vec<Instruction>;
-
-/// read a set of [`Instruction`]s from a file
-fn read_program(file_path: &str) -> Program {
-    read_file(file_path)
-        .lines()
-        .map(|instruction| instruction.parse().unwrap())
-        .collect()
-}
-
-/// the state (registers?) of a simple computer system
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
-struct State {
-    /// program counter
-    pc: usize,
-    /// accumulator
-    acc: isize,
-}
-
-#[derive(Debug, PartialEq)]
-enum Flag {
-    Idle,
-    Complete,
-    Halted,
-}
-
-impl Default for Flag {
-    fn default() -> Self {
-        Flag::Idle
-    }
-}
-
-/// a simple computer
-#[derive(Debug, Default, PartialEq)]
-struct Computer {
-    state: State,
-    /// a [Program] held in memory
-    program: Program,
-    /// previously visited memory locations
-    visited: HashSet<usize>,
-    /// flag set if program execution causes a halt
-    flag: Flag,
-}
-
-impl Computer {
-    /// Create an instance with the supplied program in memory
-    pub fn with_program(program: Program) -> Self {
-        Self {
-            program,
-            ..Default::default()
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Acc(argument) => write!(f, "acc {:+}", argument),
+            Self::Jmp(argument) => write!(f, "jmp {:+}", argument),
+            Self::Nop(argument) => write!(f, "nop {:+}", argument),
         }
     }
+}
 
-    // halt any further instruction execution
-    fn halt(&mut self) {
-        self.flag = Flag::Halted;
-    }
-
-    /// Execute the next instruction and return a copy of the next system state
-    pub fn step(&mut self) -> Option<State> {
-        if self.program.is_empty() || self.flag != Flag::Idle {
-            return None;
-        }
-        // The program is supposed to terminate by attempting to execute an instruction immediately
-        // after the last instruction in the file.
-        if self.state.pc >= self.program.len() {
-            self.flag = Flag::Complete;
-            return None;
-        }
-
-        // update instruction visit list
-        self.visited.insert(self.state.pc);
-        // unsafe indexing. if it fails, its the other programmers fault, right?
-        let instruction = self.program[self.state.pc];
-        match instruction {
+impl vm::Instruction for Instruction {
+    fn execute(&self, registers: &mut [isize]) -> Result<Control, String> {
+        match self {
             Instruction::Acc(argument) => {
-                let pc = self.state.pc + 1;
-                if self.visited.contains(&pc) {
-                    self.halt();
-                    return None;
-                }
-
-                self.state = State {
-                    pc,
-                    acc: self.state.acc + argument,
-                }
-            }
-            Instruction::Jmp(argument) => {
-                // sketchy typecasting
-                let pc = (self.state.pc as isize + argument) as usize;
-                if self.visited.contains(&pc) {
-                    self.halt();
-                    return None;
-                }
-
-                self.state = State { pc, ..self.state }
-            }
-            Instruction::Nop(_) => {
-                let pc = self.state.pc + 1;
-                if self.visited.contains(&pc) {
-                    self.halt();
-                    return None;
-                }
-
-                self.state = State {
-                    pc: self.state.pc + 1,
-                    ..self.state
-                }
+                registers[ACC] = registers[ACC]
+                    .checked_add(*argument)
+                    .ok_or("accumulator overflow")?;
+                Ok(Control::Next)
             }
+            Instruction::Jmp(argument) => Ok(Control::Jump(*argument)),
+            Instruction::Nop(_) => Ok(Control::Next),
         }
-
-        self.state.into()
     }
+}
 
-    /// Execute instructions until the computer halts, returning the last state
-    pub fn run(&mut self) -> Option<State> {
-        self.last()
-    }
+/// a list of instructions that together comprise a program
+pub type Program = Vec<Instruction>;
+
+/// read a set of [`Instruction`]s from a file
+fn read_program(file_path: &str) -> Program {
+    assemble(&read_file(file_path)).expect("Failed to assemble program")
 }
 
-impl Iterator for Computer {
-    type Item = State;
+/// register holding the accumulator
+const ACC: usize = 0;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.step()
-    }
+/// a simple computer, with a single accumulator register
+type Computer = Machine<Instruction>;
+
+/// Create a computer with the supplied program in memory
+fn computer(program: Program) -> Computer {
+    Machine::new(program, 1)
 }
 
 /// return the accumulator value before repeating an instruction
 pub fn one(file_path: &str) -> isize {
     let program = read_program(file_path);
-    let mut computer = Computer::with_program(program);
+    let mut computer = computer(program);
 
-    computer.run().map(|state| state.acc).unwrap()
+    computer
+        .run(&mut LoopDetector::default())
+        .expect("Program faulted");
+    computer.state().registers[ACC]
 }
 
-/// Execute a program starting at the provided program counter, and return the state flag
-fn test_branch(program: Program, pc: usize) -> Flag {
-    let mut compute = Computer::with_program(program);
-    compute.state.pc = pc;
-
-    compute.run();
-    compute.flag
-}
-
-/// Fix a single jmp or nop instruction by walking every potential branch (and feel quite bad about
-/// the inelegance of it, despite being aware that early optimization may be the worse crime)
-fn repair_program(program: &[Instruction]) -> Program {
+/// Fix the single jmp or nop instruction that keeps the program from terminating
+fn repair_program(program: &[Instruction]) -> Result<Program, String> {
+    let pc = ControlFlowGraph::new(program)
+        .repair()
+        .ok_or("no single jmp/nop flip makes the program terminate")?;
     let mut fixed = program.to_vec();
+    fixed[pc] = match fixed[pc] {
+        Instruction::Jmp(arg) => Instruction::Nop(arg),
+        Instruction::Nop(arg) => Instruction::Jmp(arg),
+        instruction => instruction,
+    };
 
-    // locate cycle
-    let mut compute = Computer::with_program(program.to_vec());
-    compute.run();
-    let start = compute.state.pc;
-
-    // Test modified branches for completion
-    for idx in (0..fixed.len()).rev() {
-        match fixed[idx] {
-            Instruction::Jmp(arg) => {
-                let mut possible = program.to_vec();
-                possible[idx] = Instruction::Nop(arg);
-                if let Flag::Complete = test_branch(possible, start) {
-                    fixed[idx] = Instruction::Nop(arg);
-                    break;
-                }
-            }
-            Instruction::Nop(arg) => {
-                let mut possible = program.to_vec();
-                possible[idx] = Instruction::Jmp(arg);
-                if let Flag::Complete = test_branch(possible, start) {
-                    fixed[idx] = Instruction::Jmp(arg);
-                    break;
-                }
-            }
-            _ => {}
-        }
-    }
-
-    fixed
+    Ok(fixed)
 }
 
 /// fix a corrupted program, execute the program, and return the accumulator value
 pub fn two(file_path: &str) -> isize {
     let program = read_program(file_path);
-    let program = repair_program(&program);
-    let mut computer = Computer::with_program(program);
-
-    computer.run().map(|state| state.acc).unwrap()
+    let program = repair_program(&program).expect("Failed to repair program");
+    let mut computer = computer(program);
+
+    let status = computer
+        .run(&mut LoopDetector::default())
+        .expect("Program faulted");
+    if let Status::Interrupted(interrupt) = status {
+        panic!("Repaired program still loops: {:?}", interrupt);
+    }
+    computer.state().registers[ACC]
 }
 
 #[cfg(test)]
@@ -255,6 +174,12 @@ mod test {
         let expected = Instruction::Acc(-99);
         let actual: Instruction = "acc -99".parse().unwrap();
         assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should reject malformed instructions";
+        let expected = Err("Instruction parse failure: missing argument".to_string());
+        assert_eq!("nop".parse::<Instruction>(), expected, "{}", msg);
+        assert!("acc +1 +2".parse::<Instruction>().is_err(), "{}", msg);
+        assert!("acc x".parse::<Instruction>().is_err(), "{}", msg);
     }
 
     #[test]
@@ -264,7 +189,12 @@ mod test {
         let mut expected = read_program("input/8-t.txt");
         expected[7] = Instruction::Nop(-4);
         let actual = repair_program(&program);
-        assert_eq!(actual, expected, "{}", msg);
+        assert_eq!(actual, Ok(expected), "{}", msg);
+
+        let msg = "should report programs no single flip can fix";
+        let program = vec![Instruction::Jmp(0), Instruction::Jmp(-1)];
+        let expected = Err("no single jmp/nop flip makes the program terminate".to_string());
+        assert_eq!(repair_program(&program), expected, "{}", msg);
     }
 
     #[test]
@@ -0,0 +1,329 @@
+//! Execution tracing and replay
+//!
+//! An [`ExecutionTrace`] is an [`Observer`] that records every instruction a computer executes.
+//! A [`Replay`] steps back and forth through a recorded trace, recovering the computer's state
+//! before and after each step.
+//!
+//! ```
+//! use advent_2020::day_8::{assemble, ExecutionTrace};
+//!
+//! let program = assemble("acc +2\nloop: acc -1\njmp loop").unwrap();
+//! let (_, trace) = ExecutionTrace::record(program);
+//!
+//! // the loop starts at the instruction the computer stopped before repeating
+//! let loop_start = trace.final_pc().unwrap();
+//! let mut replay = trace.replay();
+//! replay.seek(trace.first_visit(loop_start).unwrap());
+//! assert_eq!(replay.state().registers, vec![2]);
+//! ```
+use std::fmt;
+
+use super::{computer, Instruction, Program, ACC};
+use crate::vm::{Interrupt, LoopDetector, Observer, State, Status};
+
+/// A single executed instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// number of instructions executed before this one
+    pub step: usize,
+    /// program counter of the instruction
+    pub pc: usize,
+    /// instruction executed
+    pub instruction: Instruction,
+    /// accumulator before executing the instruction
+    pub acc_before: isize,
+    /// accumulator after executing the instruction
+    pub acc_after: isize,
+    /// program counter after executing the instruction
+    pub next_pc: usize,
+}
+
+impl TraceEntry {
+    /// serialize as a JSON object
+    fn to_json(self) -> String {
+        format!(
+            r#"{{"step":{},"pc":{},"instruction":"{}","acc_before":{},"acc_after":{},"next_pc":{}}}"#,
+            self.step, self.pc, self.instruction, self.acc_before, self.acc_after, self.next_pc
+        )
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>5} {:>5}  {:<8} acc {} -> {}",
+            self.step,
+            self.pc,
+            self.instruction.to_string(),
+            self.acc_before,
+            self.acc_after
+        )
+    }
+}
+
+/// A record of every instruction executed by a computer
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExecutionTrace {
+    entries: Vec<TraceEntry>,
+    /// accumulator before the instruction being executed
+    pending: isize,
+}
+
+impl ExecutionTrace {
+    /// Run `program` until it terminates or is about to repeat an instruction, recording each
+    /// instruction executed. Returns the final status with the trace, which covers the
+    /// instructions executed before a fault if the status is [`Status::Faulted`]
+    pub fn record(program: Program) -> (Status, Self) {
+        let mut trace = Self::default();
+        let status = match computer(program).run(&mut (LoopDetector::default(), &mut trace)) {
+            Ok(status) => status,
+            Err(err) => Status::Faulted(err),
+        };
+
+        (status, trace)
+    }
+
+    /// Return the recorded instructions, in execution order
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Return the number of instructions executed
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// true if no instructions have been executed
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the program counter after the last executed instruction
+    pub fn final_pc(&self) -> Option<usize> {
+        self.entries.last().map(|entry| entry.next_pc)
+    }
+
+    /// Return the step the instruction at `pc` was first executed
+    pub fn first_visit(&self, pc: usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|entry| entry.pc == pc)
+            .map(|entry| entry.step)
+    }
+
+    /// Return a replay positioned before the first step
+    pub fn replay(&self) -> Replay<'_> {
+        Replay {
+            trace: self,
+            position: 0,
+        }
+    }
+
+    /// Serialize the trace as a JSON array of entries
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries.iter().map(|entry| entry.to_json()).collect();
+        format!("[{}]", entries.join(","))
+    }
+}
+
+impl Observer<Instruction> for ExecutionTrace {
+    fn before(
+        &mut self,
+        _pc: usize,
+        _instruction: &Instruction,
+        state: &State,
+    ) -> Option<Interrupt> {
+        self.pending = state.registers[ACC];
+        None
+    }
+
+    fn after(&mut self, pc: usize, instruction: &Instruction, state: &State) {
+        self.entries.push(TraceEntry {
+            step: self.entries.len(),
+            pc,
+            instruction: *instruction,
+            acc_before: self.pending,
+            acc_after: state.registers[ACC],
+            next_pc: state.pc,
+        });
+    }
+}
+
+/// Text listing of the trace, one executed instruction per line
+impl fmt::Display for ExecutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, " step    pc  instruction")?;
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A cursor over a recorded trace, positioned between steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replay<'a> {
+    trace: &'a ExecutionTrace,
+    /// number of steps replayed
+    position: usize,
+}
+
+impl<'a> Replay<'a> {
+    /// Return the number of steps replayed
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Return the computer's state at the current position
+    pub fn state(&self) -> State {
+        let entries = &self.trace.entries;
+        let (pc, acc) = match entries.get(self.position) {
+            Some(entry) => (entry.pc, entry.acc_before),
+            None => entries
+                .last()
+                .map_or((0, 0), |entry| (entry.next_pc, entry.acc_after)),
+        };
+
+        State {
+            pc,
+            registers: vec![acc],
+        }
+    }
+
+    /// Replay the next step, returning it
+    pub fn step_forward(&mut self) -> Option<&'a TraceEntry> {
+        let entry = self.trace.entries.get(self.position)?;
+        self.position += 1;
+        Some(entry)
+    }
+
+    /// Undo the previous step, returning it
+    pub fn step_back(&mut self) -> Option<&'a TraceEntry> {
+        self.position = self.position.checked_sub(1)?;
+        self.trace.entries.get(self.position)
+    }
+
+    /// Move to the position before `step`, or the end of the trace if `step` is past it
+    pub fn seek(&mut self, step: usize) {
+        self.position = step.min(self.trace.len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> (Status, ExecutionTrace) {
+        ExecutionTrace::record(super::super::read_program("input/8-t.txt"))
+    }
+
+    #[test]
+    fn records() {
+        let msg = "should record every instruction executed until a loop";
+        let (status, trace) = example();
+        assert_eq!(status, Status::Interrupted(Interrupt::Loop(1)), "{}", msg);
+        let pcs: Vec<_> = trace.entries().iter().map(|entry| entry.pc).collect();
+        assert_eq!(pcs, vec![0, 1, 2, 6, 7, 3, 4], "{}", msg);
+
+        let expected = TraceEntry {
+            step: 5,
+            pc: 3,
+            instruction: Instruction::Acc(3),
+            acc_before: 2,
+            acc_after: 5,
+            next_pc: 4,
+        };
+        assert_eq!(trace.entries()[5], expected, "{}", msg);
+        assert_eq!(trace.final_pc(), Some(1), "{}", msg);
+        assert_eq!(trace.first_visit(6), Some(3), "{}", msg);
+
+        let msg = "should keep the trace when the program faults";
+        let program = vec![Instruction::Acc(2), Instruction::Jmp(-2)];
+        let (status, trace) = ExecutionTrace::record(program);
+        let expected = Status::Faulted("Jump from 1 by -2 leaves the program".to_string());
+        assert_eq!(status, expected, "{}", msg);
+        assert_eq!(trace.len(), 1, "{}", msg);
+        assert_eq!(trace.entries()[0].acc_after, 2, "{}", msg);
+    }
+
+    #[test]
+    fn exports() {
+        let msg = "should export as JSON";
+        let (_, trace) = example();
+        let json = trace.to_json();
+        let expected_start = r#"[{"step":0,"pc":0,"instruction":"nop +0","acc_before":0,"acc_after":0,"next_pc":1},"#;
+        let expected_end =
+            r#"{"step":6,"pc":4,"instruction":"jmp -3","acc_before":5,"acc_after":5,"next_pc":1}]"#;
+        assert!(json.starts_with(expected_start), "{}", msg);
+        assert!(json.ends_with(expected_end), "{}", msg);
+
+        let msg = "should export as text";
+        let text = trace.to_string();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(" step    pc  instruction"), "{}", msg);
+        assert_eq!(
+            lines.nth(2),
+            Some("    2     2  jmp +4   acc 1 -> 1"),
+            "{}",
+            msg
+        );
+        assert_eq!(text.lines().count(), 8, "{}", msg);
+    }
+
+    #[test]
+    fn replays() {
+        let msg = "should step back and forth through the trace";
+        let (_, trace) = example();
+        let mut replay = trace.replay();
+        assert_eq!(replay.step_back(), None, "{}", msg);
+        assert_eq!(
+            replay.state(),
+            State {
+                pc: 0,
+                registers: vec![0]
+            },
+            "{}",
+            msg
+        );
+
+        replay.seek(100);
+        assert_eq!(replay.position(), 7, "{}", msg);
+        assert_eq!(
+            replay.state(),
+            State {
+                pc: 1,
+                registers: vec![5]
+            },
+            "{}",
+            msg
+        );
+        assert_eq!(replay.step_forward(), None, "{}", msg);
+
+        let entry = replay.step_back().unwrap();
+        assert_eq!(entry.pc, 4, "{}", msg);
+        assert_eq!(
+            replay.state(),
+            State {
+                pc: 4,
+                registers: vec![5]
+            },
+            "{}",
+            msg
+        );
+        let entry = replay.step_back().unwrap();
+        assert_eq!(entry.instruction, Instruction::Acc(3), "{}", msg);
+        assert_eq!(
+            replay.state(),
+            State {
+                pc: 3,
+                registers: vec![2]
+            },
+            "{}",
+            msg
+        );
+        assert_eq!(replay.step_forward(), Some(entry), "{}", msg);
+        assert_eq!(replay.position(), 6, "{}", msg);
+    }
+}
@@ -0,0 +1,477 @@
+//! Shared 2 dimensional grid
+//!
+//! Several puzzles (days 3, 11, 20) work on a rectangular map of cells read from text. [`Grid`]
+//! stores the cells in a single row-major `Vec`, indexed by `(row, col)`.
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+/// A `(row, col)` position within a [`Grid`]
+pub type Position = (usize, usize);
+
+/// A `(row, col)` step between positions
+pub type Offset = (isize, isize);
+
+/// The 4 orthogonal directions: up, right, down, left
+pub const DIRECTIONS_4: [Offset; 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// The 8 orthogonal and diagonal directions, clockwise starting from up
+pub const DIRECTIONS_8: [Offset; 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+/// A rectangular grid of cells
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Create a grid with every cell set to `value`
+    pub fn new(width: usize, height: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    /// Create a grid from a list of rows. Fails if the rows are not all the same length
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, String> {
+        let height = rows.len();
+        let width = rows.first().map(Vec::len).unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * height);
+        for (idx, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return Err(format!(
+                    "Row {} has length {}, expected {}",
+                    idx,
+                    row.len(),
+                    width
+                ));
+            }
+            cells.extend(row);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    /// Convert back into a list of rows
+    pub fn into_rows(self) -> Vec<Vec<T>> {
+        let width = self.width.max(1);
+        let mut cells = self.cells.into_iter();
+        (0..self.height)
+            .map(|_| cells.by_ref().take(width).collect())
+            .collect()
+    }
+
+    /// Number of columns
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// return true if the position is within the grid
+    pub fn contains(&self, (row, col): Position) -> bool {
+        row < self.height && col < self.width
+    }
+
+    /// Return a reference to the cell at `position`, or None if it is out of bounds
+    pub fn get(&self, position: Position) -> Option<&T> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.cells.get(position.0 * self.width + position.1)
+    }
+
+    /// Return a mutable reference to the cell at `position`, or None if it is out of bounds
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut T> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.cells.get_mut(position.0 * self.width + position.1)
+    }
+
+    /// Return a reference to the cell at `(row, col)`, wrapping around each edge as if the grid
+    /// repeats forever in every direction. None only if the grid is empty
+    pub fn get_wrapping(&self, row: isize, col: isize) -> Option<&T> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let row = row.rem_euclid(self.height as isize) as usize;
+        let col = col.rem_euclid(self.width as isize) as usize;
+        self.get((row, col))
+    }
+
+    /// Return a single row
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    /// Iterate over the rows
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Iterate over the cells in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    /// Iterate over the positions of every cell in row-major order
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| (row, col)))
+    }
+
+    /// Iterate over each cell along with its position
+    pub fn indexed_iter(&self) -> impl Iterator<Item = (Position, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    /// Apply `f` to each cell, producing a new grid of the same size
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Step from `position` by `offset`, returning None if that leaves the grid
+    pub fn offset(&self, (row, col): Position, (d_row, d_col): Offset) -> Option<Position> {
+        let row = (row as isize).checked_add(d_row)?;
+        let col = (col as isize).checked_add(d_col)?;
+        if row < 0 || col < 0 {
+            return None;
+        }
+        let position = (row as usize, col as usize);
+        self.contains(position).then_some(position)
+    }
+
+    /// Iterate over the in-bounds positions one step from `position` in each of the `directions`
+    pub fn neighbors<'a>(
+        &'a self,
+        position: Position,
+        directions: &'a [Offset],
+    ) -> impl Iterator<Item = Position> + 'a {
+        directions
+            .iter()
+            .filter_map(move |direction| self.offset(position, *direction))
+    }
+
+    /// Iterate over the (up to) 4 orthogonally adjacent positions
+    pub fn neighbors_4(&self, position: Position) -> impl Iterator<Item = Position> + '_ {
+        self.neighbors(position, &DIRECTIONS_4)
+    }
+
+    /// Iterate over the (up to) 8 orthogonally or diagonally adjacent positions
+    pub fn neighbors_8(&self, position: Position) -> impl Iterator<Item = Position> + '_ {
+        self.neighbors(position, &DIRECTIONS_8)
+    }
+
+    /// Iterate over the positions along a straight line from `position` (exclusive) in
+    /// `direction`, until the line leaves the grid
+    pub fn ray(
+        &self,
+        position: Position,
+        direction: Offset,
+    ) -> impl Iterator<Item = Position> + '_ {
+        let mut current = Some(position);
+        std::iter::from_fn(move || {
+            current = self.offset(current?, direction);
+            current
+        })
+    }
+
+    /// Iterate over one ray in each of the 8 directions
+    pub fn rays(
+        &self,
+        position: Position,
+    ) -> impl Iterator<Item = impl Iterator<Item = Position> + '_> + '_ {
+        DIRECTIONS_8
+            .iter()
+            .map(move |direction| self.ray(position, *direction))
+    }
+
+    /// Return a new grid with cells chosen by `source`, which maps each new position to a
+    /// position in this grid
+    fn remap(&self, width: usize, height: usize, source: impl Fn(Position) -> Position) -> Self
+    where
+        T: Clone,
+    {
+        let cells = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|position| self[source(position)].clone())
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Return the grid rotated clockwise by 90 degrees `units` times
+    pub fn rotate(&self, units: usize) -> Self
+    where
+        T: Clone,
+    {
+        let (width, height) = (self.width, self.height);
+        match units % 4 {
+            1 => self.remap(height, width, |(row, col)| (height - 1 - col, row)),
+            2 => self.remap(width, height, |(row, col)| {
+                (height - 1 - row, width - 1 - col)
+            }),
+            3 => self.remap(height, width, |(row, col)| (col, width - 1 - row)),
+            _ => self.clone(),
+        }
+    }
+
+    /// Return the grid flipped top to bottom
+    pub fn flip_vertical(&self) -> Self
+    where
+        T: Clone,
+    {
+        let height = self.height;
+        self.remap(self.width, height, |(row, col)| (height - 1 - row, col))
+    }
+
+    /// Return the grid flipped left to right
+    pub fn flip_horizontal(&self) -> Self
+    where
+        T: Clone,
+    {
+        let width = self.width;
+        self.remap(width, self.height, |(row, col)| (row, width - 1 - col))
+    }
+
+    /// Return the grid flipped across its main diagonal
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.remap(self.height, self.width, |(row, col)| (col, row))
+    }
+
+    /// Iterate over the 8 symmetries of the grid (the dihedral group): the 4 rotations, followed
+    /// by the 4 rotations of the horizontally flipped grid
+    pub fn dihedral(&self) -> impl Iterator<Item = Self> + '_
+    where
+        T: Clone,
+    {
+        let flipped = self.flip_horizontal();
+        (0..4)
+            .map(move |units| self.rotate(units))
+            .chain((0..4).map(move |units| flipped.rotate(units)))
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &Self::Output {
+        self.get(position)
+            .unwrap_or_else(|| panic!("Position {:?} is outside the grid", position))
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, position: Position) -> &mut Self::Output {
+        self.get_mut(position)
+            .unwrap_or_else(|| panic!("Position {:?} is outside the grid", position))
+    }
+}
+
+/// parse a grid of cells, one char per cell and one line per row
+impl<T> FromStr for Grid<T>
+where
+    T: From<char>,
+{
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let rows = input
+            .lines()
+            .map(|line| line.chars().map(From::from).collect())
+            .collect();
+        Self::from_rows(rows)
+    }
+}
+
+impl<T> fmt::Display for Grid<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, row) in self.rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn abcd() -> Grid<char> {
+        "ab\ncd".parse().unwrap()
+    }
+
+    #[test]
+    fn parses() {
+        let msg = "should parse a grid from text";
+
+        let grid: Grid<char> = "abc\ndef".parse().unwrap();
+        assert_eq!(grid.width(), 3, "{}", msg);
+        assert_eq!(grid.height(), 2, "{}", msg);
+        assert_eq!(grid[(1, 0)], 'd', "{}", msg);
+        assert_eq!(grid.to_string(), "abc\ndef", "{}", msg);
+
+        let expected = Err("Row 1 has length 2, expected 3".to_string());
+        let actual = "abc\nde".parse::<Grid<char>>();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn bounds() {
+        let msg = "should return None for positions outside the grid";
+
+        let grid = abcd();
+        assert_eq!(grid.get((1, 1)), Some(&'d'), "{}", msg);
+        assert_eq!(grid.get((0, 2)), None, "{}", msg);
+        assert_eq!(grid.get((2, 0)), None, "{}", msg);
+    }
+
+    #[test]
+    fn wraps() {
+        let msg = "should wrap around each edge";
+
+        let grid = abcd();
+        assert_eq!(grid.get_wrapping(0, 2), Some(&'a'), "{}", msg);
+        assert_eq!(grid.get_wrapping(5, 7), Some(&'d'), "{}", msg);
+        assert_eq!(grid.get_wrapping(-1, -1), Some(&'d'), "{}", msg);
+    }
+
+    #[test]
+    fn neighbors() {
+        let msg = "should return the in-bounds adjacent positions";
+
+        let grid = Grid::new(3, 3, 0);
+        let expected = vec![(0, 1), (1, 2), (2, 1), (1, 0)];
+        let actual: Vec<_> = grid.neighbors_4((1, 1)).collect();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = vec![(0, 1), (1, 1), (1, 0)];
+        let actual: Vec<_> = grid.neighbors_8((0, 0)).collect();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn casts_rays() {
+        let msg = "should return positions along a line until it leaves the grid";
+
+        let grid = Grid::new(4, 3, 0);
+        let expected = vec![(1, 2), (2, 3)];
+        let actual: Vec<_> = grid.ray((0, 1), (1, 1)).collect();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = vec![0, 0, 2, 2, 2, 1, 1, 0];
+        let actual: Vec<_> = grid.rays((0, 1)).map(Iterator::count).collect();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn rotates() {
+        let grid = abcd();
+        let expected: Grid<char> = "ca\ndb".parse().unwrap();
+        assert_eq!(grid.rotate(1), expected);
+
+        let expected: Grid<char> = "dc\nba".parse().unwrap();
+        assert_eq!(grid.rotate(2), expected);
+
+        let expected: Grid<char> = "bd\nac".parse().unwrap();
+        assert_eq!(grid.rotate(3), expected);
+
+        assert_eq!(grid.rotate(0), grid);
+
+        let expected: Grid<char> = "ca\ndb".parse().unwrap();
+        assert_eq!(grid.rotate(5), expected);
+
+        let msg = "should rotate non-square grids";
+        let grid: Grid<char> = "abc\ndef".parse().unwrap();
+        let expected: Grid<char> = "da\neb\nfc".parse().unwrap();
+        assert_eq!(grid.rotate(1), expected, "{}", msg);
+        let expected: Grid<char> = "cf\nbe\nad".parse().unwrap();
+        assert_eq!(grid.rotate(3), expected, "{}", msg);
+    }
+
+    #[test]
+    fn flips() {
+        let msg = "should flip the grid";
+
+        let grid: Grid<char> = "abc\ndef".parse().unwrap();
+        let expected: Grid<char> = "def\nabc".parse().unwrap();
+        assert_eq!(grid.flip_vertical(), expected, "{}", msg);
+
+        let expected: Grid<char> = "cba\nfed".parse().unwrap();
+        assert_eq!(grid.flip_horizontal(), expected, "{}", msg);
+
+        let expected: Grid<char> = "ad\nbe\ncf".parse().unwrap();
+        assert_eq!(grid.transpose(), expected, "{}", msg);
+    }
+
+    #[test]
+    fn symmetries() {
+        let msg = "should produce the 8 distinct symmetries of an asymmetric grid";
+
+        let grid: Grid<char> = "abc\ndef\nghi".parse().unwrap();
+        let mut variants: Vec<String> = grid.dihedral().map(|grid| grid.to_string()).collect();
+        variants.sort();
+        variants.dedup();
+        assert_eq!(variants.len(), 8, "{}", msg);
+        assert!(variants.contains(&grid.transpose().to_string()), "{}", msg);
+        assert!(
+            variants.contains(&grid.flip_vertical().to_string()),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn round_trips_rows() {
+        let msg = "should convert to and from a list of rows";
+
+        let rows = vec![vec![1, 2], vec![3, 4], vec![5, 6]];
+        let grid = Grid::from_rows(rows.clone()).unwrap();
+        let actual: Vec<Vec<i32>> = grid.rows().map(<[i32]>::to_vec).collect();
+        assert_eq!(actual, rows, "{}", msg);
+        assert_eq!(grid.into_rows(), rows, "{}", msg);
+    }
+}
@@ -1,6 +1,8 @@
 #![deny(missing_debug_implementations)]
 #![deny(missing_docs)]
 //! Advent of Code 2020 Solutions
+pub mod assignment;
+pub mod automaton;
 pub mod day_1;
 pub mod day_10;
 pub mod day_11;
@@ -26,3 +28,6 @@ pub mod day_6;
 pub mod day_7;
 pub mod day_8;
 pub mod day_9;
+pub mod grid;
+pub mod math;
+pub mod vm;
@@ -28,6 +28,22 @@ macro_rules! show {
     };
 }
 
+/// parse an input file up front, printing any failure as a diagnostic and exiting
+macro_rules! check {
+    ($file: literal, $fn: path) => {
+        let input_path = format!("{}/input/{}.txt", get_root_dir().display(), $file);
+        let source = day_1::read_file(&input_path);
+        if let Err(err) = $fn(&source) {
+            let diagnostic = err
+                .to_diagnostic()
+                .with_file_name(format!("input/{}.txt", $file))
+                .with_context(1);
+            eprint!("{}", diagnostic.render(&source));
+            std::process::exit(1);
+        }
+    };
+}
+
 fn main() {
     show!("1-1", "Product", "1-1", day_1::one);
     show!("1-2", "Product", "1-1", day_1::two);
@@ -35,6 +51,7 @@ fn main() {
     show!("2-2", "Valid passwords", "2-1", day_2::two);
     show!("3-1", "Trees encountered", "3-1", day_3::one);
     show!("3-2", "Trees encountered product", "3-1", day_3::two);
+    check!("4-1", day_4::parse_passports);
     show!("4-1", "Valid passports", "4-1", day_4::one);
     show!("4-2", "Valid passports", "4-1", day_4::two);
     show!("5-1", "Highest seat ID", "5-1", day_5::one);
@@ -59,6 +76,7 @@ fn main() {
     show!("14-2", "Sum of mem values", "14-1", day_14::two);
     show!("15-1", "2020th number spoken", "15-1", day_15::one);
     show!("15-2", "30_000_000th number spoken", "15-1", day_15::two);
+    check!("16-1", day_16::parse_notes);
     show!("16-1", "Ticket scanning error rate", "16-1", day_16::one);
     show!("16-2", "Product of departure fields", "16-1", day_16::two);
     show!("17-1", "Active cubes", "17-1", day_17::one);
@@ -0,0 +1,397 @@
+//! Number theory helpers
+//!
+//! Day 13 is the Chinese Remainder Theorem in disguise and day 25 is a discrete logarithm. Values
+//! are `u128` throughout, and products are reduced without overflowing, so inputs anywhere in the
+//! `u64` range are safe.
+use std::collections::HashMap;
+
+/// Return the greatest common divisor of `a` and `b`
+pub fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+/// Return `(g, x, y)` such that `a * x + b * y = g`, where `g` is the greatest common divisor of
+/// `a` and `b`
+pub fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, old_x - quotient * x);
+        (old_y, y) = (y, old_y - quotient * y);
+    }
+
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Return the least common multiple of `a` and `b`, or None if it overflows
+pub fn lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Return `a * b % modulus` without overflowing
+///
+/// # Panics
+/// if `modulus` is zero
+pub fn mulmod(a: u128, b: u128, modulus: u128) -> u128 {
+    let (mut a, mut b) = (a % modulus, b % modulus);
+    if let Some(product) = a.checked_mul(b) {
+        return product % modulus;
+    }
+
+    // double and add, keeping every intermediate below the modulus
+    let add = |x: u128, y: u128| {
+        if x >= modulus - y {
+            x - (modulus - y)
+        } else {
+            x + y
+        }
+    };
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add(result, a);
+        }
+        a = add(a, a);
+        b >>= 1;
+    }
+
+    result
+}
+
+/// return `(a - b) % modulus` for `a` and `b` already below the modulus, without going negative
+fn submod(a: u128, b: u128, modulus: u128) -> u128 {
+    if a >= b {
+        a - b
+    } else {
+        modulus - (b - a)
+    }
+}
+
+/// Return `base ^ exponent % modulus`
+///
+/// # Panics
+/// if `modulus` is zero
+pub fn modpow(base: u128, mut exponent: u128, modulus: u128) -> u128 {
+    if modulus == 1 {
+        return 0;
+    }
+
+    let mut base = base % modulus;
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mulmod(result, base, modulus);
+        }
+        base = mulmod(base, base, modulus);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+/// Return `x` such that `a * x % modulus == 1`, or None if `a` and `modulus` share a factor
+pub fn modinv(a: u128, modulus: u128) -> Option<u128> {
+    if modulus == 0 {
+        return None;
+    }
+
+    // extended euclid, tracking only the coefficient of `a`, kept in the range 0..modulus
+    let (mut old_r, mut r) = (a % modulus, modulus);
+    let (mut old_s, mut s) = (1 % modulus, 0);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        let next_s = submod(old_s, mulmod(quotient, s, modulus), modulus);
+        (old_s, s) = (s, next_s);
+    }
+
+    match old_r {
+        1 => Some(old_s),
+        _ => None,
+    }
+}
+
+/// Reason [`crt`] can't return a solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtError {
+    /// a congruence has a modulus of zero
+    ZeroModulus,
+    /// the congruences contradict each other, so there is no solution
+    Contradictory,
+    /// the least common multiple of the moduli doesn't fit in a `u128`
+    Overflow,
+}
+
+/// Solve a system of congruences `x % modulus == residue`, given as `(residue, modulus)` pairs
+///
+/// The moduli don't need to be coprime. Returns `(x, m)` where `x` is the smallest solution and
+/// every solution is `x` plus a multiple of `m`, the least common multiple of the moduli.
+pub fn crt(congruences: &[(u128, u128)]) -> Result<(u128, u128), CrtError> {
+    congruences
+        .iter()
+        .try_fold((0, 1), |(x, m), &(residue, modulus)| {
+            if modulus == 0 {
+                return Err(CrtError::ZeroModulus);
+            }
+            let residue = residue % modulus;
+
+            // x + m * t == residue (mod modulus) has a solution only if gcd(m, modulus) divides
+            // the difference
+            let g = gcd(m, modulus);
+            let difference = submod(residue, x % modulus, modulus);
+            if !difference.is_multiple_of(g) {
+                return Err(CrtError::Contradictory);
+            }
+
+            let reduced = modulus / g;
+            let inverse = modinv(m / g, reduced).ok_or(CrtError::Contradictory)?;
+            let t = mulmod(difference / g, inverse, reduced);
+            let combined = lcm(m, modulus).ok_or(CrtError::Overflow)?;
+
+            // x < m and t < reduced, so this stays below the combined modulus
+            Ok((x + m * t, combined))
+        })
+}
+
+/// Return the smallest `x` such that `base ^ x % modulus == target`, or None if there isn't one
+///
+/// Uses baby-step giant-step, so takes around `sqrt(modulus)` time and memory. A modulus of zero
+/// has no solutions
+pub fn discrete_log(base: u128, target: u128, modulus: u128) -> Option<u128> {
+    if modulus == 0 {
+        return None;
+    }
+    if modulus == 1 {
+        return Some(0);
+    }
+    let (mut base, mut target, mut modulus) = (base % modulus, target % modulus, modulus);
+
+    // divide out factors shared by the base and modulus, until they're coprime
+    let mut offset = 0;
+    let mut factor = 1;
+    loop {
+        if target == factor {
+            return Some(offset);
+        }
+        let g = gcd(base, modulus);
+        if g == 1 {
+            break;
+        }
+        if !target.is_multiple_of(g) {
+            return None;
+        }
+        target /= g;
+        modulus /= g;
+        offset += 1;
+        factor = mulmod(factor, base / g, modulus);
+        base %= modulus;
+    }
+
+    // x = giant * steps - baby, so factor * base ^ (giant * steps) == target * base ^ baby
+    let steps = (modulus as f64).sqrt().ceil() as u128 + 1;
+    let mut babies = HashMap::new();
+    let mut value = target;
+    for baby in 0..=steps {
+        // later babies give smaller solutions
+        babies.insert(value, baby);
+        value = mulmod(value, base, modulus);
+    }
+
+    let giant_step = modpow(base, steps, modulus);
+    let mut value = factor;
+    for giant in 1..=steps {
+        value = mulmod(value, giant_step, modulus);
+        if let Some(baby) = babies.get(&value) {
+            return Some(giant * steps - baby + offset);
+        }
+    }
+
+    None
+}
+
+/// A precomputed baby-step giant-step table, for taking many discrete logarithms with the same
+/// base and modulus
+#[derive(Debug, Clone)]
+pub struct DiscreteLog {
+    modulus: u128,
+    steps: u128,
+    /// base ^ baby for each baby step, mapped to the smallest exponent
+    babies: HashMap<u128, u128>,
+    /// base ^ -steps
+    giant_step: u128,
+}
+
+impl DiscreteLog {
+    /// Build the table for `base` and `modulus`, or return None if they share a factor
+    pub fn new(base: u128, modulus: u128) -> Option<Self> {
+        if modulus < 2 || gcd(base, modulus) != 1 {
+            return None;
+        }
+
+        let steps = (modulus as f64).sqrt().ceil() as u128;
+        let mut babies = HashMap::new();
+        let mut value = 1;
+        for baby in 0..steps {
+            babies.entry(value).or_insert(baby);
+            value = mulmod(value, base, modulus);
+        }
+        let giant_step = modpow(modinv(base, modulus)?, steps, modulus);
+
+        Some(Self {
+            modulus,
+            steps,
+            babies,
+            giant_step,
+        })
+    }
+
+    /// Return the smallest `x` such that `base ^ x % modulus == target`, or None if there isn't one
+    pub fn solve(&self, target: u128) -> Option<u128> {
+        // x = giant * steps + baby, so target * base ^ (-giant * steps) == base ^ baby
+        let mut value = target % self.modulus;
+        for giant in 0..self.steps {
+            if let Some(baby) = self.babies.get(&value) {
+                return Some(giant * self.steps + baby);
+            }
+            value = mulmod(value, self.giant_step, self.modulus);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn greatest_common_divisor() {
+        let msg = "should return the greatest common divisor";
+        assert_eq!(gcd(18, 30), 6, "{}", msg);
+        assert_eq!(gcd(17, 5), 1, "{}", msg);
+        assert_eq!(gcd(0, 5), 5, "{}", msg);
+
+        let msg = "should return the bezout coefficients";
+        let (g, x, y) = egcd(240, 46);
+        assert_eq!(g, 2, "{}", msg);
+        assert_eq!(240 * x + 46 * y, g, "{}", msg);
+    }
+
+    #[test]
+    fn least_common_multiple() {
+        let msg = "should return the least common multiple";
+        let expected = Some(90);
+        let actual = lcm(18, 30);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Some(84);
+        let actual = lcm(21, 28);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = Some(140);
+        let actual = lcm(14, 20);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = None;
+        let actual = lcm(u128::MAX, u128::MAX - 1);
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        let msg = "should multiply and exponentiate without overflowing";
+        assert_eq!(modpow(7, 8, 20201227), 5764801, "{}", msg);
+        assert_eq!(modpow(2, 127, u128::MAX), 1 << 127, "{}", msg);
+        assert_eq!(
+            mulmod(u128::MAX - 1, 2, u128::MAX),
+            u128::MAX - 2,
+            "{}",
+            msg
+        );
+
+        let msg = "should return the modular inverse when there is one";
+        assert_eq!(modinv(3, 11), Some(4), "{}", msg);
+        assert_eq!(modinv(6, 9), None, "{}", msg);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        let msg = "should solve a system of congruences";
+        let expected = Ok((23, 105));
+        let actual = crt(&[(2, 3), (3, 5), (2, 7)]);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should solve congruences with moduli that share factors";
+        let expected = Ok((10, 12));
+        let actual = crt(&[(4, 6), (2, 4)]);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should report contradicting congruences";
+        let expected = Err(CrtError::Contradictory);
+        let actual = crt(&[(1, 6), (2, 4)]);
+        assert_eq!(actual, expected, "{}", msg);
+
+        let msg = "should report a zero modulus";
+        assert_eq!(crt(&[(1, 0)]), Err(CrtError::ZeroModulus), "{}", msg);
+
+        let msg = "should report a combined modulus too large for a u128";
+        let primes = [
+            (0, 18_446_744_073_709_551_557),
+            (1, 18_446_744_073_709_551_533),
+            (2, 18_446_744_073_709_551_521),
+        ];
+        assert_eq!(crt(&primes), Err(CrtError::Overflow), "{}", msg);
+    }
+
+    #[test]
+    fn discrete_logarithm() {
+        let msg = "should return the smallest exponent";
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8), "{}", msg);
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11), "{}", msg);
+        assert_eq!(discrete_log(7, 1, 20201227), Some(0), "{}", msg);
+        assert_eq!(discrete_log(2, 1, 7), Some(0), "{}", msg);
+        assert_eq!(discrete_log(2, 4, 7), Some(2), "{}", msg);
+
+        let msg = "should handle a base that shares factors with the modulus";
+        assert_eq!(discrete_log(2, 8, 24), Some(3), "{}", msg);
+        assert_eq!(discrete_log(2, 16, 24), Some(4), "{}", msg);
+
+        let msg = "should return None when there is no solution";
+        assert_eq!(discrete_log(2, 3, 7), None, "{}", msg);
+        assert_eq!(discrete_log(2, 3, 24), None, "{}", msg);
+        assert_eq!(discrete_log(2, 3, 0), None, "{}", msg);
+    }
+
+    #[test]
+    fn discrete_logarithm_table() {
+        let msg = "should reuse a table for many logarithms";
+        let table = DiscreteLog::new(7, 20201227).unwrap();
+        assert_eq!(table.solve(5764801), Some(8), "{}", msg);
+        assert_eq!(table.solve(17807724), Some(11), "{}", msg);
+        assert_eq!(table.solve(1), Some(0), "{}", msg);
+
+        let table = DiscreteLog::new(2, 7).unwrap();
+        assert_eq!(table.solve(4), Some(2), "{}", msg);
+        assert_eq!(table.solve(3), None, "{}", msg);
+
+        let msg = "should only build a table for a base coprime to the modulus";
+        assert!(DiscreteLog::new(2, 24).is_none(), "{}", msg);
+    }
+}
@@ -0,0 +1,536 @@
+//! A small virtual machine
+//!
+//! Day 8's handheld console runs a program of instructions that update an accumulator and jump
+//! around relative to the program counter. A [`Machine`] generalizes it:
+//!
+//! - an [`Instruction`] set is pluggable, and each instruction works on any number of registers
+//! - jumps are checked, so leaving the program is an error instead of wrapping around
+//! - an [`Observer`] watches execution and can interrupt it. [`LoopDetector`], [`Tracer`] and
+//!   [`Breakpoint`] are provided, and tuples of observers combine them
+//!
+//! A program completes by trying to execute the instruction immediately after its last one.
+//!
+//! ```
+//! use advent_2020::vm::{Control, Instruction, Machine, Status};
+//!
+//! // decrement a register and jump back while it is positive
+//! #[derive(Debug, Clone, Copy)]
+//! struct Countdown;
+//!
+//! impl Instruction for Countdown {
+//!     fn execute(&self, registers: &mut [isize]) -> Result<Control, String> {
+//!         registers[0] -= 1;
+//!         registers[1] += 2;
+//!         Ok(if registers[0] > 0 { Control::Jump(0) } else { Control::Next })
+//!     }
+//! }
+//!
+//! let mut machine = Machine::new(vec![Countdown], 2);
+//! machine.state_mut().registers[0] = 5;
+//! assert_eq!(machine.run(&mut ()), Ok(Status::Complete));
+//! assert_eq!(machine.state().registers, vec![0, 10]);
+//! ```
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+/// What the machine does after executing an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// continue with the next instruction
+    Next,
+    /// jump relative to the current instruction
+    Jump(isize),
+    /// stop execution
+    Halt,
+}
+
+/// A single machine instruction
+pub trait Instruction: Copy + Debug {
+    /// Apply the instruction to the registers, and return where execution continues
+    fn execute(&self, registers: &mut [isize]) -> Result<Control, String>;
+}
+
+/// The registers of a machine
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct State {
+    /// program counter
+    pub pc: usize,
+    /// general purpose registers
+    pub registers: Vec<isize>,
+}
+
+/// The reason an [`Observer`] interrupted execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// the instruction at `pc` was about to run a second time
+    Loop(usize),
+    /// execution reached a breakpoint at `pc`
+    Breakpoint(usize),
+}
+
+/// The execution status of a machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// ready to execute the next instruction
+    Ready,
+    /// the program ran past its last instruction
+    Complete,
+    /// an instruction halted the machine
+    Halted,
+    /// an observer stopped execution before an instruction. Stepping again resumes
+    Interrupted(Interrupt),
+    /// an instruction failed or jumped outside of the program
+    Faulted(String),
+}
+
+/// Watches a machine's execution
+pub trait Observer<I> {
+    /// Called before executing `instruction` at `pc`. Return an [`Interrupt`] to stop execution
+    /// without running the instruction. In a tuple, observers after the one that interrupts aren't
+    /// called
+    fn before(&mut self, _pc: usize, _instruction: &I, _state: &State) -> Option<Interrupt> {
+        None
+    }
+
+    /// Called after executing `instruction` at `pc`, with the resulting state
+    fn after(&mut self, _pc: usize, _instruction: &I, _state: &State) {}
+}
+
+/// Observes nothing
+impl<I> Observer<I> for () {}
+
+impl<I, A, B> Observer<I> for (A, B)
+where
+    A: Observer<I>,
+    B: Observer<I>,
+{
+    fn before(&mut self, pc: usize, instruction: &I, state: &State) -> Option<Interrupt> {
+        // an interrupted instruction isn't shown to the remaining observers, as it hasn't run
+        self.0
+            .before(pc, instruction, state)
+            .or_else(|| self.1.before(pc, instruction, state))
+    }
+
+    fn after(&mut self, pc: usize, instruction: &I, state: &State) {
+        self.0.after(pc, instruction, state);
+        self.1.after(pc, instruction, state);
+    }
+}
+
+impl<I, A, B, C> Observer<I> for (A, B, C)
+where
+    A: Observer<I>,
+    B: Observer<I>,
+    C: Observer<I>,
+{
+    fn before(&mut self, pc: usize, instruction: &I, state: &State) -> Option<Interrupt> {
+        self.0
+            .before(pc, instruction, state)
+            .or_else(|| (&mut self.1, &mut self.2).before(pc, instruction, state))
+    }
+
+    fn after(&mut self, pc: usize, instruction: &I, state: &State) {
+        self.0.after(pc, instruction, state);
+        self.1.after(pc, instruction, state);
+        self.2.after(pc, instruction, state);
+    }
+}
+
+impl<I, O: Observer<I>> Observer<I> for &mut O {
+    fn before(&mut self, pc: usize, instruction: &I, state: &State) -> Option<Interrupt> {
+        (**self).before(pc, instruction, state)
+    }
+
+    fn after(&mut self, pc: usize, instruction: &I, state: &State) {
+        (**self).after(pc, instruction, state)
+    }
+}
+
+/// Interrupts execution before any instruction runs a second time
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoopDetector {
+    visited: HashSet<usize>,
+}
+
+impl LoopDetector {
+    /// Return true if the instruction at `pc` has run
+    pub fn visited(&self, pc: usize) -> bool {
+        self.visited.contains(&pc)
+    }
+}
+
+impl<I> Observer<I> for LoopDetector {
+    fn before(&mut self, pc: usize, _instruction: &I, _state: &State) -> Option<Interrupt> {
+        if self.visited(pc) {
+            Some(Interrupt::Loop(pc))
+        } else {
+            None
+        }
+    }
+
+    // only count instructions that ran, so an interrupt by another observer isn't a visit
+    fn after(&mut self, pc: usize, _instruction: &I, _state: &State) {
+        self.visited.insert(pc);
+    }
+}
+
+/// Records each instruction executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tracer<I> {
+    executed: Vec<(usize, I)>,
+}
+
+impl<I> Default for Tracer<I> {
+    fn default() -> Self {
+        Self { executed: vec![] }
+    }
+}
+
+impl<I> Tracer<I> {
+    /// Return the program counter and instruction of each instruction executed, in order
+    pub fn executed(&self) -> &[(usize, I)] {
+        &self.executed
+    }
+}
+
+impl<I: Copy> Observer<I> for Tracer<I> {
+    fn after(&mut self, pc: usize, instruction: &I, _state: &State) {
+        self.executed.push((pc, *instruction));
+    }
+}
+
+/// Interrupts execution on reaching any of a set of instructions
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pcs: HashSet<usize>,
+    /// breakpoint execution stopped at, so resuming doesn't stop at it again
+    hit: Option<usize>,
+}
+
+impl Breakpoint {
+    /// Create breakpoints at each of the provided program counters
+    pub fn new(pcs: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            pcs: pcs.into_iter().collect(),
+            hit: None,
+        }
+    }
+}
+
+impl<I> Observer<I> for Breakpoint {
+    fn before(&mut self, pc: usize, _instruction: &I, _state: &State) -> Option<Interrupt> {
+        if self.hit.take() == Some(pc) || !self.pcs.contains(&pc) {
+            return None;
+        }
+
+        self.hit = Some(pc);
+        Some(Interrupt::Breakpoint(pc))
+    }
+}
+
+/// A machine with a program in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Machine<I> {
+    program: Vec<I>,
+    state: State,
+    status: Status,
+    /// number of instructions executed
+    steps: usize,
+}
+
+impl<I: Instruction> Machine<I> {
+    /// Create a machine with `program` in memory and the provided number of registers, all zero
+    pub fn new(program: Vec<I>, registers: usize) -> Self {
+        Self {
+            program,
+            state: State {
+                pc: 0,
+                registers: vec![0; registers],
+            },
+            status: Status::Ready,
+            steps: 0,
+        }
+    }
+
+    /// Return the program in memory
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    /// Return the current state
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Return the current state for modification
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    /// Return the execution status
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Return the number of instructions executed
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Execute the next instruction, returning the resulting status. A machine that has completed,
+    /// halted or faulted does nothing
+    pub fn step(&mut self, observer: &mut impl Observer<I>) -> Result<Status, String> {
+        match &self.status {
+            Status::Ready | Status::Interrupted(_) => {}
+            Status::Faulted(err) => return Err(err.clone()),
+            status => return Ok(status.clone()),
+        }
+
+        let pc = self.state.pc;
+        if pc == self.program.len() {
+            self.status = Status::Complete;
+            return Ok(Status::Complete);
+        }
+        let instruction = match self.program.get(pc) {
+            Some(instruction) => *instruction,
+            None => return self.fault(format!("Program counter {} is outside the program", pc)),
+        };
+
+        if let Some(interrupt) = observer.before(pc, &instruction, &self.state) {
+            self.status = Status::Interrupted(interrupt);
+            return Ok(self.status.clone());
+        }
+
+        let control = match instruction.execute(&mut self.state.registers) {
+            Ok(control) => control,
+            Err(err) => return self.fault(format!("Instruction {} failed: {}", pc, err)),
+        };
+        self.steps += 1;
+        self.status = match control {
+            Control::Next => {
+                self.state.pc = pc + 1;
+                Status::Ready
+            }
+            Control::Jump(offset) => match pc
+                .checked_add_signed(offset)
+                .filter(|target| *target <= self.program.len())
+            {
+                Some(target) => {
+                    self.state.pc = target;
+                    Status::Ready
+                }
+                None => {
+                    return self.fault(format!("Jump from {} by {} leaves the program", pc, offset))
+                }
+            },
+            Control::Halt => Status::Halted,
+        };
+        observer.after(pc, &instruction, &self.state);
+
+        Ok(self.status.clone())
+    }
+
+    /// Execute up to `count` instructions, stopping early if the machine stops being ready
+    pub fn step_n(
+        &mut self,
+        count: usize,
+        observer: &mut impl Observer<I>,
+    ) -> Result<Status, String> {
+        for _ in 0..count {
+            let status = self.step(observer)?;
+            if status != Status::Ready {
+                return Ok(status);
+            }
+        }
+
+        Ok(self.status.clone())
+    }
+
+    /// Execute instructions until `predicate` holds for the state, or the machine stops being
+    /// ready
+    pub fn run_until(
+        &mut self,
+        mut predicate: impl FnMut(&State) -> bool,
+        observer: &mut impl Observer<I>,
+    ) -> Result<Status, String> {
+        while !predicate(&self.state) {
+            let status = self.step(observer)?;
+            if status != Status::Ready {
+                return Ok(status);
+            }
+        }
+
+        Ok(self.status.clone())
+    }
+
+    /// Execute instructions until the machine stops being ready
+    pub fn run(&mut self, observer: &mut impl Observer<I>) -> Result<Status, String> {
+        self.run_until(|_| false, observer)
+    }
+
+    // stop execution with an error
+    fn fault(&mut self, err: String) -> Result<Status, String> {
+        self.status = Status::Faulted(err.clone());
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// a two register instruction set
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Op {
+        Add(usize, isize),
+        Swap,
+        Jnz(usize, isize),
+        Halt,
+    }
+
+    impl Instruction for Op {
+        fn execute(&self, registers: &mut [isize]) -> Result<Control, String> {
+            match *self {
+                Op::Add(register, value) => {
+                    let register = registers
+                        .get_mut(register)
+                        .ok_or(format!("No register {}", register))?;
+                    *register = register.checked_add(value).ok_or("Overflow")?;
+                    Ok(Control::Next)
+                }
+                Op::Swap => {
+                    registers.swap(0, 1);
+                    Ok(Control::Next)
+                }
+                Op::Jnz(register, offset) => Ok(if registers[register] != 0 {
+                    Control::Jump(offset)
+                } else {
+                    Control::Next
+                }),
+                Op::Halt => Ok(Control::Halt),
+            }
+        }
+    }
+
+    /// count register 0 down from 3, adding 10 to register 1 each time
+    fn countdown() -> Vec<Op> {
+        vec![
+            Op::Add(0, 3),
+            Op::Add(1, 10),
+            Op::Add(0, -1),
+            Op::Jnz(0, -2),
+            Op::Swap,
+        ]
+    }
+
+    #[test]
+    fn runs_program() {
+        let msg = "should run a program to completion";
+        let mut machine = Machine::new(countdown(), 2);
+        assert_eq!(machine.run(&mut ()), Ok(Status::Complete), "{}", msg);
+        assert_eq!(machine.state().registers, vec![30, 0], "{}", msg);
+        assert_eq!(machine.steps(), 11, "{}", msg);
+
+        let msg = "should stop at a halt instruction";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Halt, Op::Add(0, 1)], 1);
+        assert_eq!(machine.run(&mut ()), Ok(Status::Halted), "{}", msg);
+        assert_eq!(machine.step(&mut ()), Ok(Status::Halted), "{}", msg);
+        assert_eq!(machine.state().registers, vec![1], "{}", msg);
+    }
+
+    #[test]
+    fn checks_jumps() {
+        let msg = "should fault on jumps outside the program";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Jnz(0, -2)], 1);
+        let expected = Err("Jump from 1 by -2 leaves the program".to_string());
+        assert_eq!(machine.run(&mut ()), expected, "{}", msg);
+        assert_eq!(machine.step(&mut ()), expected, "{}", msg);
+
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Jnz(0, 2)], 1);
+        let expected = Err("Jump from 1 by 2 leaves the program".to_string());
+        assert_eq!(machine.run(&mut ()), expected, "{}", msg);
+
+        let msg = "should complete on jumps just past the end of the program";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Jnz(0, 1)], 1);
+        assert_eq!(machine.run(&mut ()), Ok(Status::Complete), "{}", msg);
+
+        let msg = "should fault on failed instructions";
+        let mut machine = Machine::new(vec![Op::Add(2, 1)], 2);
+        let expected = Err("Instruction 0 failed: No register 2".to_string());
+        assert_eq!(machine.run(&mut ()), expected, "{}", msg);
+    }
+
+    #[test]
+    fn steps_and_runs_until() {
+        let msg = "should execute a number of instructions";
+        let mut machine = Machine::new(countdown(), 2);
+        assert_eq!(machine.step_n(3, &mut ()), Ok(Status::Ready), "{}", msg);
+        assert_eq!(machine.state().pc, 3, "{}", msg);
+        assert_eq!(machine.state().registers, vec![2, 10], "{}", msg);
+        assert_eq!(
+            machine.step_n(100, &mut ()),
+            Ok(Status::Complete),
+            "{}",
+            msg
+        );
+
+        let msg = "should execute until the state matches";
+        let mut machine = Machine::new(countdown(), 2);
+        let status = machine.run_until(|state| state.registers[1] == 20, &mut ());
+        assert_eq!(status, Ok(Status::Ready), "{}", msg);
+        assert_eq!(machine.state().registers, vec![2, 20], "{}", msg);
+    }
+
+    #[test]
+    fn observes() {
+        let msg = "should interrupt before repeating an instruction";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Jnz(0, -1)], 1);
+        let mut detector = LoopDetector::default();
+        let status = machine.run(&mut detector);
+        assert_eq!(
+            status,
+            Ok(Status::Interrupted(Interrupt::Loop(0))),
+            "{}",
+            msg
+        );
+        assert_eq!(machine.state().registers, vec![1], "{}", msg);
+        assert!(detector.visited(1), "{}", msg);
+
+        let msg = "should interrupt at breakpoints and resume after them";
+        let mut machine = Machine::new(countdown(), 2);
+        let mut observers = (Breakpoint::new(vec![3]), Tracer::default());
+        let expected = Ok(Status::Interrupted(Interrupt::Breakpoint(3)));
+        assert_eq!(machine.run(&mut observers), expected, "{}", msg);
+        assert_eq!(machine.state().registers, vec![2, 10], "{}", msg);
+        assert_eq!(machine.run(&mut observers), expected, "{}", msg);
+        assert_eq!(machine.state().registers, vec![1, 20], "{}", msg);
+
+        let msg = "should trace executed instructions";
+        let expected = [
+            (0, Op::Add(0, 3)),
+            (1, Op::Add(1, 10)),
+            (2, Op::Add(0, -1)),
+            (3, Op::Jnz(0, -2)),
+            (1, Op::Add(1, 10)),
+            (2, Op::Add(0, -1)),
+        ];
+        assert_eq!(observers.1.executed(), &expected[..], "{}", msg);
+
+        let msg = "should not report a loop when resuming from a breakpoint";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Add(0, 1), Op::Jnz(0, -2)], 1);
+        let mut observers = (Breakpoint::new([1]), LoopDetector::default());
+        let expected = Ok(Status::Interrupted(Interrupt::Breakpoint(1)));
+        assert_eq!(machine.run(&mut observers), expected, "{}", msg);
+        assert!(!observers.1.visited(1), "{}", msg);
+        let expected = Ok(Status::Interrupted(Interrupt::Loop(0)));
+        assert_eq!(machine.run(&mut observers), expected, "{}", msg);
+        assert_eq!(machine.state().registers, vec![2], "{}", msg);
+
+        let msg = "should not report a loop when the breakpoint comes second";
+        let mut machine = Machine::new(vec![Op::Add(0, 1), Op::Add(0, 1), Op::Halt], 1);
+        let mut observers = (LoopDetector::default(), Breakpoint::new([1]));
+        let expected = Ok(Status::Interrupted(Interrupt::Breakpoint(1)));
+        assert_eq!(machine.run(&mut observers), expected, "{}", msg);
+        assert_eq!(machine.run(&mut observers), Ok(Status::Halted), "{}", msg);
+    }
+}
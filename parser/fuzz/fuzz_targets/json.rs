@@ -0,0 +1,10 @@
+//! Feed arbitrary input to the JSON parser, asserting it never panics
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parser::json::parse_json;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = parse_json(input);
+    }
+});
@@ -0,0 +1,49 @@
+//! Feed arbitrary input to a small recursive JSON-like grammar, asserting it never panics
+//!
+//! value = int | float | "[" value ("," value)* "]"
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parser::three::lib::{between, choice, p_char, p_float, p_int, sep_by, spaces, Parser};
+
+// values are only built to exercise the parsers, never inspected
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+enum Value {
+    Int(isize),
+    Float(f64),
+    List(Vec<Value>),
+}
+
+/// deepest nesting of lists accepted, so deep input fails rather than overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+/// defer construction of the recursive value parser, nested `depth` lists deep, until it is run
+fn value<'a>(depth: usize) -> Parser<'a, Value> {
+    Parser::of(()).bind(move |_| {
+        let open = p_char('[').try_map(move |ch| {
+            if depth < MAX_DEPTH {
+                Ok(ch)
+            } else {
+                Err("Nested too deeply".to_string())
+            }
+        });
+        let list = between(
+            open,
+            sep_by(between(spaces(), value(depth + 1), spaces()), p_char(',')),
+            p_char(']'),
+        )
+        .map(Value::List);
+
+        choice(vec![
+            p_float(10).map(Value::Float),
+            p_int(10).map(Value::Int),
+            list,
+        ])
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = value(0).parse(input);
+    }
+});
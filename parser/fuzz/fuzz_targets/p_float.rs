@@ -0,0 +1,12 @@
+//! Feed arbitrary input to `p_float`, asserting it never panics
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parser::three::lib::p_float;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        for base in [2, 10, 16, 36] {
+            let _ = p_float(base).parse(input);
+        }
+    }
+});
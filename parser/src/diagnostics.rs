@@ -0,0 +1,416 @@
+//! Source span diagnostics
+//!
+//! Render one or more labelled spans over multi-line input, with surrounding context lines, in
+//! roughly the style rustc uses for its errors
+//!
+//! ```text
+//! error: Error parsing integer
+//!  --> input.txt:1:2
+//!   |
+//! 1 | -Z123
+//!   |  ^ Unexpected 'Z'
+//! ```
+use std::fmt::Write;
+
+// ANSI escape sequences used when color output is enabled
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// A location within some source text. Line and column are both zero based, column counts chars
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    /// Line number
+    pub line: usize,
+    /// Column within the line
+    pub column: usize,
+}
+
+impl Location {
+    /// Create a new Location
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// A region of source text, from `start` (inclusive) to `end` (exclusive)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// First location in the span
+    pub start: Location,
+    /// Location just past the end of the span
+    pub end: Location,
+}
+
+impl Span {
+    /// Create a new Span. The bounds are swapped if `end` precedes `start`
+    pub fn new(start: Location, end: Location) -> Self {
+        if end < start {
+            return Self {
+                start: end,
+                end: start,
+            };
+        }
+        Self { start, end }
+    }
+
+    /// Create a zero width Span at a single location, rendered as a single marker
+    pub fn point(location: Location) -> Self {
+        Self::new(location, location)
+    }
+}
+
+/// How prominently a [`Label`] is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// The cause of the diagnostic, underlined with `^`
+    Primary,
+    /// Additional context, underlined with `-`
+    Secondary,
+}
+
+impl Style {
+    fn marker(self) -> char {
+        match self {
+            Style::Primary => '^',
+            Style::Secondary => '-',
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Style::Primary => RED,
+            Style::Secondary => BLUE,
+        }
+    }
+}
+
+/// A message attached to a span of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// Region of the source the label applies to
+    pub span: Span,
+    /// Text displayed after the underline
+    pub message: String,
+    /// Display style
+    pub style: Style,
+}
+
+/// A diagnostic message with any number of labelled spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    message: String,
+    file_name: Option<String>,
+    labels: Vec<Label>,
+    context: usize,
+    color: bool,
+}
+
+impl Diagnostic {
+    /// Create a new Diagnostic with a headline message and no labels
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            file_name: None,
+            labels: vec![],
+            context: 0,
+            color: false,
+        }
+    }
+
+    /// Set the file name shown in the location header
+    pub fn with_file_name(self, file_name: impl Into<String>) -> Self {
+        Self {
+            file_name: Some(file_name.into()),
+            ..self
+        }
+    }
+
+    /// Add a primary label
+    pub fn with_label(self, span: Span, message: impl Into<String>) -> Self {
+        self.with_styled_label(span, message, Style::Primary)
+    }
+
+    /// Add a secondary label
+    pub fn with_secondary_label(self, span: Span, message: impl Into<String>) -> Self {
+        self.with_styled_label(span, message, Style::Secondary)
+    }
+
+    fn with_styled_label(mut self, span: Span, message: impl Into<String>, style: Style) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            style,
+        });
+        self
+    }
+
+    /// Set the number of context lines shown before and after each labelled line
+    pub fn with_context(self, context: usize) -> Self {
+        Self { context, ..self }
+    }
+
+    /// Enable or disable ANSI color output
+    pub fn with_color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    /// The headline message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The attached labels
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// wrap `text` in an escape sequence if color is enabled
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// return the sorted, deduplicated list of line numbers to display
+    fn visible_lines(&self, line_count: usize) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .labels
+            .iter()
+            .flat_map(|label| {
+                let first = label.span.start.line.saturating_sub(self.context);
+                let last = label.span.end.line + self.context;
+                // context lines never extend past the end of the input, labelled lines may
+                let last = last.min(line_count.saturating_sub(1).max(label.span.end.line));
+                first..=last
+            })
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+
+        lines
+    }
+
+    /// Render the diagnostic against the source text it refers to
+    pub fn render(&self, source: &str) -> String {
+        let source_lines: Vec<&str> = source.lines().collect();
+        let lines = self.visible_lines(source_lines.len());
+        let gutter_width = lines
+            .last()
+            .map(|line| (line + 1).to_string().len())
+            .unwrap_or(1);
+        let gutter = self.paint(BLUE, &format!("{:width$} |", "", width = gutter_width));
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{}{}",
+            self.paint(RED, "error"),
+            self.paint(BOLD, &format!(": {}", self.message))
+        )
+        .unwrap();
+
+        // location header points at the first primary label, or failing that any label
+        let origin = self
+            .labels
+            .iter()
+            .find(|label| label.style == Style::Primary)
+            .or_else(|| self.labels.first());
+        if let Some(label) = origin {
+            let Location { line, column } = label.span.start;
+            writeln!(
+                out,
+                "{:width$}{} {}:{}:{}",
+                "",
+                self.paint(BLUE, "-->"),
+                self.file_name.as_deref().unwrap_or("<input>"),
+                line + 1,
+                column + 1,
+                width = gutter_width
+            )
+            .unwrap();
+        }
+        if lines.is_empty() {
+            return out;
+        }
+        writeln!(out, "{}", gutter).unwrap();
+
+        let mut previous: Option<usize> = None;
+        for &line_idx in &lines {
+            if matches!(previous, Some(prev) if line_idx > prev + 1) {
+                writeln!(out, "{}", self.paint(BLUE, "...")).unwrap();
+            }
+            previous = Some(line_idx);
+
+            let text = source_lines.get(line_idx).copied().unwrap_or("");
+            let line_num = format!("{:>width$} |", line_idx + 1, width = gutter_width);
+            if text.is_empty() {
+                writeln!(out, "{}", self.paint(BLUE, &line_num)).unwrap();
+            } else {
+                writeln!(out, "{} {}", self.paint(BLUE, &line_num), text).unwrap();
+            }
+
+            // labels touching this line, ordered by their starting column
+            let mut labels: Vec<&Label> = self
+                .labels
+                .iter()
+                .filter(|label| (label.span.start.line..=label.span.end.line).contains(&line_idx))
+                .collect();
+            labels.sort_by_key(|label| {
+                if label.span.start.line == line_idx {
+                    label.span.start.column
+                } else {
+                    0
+                }
+            });
+
+            let line_len = text.chars().count();
+            for label in labels {
+                let Span { start, end } = label.span;
+                let from = if start.line == line_idx {
+                    start.column
+                } else {
+                    0
+                };
+                let to = if end.line == line_idx {
+                    end.column
+                } else {
+                    line_len
+                };
+                let width = to.saturating_sub(from).max(1);
+                let underline = label.style.marker().to_string().repeat(width);
+                // the message is only shown beneath the last line of the span
+                let message = if end.line == line_idx {
+                    format!(" {}", label.message)
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    out,
+                    "{} {:pad$}{}",
+                    gutter,
+                    "",
+                    self.paint(label.style.color(), &format!("{}{}", underline, message)),
+                    pad = from
+                )
+                .unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_label() {
+        let msg = "should underline a single location";
+
+        let diagnostic = Diagnostic::new("Error parsing integer")
+            .with_file_name("input.txt")
+            .with_label(Span::point(Location::new(0, 1)), "Unexpected 'Z'");
+
+        let expected = r#"error: Error parsing integer
+ --> input.txt:1:2
+  |
+1 | -Z123
+  |  ^ Unexpected 'Z'
+"#;
+        let actual = diagnostic.render("-Z123");
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn multiple_labels() {
+        let msg = "should show several labelled spans with context lines";
+
+        let source = "byr:1937\niyr:2017\neyr:2020\nhgt:183\nhcl:#fffffd";
+        let diagnostic = Diagnostic::new("Invalid passport")
+            .with_file_name("4-1.txt")
+            .with_context(1)
+            .with_secondary_label(Span::new(Location::new(0, 4), Location::new(0, 8)), "valid")
+            .with_label(
+                Span::new(Location::new(3, 4), Location::new(3, 7)),
+                "missing unit",
+            );
+
+        let expected = r#"error: Invalid passport
+ --> 4-1.txt:4:5
+  |
+1 | byr:1937
+  |     ---- valid
+2 | iyr:2017
+3 | eyr:2020
+4 | hgt:183
+  |     ^^^ missing unit
+5 | hcl:#fffffd
+"#;
+        let actual = diagnostic.render(source);
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn elides_gaps() {
+        let msg = "should elide lines between distant labels";
+
+        let source = "a\nb\nc\nd\ne";
+        let diagnostic = Diagnostic::new("oops")
+            .with_label(Span::point(Location::new(0, 0)), "here")
+            .with_label(Span::point(Location::new(4, 0)), "and here");
+
+        let expected = r#"error: oops
+ --> <input>:1:1
+  |
+1 | a
+  | ^ here
+...
+5 | e
+  | ^ and here
+"#;
+        let actual = diagnostic.render(source);
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn multi_line_span() {
+        let msg = "should underline every line of a span that crosses lines";
+
+        let source = "ab\ncd\nef";
+        let diagnostic = Diagnostic::new("oops").with_label(
+            Span::new(Location::new(0, 1), Location::new(2, 1)),
+            "spans three lines",
+        );
+
+        let expected = r#"error: oops
+ --> <input>:1:2
+  |
+1 | ab
+  |  ^
+2 | cd
+  | ^^
+3 | ef
+  | ^ spans three lines
+"#;
+        let actual = diagnostic.render(source);
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn colors() {
+        let msg = "should wrap output in ANSI escape sequences";
+
+        let diagnostic = Diagnostic::new("oops")
+            .with_color(true)
+            .with_label(Span::point(Location::new(0, 0)), "here");
+
+        let actual = diagnostic.render("a");
+        assert!(actual.starts_with("\x1b[1;31merror\x1b[0m"), "{}", msg);
+        assert!(actual.contains("\x1b[1;31m^ here\x1b[0m"), "{}", msg);
+    }
+}
@@ -0,0 +1,502 @@
+//! Understanding Parser Combinators - pt 4
+//!
+//! [Writing a JSON parser from scratch](https://fsharpforfunandprofit.com/posts/understanding-parser-combinators-4/)
+//!
+//! Built using only the public combinators from [`three::lib`](crate::three::lib), as a check
+//! that the library can handle a real grammar
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+use crate::three::lib::{
+    any_of, between, choice, digit_char, eof, keep_first, keep_second, many, many_chars,
+    one_or_more_chars, optional, p_char, p_string, satisfy, sep_by, sequence, ParseErr, Parser,
+};
+
+// 4-1. Building a model to represent the JSON spec
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// any number, including those with fractions and exponents
+    Number(f64),
+    /// a quoted string, with escapes resolved
+    String(String),
+    /// an ordered list of values
+    Array(Vec<JsonValue>),
+    /// a set of named values. Later duplicate keys replace earlier ones
+    Object(HashMap<String, JsonValue>),
+}
+
+/// parse zero or more JSON whitespace chars
+fn ws<'a>() -> Parser<'a, ()> {
+    many(any_of([' ', '\t', '\n', '\r'])).map(|_| ())
+}
+
+/// match a str and return a fixed value
+fn keyword<'a>(word: &str, value: JsonValue) -> Parser<'a, JsonValue> {
+    p_string(word).map(move |_| value.clone())
+}
+
+// 4-2. Parsing Null
+
+/// parse `null`
+fn j_null<'a>() -> Parser<'a, JsonValue> {
+    keyword("null", JsonValue::Null).with_label("null".to_string())
+}
+
+// 4-3. Parsing Bool
+
+/// parse `true` or `false`
+fn j_bool<'a>() -> Parser<'a, JsonValue> {
+    let j_true = keyword("true", JsonValue::Bool(true));
+    let j_false = keyword("false", JsonValue::Bool(false));
+    j_true.or_else(j_false).with_label("bool".to_string())
+}
+
+// 4-4. Parsing String
+
+/// A piece of string content. Unicode escapes are kept as UTF-16 code units until the whole string
+/// is read, so surrogate pairs can be combined
+#[derive(Debug, Clone, Copy)]
+enum Fragment {
+    Char(char),
+    Utf16(u16),
+}
+
+/// parse any char other than a quote, backslash or control char
+fn j_unescaped_char<'a>() -> Parser<'a, Fragment> {
+    let label = "char".to_string();
+    satisfy(|ch| ch != '"' && ch != '\\' && ch >= ' ', label).map(Fragment::Char)
+}
+
+/// parse a backslash escaped char, eg `\n`
+fn j_escaped_char<'a>() -> Parser<'a, Fragment> {
+    let escapes = [
+        ('"', '"'),
+        ('\\', '\\'),
+        ('/', '/'),
+        ('b', '\u{8}'),
+        ('f', '\u{c}'),
+        ('n', '\n'),
+        ('r', '\r'),
+        ('t', '\t'),
+    ];
+    let parsers = escapes
+        .iter()
+        .map(|&(escape, result)| p_char(escape).map(move |_| Fragment::Char(result)))
+        .collect::<Vec<_>>();
+
+    keep_second(p_char('\\'), choice(parsers)).with_label("escaped char".to_string())
+}
+
+/// parse a unicode escape, eg `\u00e9`
+fn j_unicode_char<'a>() -> Parser<'a, Fragment> {
+    let hex_digit = digit_char(16);
+    let four_hex_digits = sequence(&[
+        hex_digit.clone(),
+        hex_digit.clone(),
+        hex_digit.clone(),
+        hex_digit,
+    ]);
+
+    keep_second(p_string("\\u"), four_hex_digits)
+        .try_map(|digits| {
+            u16::from_str_radix(&String::from_iter(digits), 16)
+                .map(Fragment::Utf16)
+                .map_err(|err| err.to_string())
+        })
+        .with_label("unicode char".to_string())
+}
+
+/// combine string fragments, pairing up surrogates
+fn fragments_to_string(fragments: Vec<Fragment>) -> Result<String, String> {
+    let units = fragments.into_iter().flat_map(|fragment| match fragment {
+        Fragment::Char(ch) => {
+            let mut buf = [0; 2];
+            ch.encode_utf16(&mut buf).to_vec()
+        }
+        Fragment::Utf16(unit) => vec![unit],
+    });
+
+    std::char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|err| err.to_string())
+}
+
+/// parse a quoted string
+fn quoted_string<'a>() -> Parser<'a, String> {
+    let quote = p_char('"').with_label("quote".to_string());
+    let j_char = choice(vec![j_unescaped_char(), j_escaped_char(), j_unicode_char()]);
+
+    between(quote.clone(), many(j_char), quote)
+        .try_map(fragments_to_string)
+        .with_label("quoted string".to_string())
+}
+
+/// parse a JSON string
+fn j_string<'a>() -> Parser<'a, JsonValue> {
+    quoted_string().map(JsonValue::String)
+}
+
+// 4-5. Parsing Number
+
+/// parse a JSON number
+fn j_number<'a>() -> Parser<'a, JsonValue> {
+    let digit = digit_char(10);
+    let sign = optional(p_char('-')).map(|sign| sign.map(String::from).unwrap_or_default());
+    // no leading zeros
+    let non_zero_int = satisfy(|ch| ('1'..='9').contains(&ch), "digit".to_string())
+        .and_then(many_chars(digit.clone()))
+        .map(|(first, rest)| format!("{}{}", first, rest));
+    let int_part = p_string("0").or_else(non_zero_int);
+    let fraction_part = optional(
+        keep_second(p_char('.'), one_or_more_chars(digit.clone()))
+            .map(|digits| format!(".{}", digits)),
+    )
+    .map(Option::unwrap_or_default);
+    let exponent_part = optional(
+        keep_second(
+            any_of(['e', 'E']),
+            optional(any_of(['+', '-'])).and_then(one_or_more_chars(digit)),
+        )
+        .map(|(sign, digits)| format!("e{}{}", sign.map(String::from).unwrap_or_default(), digits)),
+    )
+    .map(Option::unwrap_or_default);
+
+    sign.and_then(int_part)
+        .and_then(fraction_part)
+        .and_then(exponent_part)
+        .try_map(|(((sign, int), fraction), exponent)| {
+            format!("{}{}{}{}", sign, int, fraction, exponent)
+                .parse::<f64>()
+                .map(JsonValue::Number)
+                .map_err(|err| err.to_string())
+        })
+        .with_label("number".to_string())
+}
+
+// 4-6. Parsing Array
+
+/// deepest nesting of arrays and objects accepted. Each level recurses through the parsers, so
+/// unbounded nesting could overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// parse the opening bracket of an array or object nested `depth` levels deep, failing if it
+/// would nest too deeply
+fn open<'a>(bracket: char, depth: usize) -> Parser<'a, char> {
+    p_char(bracket).try_map(move |ch| {
+        if depth < MAX_DEPTH {
+            Ok(ch)
+        } else {
+            Err(format!("Nesting deeper than {} levels", MAX_DEPTH))
+        }
+    })
+}
+
+/// parse a list of values between brackets, nested `depth` levels deep
+fn j_array<'a>(depth: usize) -> Parser<'a, JsonValue> {
+    let left = keep_first(open('[', depth), ws());
+    let right = keep_first(p_char(']'), ws());
+    let comma = keep_first(p_char(','), ws());
+    let value = keep_first(j_value(depth + 1), ws());
+
+    between(left, sep_by(value, comma), right)
+        .map(JsonValue::Array)
+        .with_label("array".to_string())
+}
+
+// 4-7. Parsing Object
+
+/// parse a set of key value pairs between braces, nested `depth` levels deep
+fn j_object<'a>(depth: usize) -> Parser<'a, JsonValue> {
+    let left = keep_first(open('{', depth), ws());
+    let right = keep_first(p_char('}'), ws());
+    let colon = keep_first(p_char(':'), ws());
+    let comma = keep_first(p_char(','), ws());
+    let key = keep_first(quoted_string(), ws());
+    let value = keep_first(j_value(depth + 1), ws());
+    let key_value = keep_first(key, colon).and_then(value);
+
+    between(left, sep_by(key_value, comma), right)
+        .map(|pairs| JsonValue::Object(pairs.into_iter().collect()))
+        .with_label("object".to_string())
+}
+
+// 4-8. Putting it all together
+
+/// parse any JSON value, nested `depth` levels deep
+///
+/// Arrays and objects contain values, so construction is deferred until the parser runs to avoid
+/// building an infinitely recursive parser
+fn j_value<'a>(depth: usize) -> Parser<'a, JsonValue> {
+    Parser::of(())
+        .bind(move |_| {
+            choice(vec![
+                j_null(),
+                j_bool(),
+                j_number(),
+                j_string(),
+                j_array(depth),
+                j_object(depth),
+            ])
+        })
+        .with_label("value".to_string())
+}
+
+/// Parse a complete JSON document
+///
+/// Arrays and objects may nest at most 64 levels deep
+pub fn parse_json(input: &str) -> Result<JsonValue, ParseErr<'_>> {
+    let document = keep_second(ws(), keep_first(keep_first(j_value(0), ws()), eof()));
+    document.parse(input).map(|(_remaining, value)| value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn object(pairs: &[(&str, JsonValue)]) -> JsonValue {
+        JsonValue::Object(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn scalars() {
+        let msg = "should parse scalar values";
+
+        let cases = [
+            ("null", JsonValue::Null),
+            ("true", JsonValue::Bool(true)),
+            (" false ", JsonValue::Bool(false)),
+            ("0", JsonValue::Number(0.0)),
+            ("-12.5e2", JsonValue::Number(-1250.0)),
+            ("1E-2", JsonValue::Number(0.01)),
+            ("1.5e+3", JsonValue::Number(1500.0)),
+            (
+                r#""a\"b\\c\/d""#,
+                JsonValue::String("a\"b\\c/d".to_string()),
+            ),
+            (
+                r#""\b\f\n\r\t""#,
+                JsonValue::String("\u{8}\u{c}\n\r\t".to_string()),
+            ),
+            (r#""caf\u00e9""#, JsonValue::String("café".to_string())),
+            (r#""\ud83d\ude00""#, JsonValue::String("😀".to_string())),
+            (r#""日本""#, JsonValue::String("日本".to_string())),
+        ];
+        for (input, expected) in cases.iter() {
+            let actual = parse_json(input).unwrap();
+            assert_eq!(&actual, expected, "{}: {}", msg, input);
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let msg = "should parse nested arrays and objects";
+
+        let input = r#"{
+    "name" : "Scott",
+    "isMale" : true,
+    "bday" : {"year":2001, "month":12, "day":25 },
+    "favouriteColors" : ["blue", "green"],
+    "emptyArray" : [],
+    "emptyObject" : {},
+    "nested": [[[1], []], {"a": [null]}]
+}"#;
+        let expected = object(&[
+            ("name", JsonValue::String("Scott".to_string())),
+            ("isMale", JsonValue::Bool(true)),
+            (
+                "bday",
+                object(&[
+                    ("year", JsonValue::Number(2001.0)),
+                    ("month", JsonValue::Number(12.0)),
+                    ("day", JsonValue::Number(25.0)),
+                ]),
+            ),
+            (
+                "favouriteColors",
+                JsonValue::Array(vec![
+                    JsonValue::String("blue".to_string()),
+                    JsonValue::String("green".to_string()),
+                ]),
+            ),
+            ("emptyArray", JsonValue::Array(vec![])),
+            ("emptyObject", object(&[])),
+            (
+                "nested",
+                JsonValue::Array(vec![
+                    JsonValue::Array(vec![
+                        JsonValue::Array(vec![JsonValue::Number(1.0)]),
+                        JsonValue::Array(vec![]),
+                    ]),
+                    object(&[("a", JsonValue::Array(vec![JsonValue::Null]))]),
+                ]),
+            ),
+        ]);
+        let actual = parse_json(input).unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn errors() {
+        let msg = "should report the failure position";
+
+        let expected = r#"Line:1 Col:5 Error parsing end of input
+  2] x
+     ^Unexpected 'x'"#;
+        let actual = parse_json("[1,\n  2] x").unwrap_err();
+        assert_eq!(format!("{}", actual), expected, "{}", msg);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let msg = "should parse values nested up to the limit";
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_json(&nested(MAX_DEPTH)).is_ok(), "{}", msg);
+        let objects = format!("{}{}", "{\"a\":".repeat(MAX_DEPTH), "}".repeat(MAX_DEPTH));
+        let objects = objects.replacen("{\"a\":}", "{}", 1);
+        assert!(parse_json(&objects).is_ok(), "{}", msg);
+
+        let msg = "should fail, rather than overflow the stack, on deeper nesting";
+        assert!(parse_json(&nested(MAX_DEPTH + 1)).is_err(), "{}", msg);
+        let deep = "[".repeat(100_000);
+        assert!(parse_json(&deep).is_err(), "{}", msg);
+    }
+
+    // A selection of cases from the JSONTestSuite (https://github.com/nst/JSONTestSuite).
+    // `y_` cases must be accepted, `n_` cases must be rejected
+    const ACCEPT: &[(&str, &str)] = &[
+        ("y_array_arraysWithSpaces", "[[]   ]"),
+        ("y_array_empty-string", r#"[""]"#),
+        ("y_array_empty", "[]"),
+        ("y_array_ending_with_newline", r#"["a"]"#),
+        ("y_array_false", "[false]"),
+        ("y_array_heterogeneous", r#"[null, 1, "1", {}]"#),
+        ("y_array_null", "[null]"),
+        ("y_array_with_leading_space", " [1]"),
+        ("y_array_with_several_null", "[1,null,null,null,2]"),
+        ("y_array_with_trailing_space", "[2] "),
+        ("y_number", "[123e65]"),
+        ("y_number_0e+1", "[0e+1]"),
+        ("y_number_0e1", "[0e1]"),
+        ("y_number_after_space", "[ 4]"),
+        (
+            "y_number_double_close_to_zero",
+            "[-0.000000000000000000000000000000000000000000000000000000000000000000000000000001]",
+        ),
+        ("y_number_int_with_exp", "[20e1]"),
+        ("y_number_minus_zero", "[-0]"),
+        ("y_number_negative_int", "[-123]"),
+        ("y_number_real_capital_e", "[1E22]"),
+        ("y_number_real_capital_e_neg_exp", "[1E-2]"),
+        ("y_number_real_capital_e_pos_exp", "[1E+2]"),
+        ("y_number_real_fraction_exponent", "[123.456e78]"),
+        ("y_number_simple_real", "[123.456789]"),
+        ("y_object_basic", r#"{"asd":"sdf"}"#),
+        ("y_object_duplicated_key", r#"{"a":"b","a":"c"}"#),
+        ("y_object_empty", "{}"),
+        ("y_object_empty_key", r#"{"":0}"#),
+        (
+            "y_object_extreme_numbers",
+            r#"{ "min": -1.0e+28, "max": 1.0e+28 }"#,
+        ),
+        (
+            "y_object_long_strings",
+            r#"{"x":[{"id": "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx"}], "id": "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx"}"#,
+        ),
+        ("y_object_simple", r#"{"a":[]}"#),
+        ("y_object_with_newlines", "{\n\"a\": \"b\"\n}"),
+        (
+            "y_string_1_2_3_bytes_UTF-8_sequences",
+            r#"["\u0060\u012a\u12AB"]"#,
+        ),
+        ("y_string_accepted_surrogate_pair", r#"["\uD801\udc37"]"#),
+        ("y_string_allowed_escapes", r#"["\"\\\/\b\f\n\r\t"]"#),
+        ("y_string_backslash_doublequotes", r#"["\""]"#),
+        ("y_string_comments", r#"["a/*b*/c/*d//e"]"#),
+        ("y_string_in_array_with_leading_space", r#"[ "asd"]"#),
+        ("y_string_nonCharacterInUTF-8_U+FFFF", "[\"\u{ffff}\"]"),
+        ("y_string_null_escape", r#"["\u0000"]"#),
+        ("y_string_unicode_escaped_double_quote", r#"["\u0022"]"#),
+        ("y_string_utf8", "[\"€𝄞\"]"),
+        ("y_structure_lonely_false", "false"),
+        ("y_structure_lonely_int", "42"),
+        ("y_structure_lonely_negative_real", "-0.1"),
+        ("y_structure_lonely_null", "null"),
+        ("y_structure_lonely_string", r#""asd""#),
+        ("y_structure_lonely_true", "true"),
+        ("y_structure_string_empty", r#""""#),
+        ("y_structure_trailing_newline", "[\"a\"]\n"),
+        ("y_structure_whitespace_array", " [] "),
+    ];
+
+    const REJECT: &[(&str, &str)] = &[
+        ("n_array_1_true_without_comma", "[1 true]"),
+        ("n_array_colon_instead_of_comma", r#"["": 1]"#),
+        ("n_array_comma_after_close", r#"[""],"#),
+        ("n_array_double_comma", "[1,,2]"),
+        ("n_array_extra_close", r#"["x"]]"#),
+        ("n_array_extra_comma", r#"["",]"#),
+        ("n_array_just_comma", "[,]"),
+        ("n_array_missing_value", r#"[   , ""]"#),
+        ("n_array_unclosed", r#"["""#),
+        ("n_incomplete_false", "[fals]"),
+        ("n_incomplete_null", "[nul]"),
+        ("n_incomplete_true", "[tru]"),
+        ("n_number_++", "[++1234]"),
+        ("n_number_-01", "[-01]"),
+        ("n_number_.-1", "[.-1]"),
+        ("n_number_0.e1", "[0.e1]"),
+        ("n_number_1.0e", "[1.0e]"),
+        ("n_number_2.e3", "[2.e3]"),
+        ("n_number_expression", "[1+2]"),
+        ("n_number_hex_1_digit", "[0x1]"),
+        ("n_number_infinity", "[Infinity]"),
+        ("n_number_leading_zero", "[012]"),
+        ("n_number_NaN", "[NaN]"),
+        ("n_number_neg_int_starting_with_zero", "[-012]"),
+        ("n_number_starting_with_dot", "[.123]"),
+        ("n_object_bad_value", r#"["x", truth]"#),
+        ("n_object_missing_colon", r#"{"a" b}"#),
+        ("n_object_missing_key", r#"{:"b"}"#),
+        ("n_object_non_string_key", "{1:1}"),
+        ("n_object_single_quote", "{'a':0}"),
+        ("n_object_trailing_comma", r#"{"id":0,}"#),
+        ("n_object_unquoted_key", r#"{a: "b"}"#),
+        ("n_single_space", " "),
+        ("n_string_1_surrogate_then_escape", r#"["\uD800\"]"#),
+        ("n_string_escape_x", r#"["\x00"]"#),
+        ("n_string_escaped_emoji", "[\"\\🌀\"]"),
+        ("n_string_incomplete_surrogate", r#"["\uD834\uDd"]"#),
+        ("n_string_lone_surrogate", r#"["\uDd1e"]"#),
+        ("n_string_single_quote", "['single quote']"),
+        ("n_string_unescaped_tab", "[\"\t\"]"),
+        ("n_string_unescaped_newline", "[\"new\nline\"]"),
+        ("n_structure_array_with_unclosed_string", r#"["asd]"#),
+        ("n_structure_double_array", "[][]"),
+        ("n_structure_no_data", ""),
+        ("n_structure_object_unclosed_no_value", r#"{"":"#),
+        ("n_structure_open_array_object", r#"[{"#),
+        ("n_structure_unclosed_array", "[1"),
+        ("n_structure_unclosed_object", r#"{"asd":"asd""#),
+    ];
+
+    #[test]
+    fn test_suite() {
+        for (name, input) in ACCEPT {
+            assert!(parse_json(input).is_ok(), "should accept {}", name);
+        }
+        for (name, input) in REJECT {
+            assert!(parse_json(input).is_err(), "should reject {}", name);
+        }
+    }
+}
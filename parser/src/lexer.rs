@@ -0,0 +1,434 @@
+//! Lexer
+//!
+//! Turn a str into a stream of [`Token`]s with [`Span`]s, so grammars can match whole identifiers,
+//! numbers and punctuation instead of single chars, and skip whitespace without sprinkling
+//! `spaces()` everywhere. The token stream is consumed with the slice based
+//! [`Parser`](crate::three::Parser) from part 3, via the token parsers in this module.
+//!
+//! ```
+//! use parser::lexer::{p_ident, p_number, Lexer};
+//!
+//! let tokens = Lexer::new().with_signed_numbers(true).tokenize("jmp -3");
+//! let instruction = p_ident().and_then(p_number::<isize>());
+//! let (_remaining, (op, arg)) = instruction.parse(&tokens).unwrap();
+//! assert_eq!((op, arg), ("jmp", -3));
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+use crate::diagnostics::{Location, Span};
+use crate::three::{satisfy, Parser};
+
+/// The category of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// a letter or underscore followed by any number of letters, digits or underscores. With
+    /// alphanumeric words enabled, any such run that isn't all digits
+    Ident,
+    /// one or more digits, optionally with a leading sign when signed numbers are enabled
+    Number,
+    /// any other single non-whitespace char
+    Punct,
+    /// a run of whitespace other than line breaks
+    Whitespace,
+    /// a single line break
+    Newline,
+}
+
+/// A lexed slice of the input
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    /// Token category
+    pub kind: TokenKind,
+    /// Input text of the token
+    pub text: &'a str,
+    /// Position of the token within the input
+    pub span: Span,
+}
+
+// kept short, since it is what appears in parse errors
+impl fmt::Debug for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:?} at {}:{}",
+            self.kind, self.text, self.span.start.line, self.span.start.column
+        )
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Which whitespace tokens are emitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitespacePolicy {
+    /// Drop all whitespace
+    Skip,
+    /// Drop whitespace but emit a [`TokenKind::Newline`] for each line break
+    KeepNewlines,
+    /// Emit every whitespace token
+    Keep,
+}
+
+/// Splits input into [`Token`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lexer {
+    whitespace: WhitespacePolicy,
+    signed_numbers: bool,
+    alphanumeric_words: bool,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self {
+            whitespace: WhitespacePolicy::Skip,
+            signed_numbers: false,
+            alphanumeric_words: false,
+        }
+    }
+}
+
+impl Lexer {
+    /// Create a Lexer that skips whitespace and lexes signs as punctuation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set which whitespace tokens are emitted
+    pub fn with_whitespace(self, whitespace: WhitespacePolicy) -> Self {
+        Self { whitespace, ..self }
+    }
+
+    /// When enabled, a `-` or `+` immediately followed by a digit is lexed as part of the number
+    pub fn with_signed_numbers(self, signed_numbers: bool) -> Self {
+        Self {
+            signed_numbers,
+            ..self
+        }
+    }
+
+    /// When enabled, a run of letters, digits and underscores is lexed as a single token, even when
+    /// it starts with a digit. It is a [`TokenKind::Number`] if it is all digits, otherwise a
+    /// [`TokenKind::Ident`]
+    pub fn with_alphanumeric_words(self, alphanumeric_words: bool) -> Self {
+        Self {
+            alphanumeric_words,
+            ..self
+        }
+    }
+
+    /// return true if a token of this kind should be emitted
+    fn keep(&self, kind: TokenKind) -> bool {
+        match (kind, self.whitespace) {
+            (TokenKind::Whitespace, WhitespacePolicy::Keep) => true,
+            (TokenKind::Whitespace, _) => false,
+            (TokenKind::Newline, WhitespacePolicy::Skip) => false,
+            _ => true,
+        }
+    }
+
+    /// Split the input into a list of tokens
+    pub fn tokenize<'a>(&self, input: &'a str) -> Vec<Token<'a>> {
+        let chars: Vec<(usize, char)> = input.char_indices().collect();
+        let mut tokens = vec![];
+        let mut location = Location::default();
+        let mut idx = 0;
+
+        while idx < chars.len() {
+            let (start_byte, ch) = chars[idx];
+            // number of chars in the token
+            let take_while = |from: usize, predicate: &dyn Fn(char) -> bool| {
+                chars[from..]
+                    .iter()
+                    .take_while(|(_, ch)| predicate(*ch))
+                    .count()
+            };
+            let is_signed_number = self.signed_numbers
+                && (ch == '-' || ch == '+')
+                && matches!(chars.get(idx + 1), Some((_, next)) if next.is_ascii_digit());
+
+            let (kind, len) = match ch {
+                '\n' => (TokenKind::Newline, 1),
+                // treat a windows line ending as a single line break, and a lone carriage return
+                // as a line break of its own
+                '\r' if matches!(chars.get(idx + 1), Some((_, '\n'))) => (TokenKind::Newline, 2),
+                '\r' => (TokenKind::Newline, 1),
+                ch if ch.is_whitespace() => (
+                    TokenKind::Whitespace,
+                    take_while(idx, &|ch| ch.is_whitespace() && ch != '\n' && ch != '\r'),
+                ),
+                ch if ch.is_alphabetic() || ch == '_' => (
+                    TokenKind::Ident,
+                    take_while(idx, &|ch| ch.is_alphanumeric() || ch == '_'),
+                ),
+                ch if ch.is_ascii_digit() && self.alphanumeric_words => {
+                    let len = take_while(idx, &|ch| ch.is_alphanumeric() || ch == '_');
+                    match take_while(idx, &|ch| ch.is_ascii_digit()) {
+                        digits if digits == len => (TokenKind::Number, len),
+                        _ => (TokenKind::Ident, len),
+                    }
+                }
+                ch if ch.is_ascii_digit() => (
+                    TokenKind::Number,
+                    take_while(idx, &|ch| ch.is_ascii_digit()),
+                ),
+                _ if is_signed_number => (
+                    TokenKind::Number,
+                    1 + take_while(idx + 1, &|ch| ch.is_ascii_digit()),
+                ),
+                _ => (TokenKind::Punct, 1),
+            };
+
+            let end_byte = chars
+                .get(idx + len)
+                .map(|(byte, _)| *byte)
+                .unwrap_or_else(|| input.len());
+            let start = location;
+            let end = Location::new(location.line, location.column + len);
+            location = match kind {
+                TokenKind::Newline => Location::new(location.line + 1, 0),
+                _ => end,
+            };
+
+            if self.keep(kind) {
+                tokens.push(Token {
+                    kind,
+                    text: &input[start_byte..end_byte],
+                    span: Span::new(start, end),
+                });
+            }
+            idx += len;
+        }
+
+        tokens
+    }
+}
+
+/// Match a token of the specified kind
+pub fn p_kind<'a>(kind: TokenKind) -> Parser<'a, Token<'a>, Token<'a>> {
+    let label = format!("{:?}", kind);
+    satisfy(move |token: &Token| token.kind == kind, label)
+}
+
+/// Match any identifier and return its text
+pub fn p_ident<'a>() -> Parser<'a, Token<'a>, &'a str> {
+    p_kind(TokenKind::Ident)
+        .map(|token| token.text)
+        .with_label("identifier".to_string())
+}
+
+/// Match a specific identifier
+pub fn p_keyword<'a>(keyword: &str) -> Parser<'a, Token<'a>, &'a str> {
+    let label = keyword.to_string();
+    let keyword = keyword.to_string();
+    satisfy(
+        move |token: &Token| token.kind == TokenKind::Ident && token.text == keyword,
+        label,
+    )
+    .map(|token| token.text)
+}
+
+/// Match a single punctuation char
+pub fn p_punct<'a>(punct: char) -> Parser<'a, Token<'a>, char> {
+    let label = punct.to_string();
+    let punct_str = punct.to_string();
+    satisfy(
+        move |token: &Token| token.kind == TokenKind::Punct && token.text == punct_str,
+        label,
+    )
+    .map(move |_| punct)
+}
+
+/// Match a number token that can be converted to a `T`
+///
+/// A number that doesn't fit in `T` fails to match rather than overflowing
+pub fn p_number<'a, T>() -> Parser<'a, Token<'a>, T>
+where
+    T: FromStr + 'a,
+{
+    let label = "number".to_string();
+    satisfy(
+        |token: &Token| token.kind == TokenKind::Number && token.text.parse::<T>().is_ok(),
+        label,
+    )
+    .map(|token| match token.text.parse::<T>() {
+        Ok(value) => value,
+        // the predicate already checked the conversion
+        Err(_) => unreachable!(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds_and_text<'a>(tokens: &[Token<'a>]) -> Vec<(TokenKind, &'a str)> {
+        tokens
+            .iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokenize() {
+        let msg = "should split input into tokens, skipping whitespace";
+
+        let expected = vec![
+            (TokenKind::Ident, "mem"),
+            (TokenKind::Punct, "["),
+            (TokenKind::Number, "8"),
+            (TokenKind::Punct, "]"),
+            (TokenKind::Punct, "="),
+            (TokenKind::Number, "11"),
+            (TokenKind::Ident, "jmp"),
+            (TokenKind::Punct, "-"),
+            (TokenKind::Number, "3"),
+        ];
+        let tokens = Lexer::new().tokenize("mem[8] = 11\njmp -3");
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+    }
+
+    #[test]
+    fn spans() {
+        let msg = "should record the location of each token";
+
+        let tokens = Lexer::new().tokenize("a = 1\n  bc");
+        let expected = vec![
+            Span::new(Location::new(0, 0), Location::new(0, 1)),
+            Span::new(Location::new(0, 2), Location::new(0, 3)),
+            Span::new(Location::new(0, 4), Location::new(0, 5)),
+            Span::new(Location::new(1, 2), Location::new(1, 4)),
+        ];
+        let actual: Vec<_> = tokens.iter().map(|token| token.span).collect();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn whitespace_policy() {
+        let msg = "should emit whitespace tokens according to the policy";
+
+        let input = "a b\r\nc";
+        let expected = vec![
+            (TokenKind::Ident, "a"),
+            (TokenKind::Ident, "b"),
+            (TokenKind::Newline, "\r\n"),
+            (TokenKind::Ident, "c"),
+        ];
+        let tokens = Lexer::new()
+            .with_whitespace(WhitespacePolicy::KeepNewlines)
+            .tokenize(input);
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+
+        let expected = vec![
+            (TokenKind::Ident, "a"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Ident, "b"),
+            (TokenKind::Newline, "\r\n"),
+            (TokenKind::Ident, "c"),
+        ];
+        let tokens = Lexer::new()
+            .with_whitespace(WhitespacePolicy::Keep)
+            .tokenize(input);
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+
+        let msg = "should treat a lone carriage return as a line break";
+        let expected = vec![
+            (TokenKind::Ident, "a"),
+            (TokenKind::Newline, "\r"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Ident, "b"),
+        ];
+        let tokens = Lexer::new()
+            .with_whitespace(WhitespacePolicy::Keep)
+            .tokenize("a\r b");
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+        let spans: Vec<_> = tokens.iter().map(|token| token.span.start).collect();
+        let expected = vec![
+            Location::new(0, 0),
+            Location::new(0, 1),
+            Location::new(1, 0),
+            Location::new(1, 1),
+        ];
+        assert_eq!(spans, expected, "{}", msg);
+    }
+
+    #[test]
+    fn signed_numbers() {
+        let msg = "should attach signs to numbers when enabled";
+
+        let expected = vec![
+            (TokenKind::Ident, "acc"),
+            (TokenKind::Number, "+7"),
+            (TokenKind::Number, "-20"),
+            (TokenKind::Punct, "-"),
+            (TokenKind::Ident, "x"),
+        ];
+        let tokens = Lexer::new()
+            .with_signed_numbers(true)
+            .tokenize("acc +7 -20 -x");
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+    }
+
+    #[test]
+    fn alphanumeric_words() {
+        let msg = "should lex runs of letters and digits as one token when enabled";
+
+        let expected = vec![
+            (TokenKind::Ident, "10XX1"),
+            (TokenKind::Number, "0101"),
+            (TokenKind::Ident, "x2"),
+            (TokenKind::Punct, "["),
+            (TokenKind::Number, "8"),
+            (TokenKind::Punct, "]"),
+        ];
+        let tokens = Lexer::new()
+            .with_alphanumeric_words(true)
+            .tokenize("10XX1 0101 x2[8]");
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+
+        let msg = "should split numbers from letters by default";
+        let expected = vec![(TokenKind::Number, "10"), (TokenKind::Ident, "XX1")];
+        let tokens = Lexer::new().tokenize("10XX1");
+        assert_eq!(kinds_and_text(&tokens), expected, "{}", msg);
+    }
+
+    #[test]
+    fn token_parsers() {
+        let msg = "should parse a token stream";
+
+        let tokens = Lexer::new().tokenize("mem[8] = 11");
+        let bad_tokens = Lexer::new().tokenize("mask = 1");
+        let assignment = p_keyword("mem")
+            .and_then(p_punct('['))
+            .and_then(p_number::<usize>())
+            .and_then(p_punct(']'))
+            .and_then(p_punct('='))
+            .and_then(p_number::<usize>())
+            .map(|(((((_mem, _), address), _), _), value)| (address, value));
+
+        let expected = (8, 11);
+        let (remaining, actual) = assignment.parse(&tokens).unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+        assert!(remaining.is_empty(), "{}", msg);
+
+        let expected = (
+            "mem".to_string(),
+            "Unexpected Ident \"mask\" at 0:0".to_string(),
+        );
+        let actual = assignment.parse(&bad_tokens).unwrap_err();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn number_overflow() {
+        let msg = "should not match numbers that don't fit the target type";
+
+        let tokens = Lexer::new().tokenize("300");
+        assert!(p_number::<u8>().parse(&tokens).is_err(), "{}", msg);
+        assert!(p_number::<u16>().parse(&tokens).is_ok(), "{}", msg);
+    }
+}
@@ -3,6 +3,9 @@
 //! [Parser combinator article](https://fsharpforfunandprofit.com/posts/understanding-parser-combinators/) implemented in
 //! Rust for...understanding
 //! (un)Organized in the same sequence presented in the articles
+pub mod diagnostics;
+pub mod json;
+pub mod lexer;
 pub mod one;
 pub mod three;
 mod two;
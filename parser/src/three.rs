@@ -5,6 +5,7 @@ use std::rc::Rc;
 
 // section 3-3, see separate file
 pub mod lib;
+pub mod trace;
 
 // 3-1. Labelling a Parser
 type ParserLabel = String;
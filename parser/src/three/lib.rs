@@ -3,14 +3,21 @@ use std::fmt;
 use std::iter::FromIterator;
 use std::rc::Rc;
 
-use super::{ParserError, ParserLabel};
+use super::{trace, ParserError, ParserLabel};
+use crate::diagnostics::{Diagnostic, Location, Span};
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 struct Position {
     line: usize,
     column: usize,
 }
 
+impl From<Position> for Location {
+    fn from(pos: Position) -> Self {
+        Location::new(pos.line, pos.column)
+    }
+}
+
 /// increment the column number
 fn incr_col(pos: Position) -> Position {
     Position {
@@ -51,6 +58,13 @@ impl<'a> From<&'a str> for InputState<'a> {
     }
 }
 
+impl InputState<'_> {
+    /// Return the current position within the input
+    pub fn position(&self) -> Location {
+        self.position.into()
+    }
+}
+
 /// return the current line
 fn current_line<'a>(input: &InputState<'a>) -> &'a str {
     let line_pos = input.position.line;
@@ -78,15 +92,15 @@ fn next_char(input: InputState<'_>) -> (InputState<'_>, Option<char>) {
         (input, None)
     } else {
         let current_line = current_line(&input);
-        if col_pos < current_line.len() {
-            let ch = current_line.chars().nth(col_pos);
+        // columns count chars rather than bytes, so multi-byte chars are consumed whole
+        if let Some(ch) = current_line.chars().nth(col_pos) {
             let new_pos = incr_col(input.position);
             let new_state = InputState {
                 position: new_pos,
                 ..input
             };
 
-            (new_state, ch)
+            (new_state, Some(ch))
         } else {
             // end of line, so return LF and move to next line
             let ch = '\n';
@@ -123,10 +137,8 @@ fn read_all_chars(input: InputState<'_>) -> Vec<char> {
 struct ParserPosition<'a> {
     /// Current line as a str
     current_line: &'a str,
-    /// Current line number
-    line: usize,
-    /// Current column within the current line
-    column: usize,
+    /// Current line and column within the current line
+    position: Position,
 }
 
 // We’ll need some way to convert a InputState into a ParserPosition:
@@ -134,8 +146,7 @@ impl<'a> From<InputState<'a>> for ParserPosition<'a> {
     fn from(input: InputState<'a>) -> Self {
         Self {
             current_line: current_line(&input),
-            line: input.position.line,
-            column: input.position.column,
+            position: input.position,
         }
     }
 }
@@ -160,8 +171,8 @@ impl fmt::Display for ParseErr<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let ParseErr(label, error, parser_pos) = self;
         let error_line = parser_pos.current_line;
-        let col_pos = parser_pos.column;
-        let line_pos = parser_pos.line;
+        let col_pos = parser_pos.position.column;
+        let line_pos = parser_pos.position.line;
         // pad and right align caret
         let failure_caret = format!("{:>width$}^{}", "", error, width = col_pos,);
         write!(
@@ -172,6 +183,17 @@ impl fmt::Display for ParseErr<'_> {
     }
 }
 
+impl ParseErr<'_> {
+    /// Convert to a [`Diagnostic`] pointing at the failure position
+    ///
+    /// Render it with the full source text to show the failure in context
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let ParseErr(label, error, parser_pos) = self;
+        let location = parser_pos.position.into();
+        Diagnostic::new(format!("Error parsing {}", label)).with_label(Span::point(location), error)
+    }
+}
+
 /// Return the String representation of a ParseResult
 pub fn print_result<O>(result: &ParseResult<O>) -> String
 where
@@ -210,12 +232,23 @@ impl<O> Clone for Parser<'_, O> {
 impl<'a, O: 'a> Parser<'a, O> {
     /// Run the parser on a InputState
     pub fn parse(&self, input: &'a str) -> ParseResult<'a, O> {
-        (self.parse)(input.into())
+        self.parse_input(input.into())
     }
 
-    /// Run the parser on a string
+    /// Run the parser on a string, recording the run if tracing is enabled
     fn parse_input(&self, input: InputState<'a>) -> ParseResult<'a, O> {
-        (self.parse)(input)
+        if !trace::is_enabled() {
+            return (self.parse)(input);
+        }
+
+        trace::enter(&self.label, input.position.into());
+        let result = (self.parse)(input);
+        trace::exit(match &result {
+            Ok((remaining, _value)) => Ok(remaining.position.into()),
+            Err(ParseErr(_label, err, _pos)) => Err(err.clone()),
+        });
+
+        result
     }
 
     /// Lift a value to a context
@@ -245,6 +278,23 @@ impl<'a, O: 'a> Parser<'a, O> {
         }
     }
 
+    /// apply a fallible function to the value inside a parser
+    ///
+    /// An `Err` from `f` fails the parser at the position it started from
+    pub fn try_map<U>(self, f: impl Fn(O) -> Result<U, String> + 'a) -> Parser<'a, U> {
+        let Self { parse, label } = self;
+        Parser {
+            label: label.clone(),
+            parse: Rc::new(move |input: InputState| {
+                let (remaining, result) = (parse)(input.clone())?;
+                match f(result) {
+                    Ok(mapped_value) => Ok((remaining, mapped_value)),
+                    Err(err) => Err(ParseErr(label.clone(), err, input.into())),
+                }
+            }),
+        }
+    }
+
     /// Update the label in the parser
     pub fn with_label(self, label: String) -> Self {
         let Self { parse, label: _ } = self;
@@ -339,27 +389,27 @@ pub fn choice<'a, O: 'a>(parsers: impl IntoIterator<Item = Parser<'a, O>>) -> Pa
 }
 
 /// (helper) match zero or more occurrences of the specified parser
+///
+/// Stops at the first failure, or at the first success that does not consume any input (which
+/// would otherwise repeat forever)
 fn zero_or_more<'a, O: 'a>(parser: Parser<'a, O>) -> Parser<'a, Vec<O>> {
     let label = format!("zero or more {}", parser.label);
     Parser {
         label,
-        parse: Rc::new(move |input: InputState| {
-            // run parser with the input
-            let first_result = parser.parse_input(input.clone());
-            // test the result for Failure/Success
-            match first_result {
-                // if parse fails, return empty list
-                Err(_err) => Ok((input, vec![])),
-                // if parse succeeds, call recursively to get the subsequent values
-                Ok((input_after_first_parse, first_value)) => {
-                    let (remaining_input, subsequent_values) = zero_or_more(parser.clone())
-                        .parse_input(input_after_first_parse)
-                        .unwrap();
-                    let mut values = vec![first_value];
-                    values.extend(subsequent_values);
-                    Ok((remaining_input, values))
+        parse: Rc::new(move |mut input: InputState| {
+            let mut values = vec![];
+            // run the parser until it fails. Iterate rather than recurse, so long inputs can't
+            // overflow the stack
+            while let Ok((remaining, value)) = parser.parse_input(input.clone()) {
+                let advanced = remaining.position != input.position;
+                values.push(value);
+                input = remaining;
+                if !advanced {
+                    break;
                 }
             }
+
+            Ok((input, values))
         }),
     }
 }
@@ -383,9 +433,8 @@ pub fn one_or_more<'a, T: 'a>(parser: Parser<'a, T>) -> Parser<'a, Vec<T>> {
             let (input_after_first_parse, first_value) = parser.parse_input(input)?;
 
             // if first found, look for zeroOrMore now
-            let (remaining_input, mut subsequent_values) = zero_or_more(parser.clone())
-                .parse_input(input_after_first_parse)
-                .unwrap();
+            let (remaining_input, mut subsequent_values) =
+                zero_or_more(parser.clone()).parse_input(input_after_first_parse)?;
             let mut values = vec![first_value];
             values.append(&mut subsequent_values);
             Ok((remaining_input, values))
@@ -544,6 +593,24 @@ pub fn one_or_more_spaces<'a>() -> Parser<'a, Vec<char>> {
     one_or_more(whitespace_char())
 }
 
+/// match the end of the input
+///
+/// Each line of input ends with a newline char, including the last, so this only succeeds once
+/// that final newline has been consumed
+pub fn eof<'a>() -> Parser<'a, ()> {
+    let label = "end of input".to_string();
+    Parser {
+        label: label.clone(),
+        parse: Rc::new(move |input: InputState| match next_char(input.clone()) {
+            (_, None) => Ok((input, ())),
+            (_, Some(ch)) => {
+                let err = format!("Unexpected {:?}", ch);
+                Err(ParseErr(label.clone(), err, input.into()))
+            }
+        }),
+    }
+}
+
 /// parse a single digit
 pub fn digit_char<'a>(base: u32) -> Parser<'a, char> {
     let predicate = move |ch: char| ch.is_digit(base);
@@ -552,16 +619,15 @@ pub fn digit_char<'a>(base: u32) -> Parser<'a, char> {
 }
 
 /// parse an integer (with sign support)
+///
+/// Fails, rather than overflowing, if the integer does not fit in an `isize`
 pub fn p_int<'a>(base: u32) -> Parser<'a, isize> {
     // helper
-    fn result_to_int((sign, digits): (Option<char>, Vec<char>)) -> isize {
-        let i = String::from_iter(digits)
-            // ignore int overflow for now
-            .parse::<isize>()
-            .unwrap();
-        match sign {
-            Some(_) => -i,
-            None => i,
+    fn result_to_int(base: u32) -> impl Fn((Option<char>, Vec<char>)) -> Result<isize, String> {
+        move |(sign, digits)| {
+            // keep the sign attached so isize::MIN is representable
+            let digits = String::from_iter(sign.into_iter().chain(digits));
+            isize::from_str_radix(&digits, base).map_err(|_| "Integer overflow".to_string())
         }
     }
 
@@ -572,26 +638,31 @@ pub fn p_int<'a>(base: u32) -> Parser<'a, isize> {
     // an "int" is optional sign + one or more digits
     optional(p_char('-'))
         .and_then(digits)
-        .map(result_to_int)
+        .try_map(result_to_int(base))
         .with_label(label)
 }
 
 type ParsedFloat = ((Option<char>, Vec<char>), char);
 
-// parse a float
+/// parse a float
+///
+/// Digits are read in `base`, but only base 10 can be converted to a value. Any other base fails
+/// with an error
 pub fn p_float<'a>(base: u32) -> Parser<'a, f64> {
     // helper
-    fn result_to_float((((sign, digits), _point), digits2): (ParsedFloat, Vec<char>)) -> f64 {
+    fn result_to_float(
+        (((sign, digits), _point), digits2): (ParsedFloat, Vec<char>),
+    ) -> Result<f64, String> {
         let i = format!(
             "{}.{}",
             String::from_iter(digits),
             String::from_iter(digits2)
         )
         .parse::<f64>()
-        .unwrap();
+        .map_err(|err| err.to_string())?;
         match sign {
-            Some(_) => -i,
-            None => i,
+            Some(_) => Ok(-i),
+            None => Ok(i),
         }
     }
 
@@ -604,7 +675,10 @@ pub fn p_float<'a>(base: u32) -> Parser<'a, f64> {
         .and_then(digits.clone())
         .and_then(p_char('.'))
         .and_then(digits)
-        .map(result_to_float)
+        .try_map(move |parsed| match base {
+            10 => result_to_float(parsed),
+            _ => Err(format!("Unsupported float base {}", base)),
+        })
         .with_label(label)
 }
 
@@ -641,8 +715,7 @@ mod test {
             "unexpected |".to_string(),
             ParserPosition {
                 current_line: "123 ab|cd",
-                line: 1,
-                column: 6,
+                position: Position { line: 1, column: 6 },
             },
         ));
 
@@ -653,6 +726,29 @@ mod test {
         assert_eq!(actual, expected, "{}", msg);
     }
 
+    #[test]
+    fn diagnostic() {
+        let msg = "should convert to a diagnostic in the context of the full input";
+
+        let parse_int = p_char('\n').and_then(p_int(10));
+        let err = parse_int.parse("\n-Z123\n456").unwrap_err();
+
+        let expected = r#"error: Error parsing integer
+ --> 1-1.txt:2:2
+  |
+1 |
+2 | -Z123
+  |  ^ Unexpected 'Z'
+3 | 456
+"#;
+        let actual = err
+            .to_diagnostic()
+            .with_file_name("1-1.txt")
+            .with_context(1)
+            .render("\n-Z123\n456");
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
     #[test]
     fn parse_char() {
         let msg = "should parse a single char";
@@ -824,6 +920,24 @@ A
         assert_eq!(print_result(&actual), expected, "{}", msg);
     }
 
+    #[test]
+    fn end_of_input() {
+        let msg = "should match only the end of the input";
+
+        let parse_a = keep_first(p_char('A'), keep_first(spaces(), eof()));
+
+        let expected = 'A';
+        let (_, actual) = parse_a.parse("A").unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = r#"Line:0 Col:1 Error parsing end of input
+AB
+ ^Unexpected 'B'"#
+            .to_string();
+        let actual = parse_a.parse("AB");
+        assert_eq!(print_result(&actual), expected, "{}", msg);
+    }
+
     #[test]
     fn parse_integer() {
         let msg = "should parse an integer";
@@ -846,6 +960,44 @@ A
         assert_eq!(print_result(&actual), expected, "{}", msg);
     }
 
+    #[test]
+    fn integer_overflow() {
+        let msg = "should fail rather than overflow";
+
+        let input = isize::MIN.to_string();
+        let parse_int = p_int(10);
+
+        let expected = isize::MIN;
+        let (_, actual) = parse_int.parse(&input).unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+
+        let expected = r#"Line:0 Col:0 Error parsing integer
+99999999999999999999
+^Integer overflow"#
+            .to_string();
+        let actual = parse_int.parse("99999999999999999999");
+        assert_eq!(print_result(&actual), expected, "{}", msg);
+    }
+
+    #[test]
+    fn multi_byte() {
+        let msg = "should consume multi-byte chars whole";
+
+        let any = satisfy(|_| true, "any".to_string());
+        let expected = vec!['é', '€', 'a', '\n'];
+        let (_, actual) = many(any).parse("é€a").unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
+    #[test]
+    fn empty_repetition() {
+        let msg = "should stop repeating a parser that consumes nothing";
+
+        let expected = vec![vec![]];
+        let (_, actual) = many(spaces()).parse("A").unwrap();
+        assert_eq!(actual, expected, "{}", msg);
+    }
+
     #[test]
     fn parse_float() {
         let msg = "should parse a float";
@@ -866,6 +1018,13 @@ A
             .to_string();
         let actual = parse_float.parse("-123Z45");
         assert_eq!(print_result(&actual), expected, "{}", msg);
+
+        let msg = "should reject bases other than 10";
+        let expected = r#"Line:0 Col:0 Error parsing float
+12.5
+^Unsupported float base 16"#;
+        let actual = p_float(16).parse("12.5");
+        assert_eq!(print_result(&actual), expected, "{}", msg);
     }
 
     #[test]
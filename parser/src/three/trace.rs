@@ -0,0 +1,286 @@
+//! Opt-in parser tracing
+//!
+//! While tracing is enabled on the current thread, every [`Parser`](super::lib::Parser) run
+//! records its label, the position it started at, and whether it succeeded. The result is an
+//! indented tree of parser entries and exits that can be dumped as text or JSON.
+//!
+//! ```
+//! use parser::three::lib::p_char;
+//! use parser::three::trace;
+//!
+//! let parse_ab = p_char('A').and_then(p_char('B'));
+//! let (_result, trace) = trace::traced(|| parse_ab.parse("AC"));
+//! println!("{}", trace);
+//! ```
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Write;
+
+use crate::diagnostics::Location;
+
+/// A single parser invocation and the invocations it made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+    /// Label of the parser that ran
+    pub label: String,
+    /// Input position when the parser was entered
+    pub start: Location,
+    /// Input position when the parser exited. Equal to `start` on failure
+    pub end: Location,
+    /// Error message if the parser failed
+    pub error: Option<String>,
+    /// Parsers run by this parser, in order
+    pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    /// true if the parser succeeded
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A tree of recorded parser invocations
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Top level parser invocations
+    pub roots: Vec<TraceNode>,
+}
+
+/// Tracing state for the current thread
+#[derive(Debug, Default)]
+struct Recorder {
+    /// invocations that have been entered but not exited
+    stack: Vec<TraceNode>,
+    /// completed top level invocations
+    roots: Vec<TraceNode>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Enable tracing on the current thread, discarding anything recorded so far
+pub fn start() {
+    RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Recorder::default()));
+}
+
+/// Disable tracing on the current thread and return everything recorded since [`start`]
+pub fn stop() -> Trace {
+    RECORDER.with(|recorder| {
+        let Recorder { roots, .. } = recorder.borrow_mut().take().unwrap_or_default();
+        Trace { roots }
+    })
+}
+
+/// true if tracing is enabled on the current thread
+pub fn is_enabled() -> bool {
+    RECORDER.with(|recorder| recorder.borrow().is_some())
+}
+
+/// Run `f` with tracing enabled, returning its result along with the recorded trace
+pub fn traced<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    start();
+    let result = f();
+    (result, stop())
+}
+
+/// record entry into a parser
+pub(crate) fn enter(label: &str, start: Location) {
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow_mut().as_mut() {
+            recorder.stack.push(TraceNode {
+                label: label.to_string(),
+                start,
+                end: start,
+                error: None,
+                children: vec![],
+            });
+        }
+    });
+}
+
+/// record exit from the most recently entered parser
+pub(crate) fn exit(result: Result<Location, String>) {
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow_mut().as_mut() {
+            let mut node = match recorder.stack.pop() {
+                Some(node) => node,
+                // tracing was started part way through a parse
+                None => return,
+            };
+            match result {
+                Ok(end) => node.end = end,
+                Err(error) => node.error = Some(error),
+            }
+            match recorder.stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => recorder.roots.push(node),
+            }
+        }
+    });
+}
+
+/// escape a str for inclusion in a JSON string
+fn escape_json(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(result, "\\u{:04x}", ch as u32).unwrap(),
+            ch => result.push(ch),
+        }
+    }
+
+    result
+}
+
+fn location_json(location: &Location) -> String {
+    format!(
+        r#"{{"line":{},"column":{}}}"#,
+        location.line, location.column
+    )
+}
+
+impl TraceNode {
+    fn write_text(&self, out: &mut String, depth: usize) {
+        let status = match &self.error {
+            None => format!("ok -> {}:{}", self.end.line, self.end.column),
+            Some(error) => format!("failed: {}", error),
+        };
+        writeln!(
+            out,
+            "{:indent$}{} @ {}:{} {}",
+            "",
+            self.label,
+            self.start.line,
+            self.start.column,
+            status,
+            indent = depth * 2
+        )
+        .unwrap();
+        for child in &self.children {
+            child.write_text(out, depth + 1);
+        }
+    }
+
+    /// Return the JSON representation of this node and its children
+    pub fn to_json(&self) -> String {
+        let error = match &self.error {
+            Some(error) => format!(r#""{}""#, escape_json(error)),
+            None => "null".to_string(),
+        };
+        let children = self
+            .children
+            .iter()
+            .map(TraceNode::to_json)
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"label":"{}","start":{},"end":{},"success":{},"error":{},"children":[{}]}}"#,
+            escape_json(&self.label),
+            location_json(&self.start),
+            location_json(&self.end),
+            self.is_success(),
+            error,
+            children
+        )
+    }
+}
+
+impl Trace {
+    /// Return the indented text representation of the trace
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for root in &self.roots {
+            root.write_text(&mut out, 0);
+        }
+
+        out
+    }
+
+    /// Return the JSON representation of the trace, an array of the top level nodes
+    pub fn to_json(&self) -> String {
+        let roots = self
+            .roots
+            .iter()
+            .map(TraceNode::to_json)
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("[{}]", roots)
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lib::{many, p_char};
+    use super::*;
+
+    #[test]
+    fn disabled() {
+        let msg = "should record nothing when tracing is disabled";
+
+        let parse_a = p_char('A');
+        parse_a.parse("A").unwrap();
+        assert!(!is_enabled(), "{}", msg);
+        assert_eq!(stop(), Trace::default(), "{}", msg);
+    }
+
+    #[test]
+    fn text() {
+        let msg = "should record an indented tree of parser entries and exits";
+
+        let parse_ab = p_char('A').and_then(p_char('B'));
+        let (result, trace) = traced(|| parse_ab.parse("AC"));
+        assert!(result.is_err());
+
+        let expected = r#"A and then B @ 0:0 failed: Unexpected 'C'
+  A @ 0:0 ok -> 0:1
+  B @ 0:1 failed: Unexpected 'C'
+"#;
+        assert_eq!(trace.to_text(), expected, "{}", msg);
+        assert!(!is_enabled(), "{}", msg);
+    }
+
+    #[test]
+    fn nesting() {
+        let msg = "should nest repeated parsers beneath their parent";
+
+        let many_a = many(p_char('A'));
+        let (_result, trace) = traced(|| many_a.parse("AB"));
+
+        assert_eq!(trace.roots.len(), 1, "{}", msg);
+        let root = &trace.roots[0];
+        assert_eq!(root.label, "many A", "{}", msg);
+        assert!(root.is_success(), "{}", msg);
+        assert_eq!(root.end, Location::new(0, 1), "{}", msg);
+
+        let attempts: Vec<bool> = root.children[0]
+            .children
+            .iter()
+            .map(TraceNode::is_success)
+            .collect();
+        assert_eq!(attempts, vec![true, false], "{}", msg);
+    }
+
+    #[test]
+    fn json() {
+        let msg = "should serialize to JSON";
+
+        let parse_a = p_char('"');
+        let (_result, trace) = traced(|| parse_a.parse("x"));
+
+        let expected = r#"[{"label":"\"","start":{"line":0,"column":0},"end":{"line":0,"column":0},"success":false,"error":"Unexpected 'x'","children":[]}]"#;
+        assert_eq!(trace.to_json(), expected, "{}", msg);
+    }
+}
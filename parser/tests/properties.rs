@@ -0,0 +1,86 @@
+//! Property tests for the combinator laws of `parser::three::lib`
+use parser::three::lib::*;
+use proptest::prelude::*;
+
+/// reduce a ParseResult to something comparable: the value and remaining position on success,
+/// the printed error on failure
+fn outcome<O>(result: ParseResult<'_, O>) -> Result<(O, usize, usize), String> {
+    result
+        .map(|(remaining, value)| {
+            let position = remaining.position();
+            (value, position.line, position.column)
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// a small alphabet so generated inputs have a good chance of matching
+fn small_input() -> impl Strategy<Value = String> {
+    "[ab,\\-0-9 \n]{0,16}"
+}
+
+proptest! {
+    #[test]
+    fn map_identity(input in small_input()) {
+        let parser = one_or_more(any_of(['a', 'b']));
+        let mapped = parser.clone().map(|value| value);
+
+        prop_assert_eq!(outcome(mapped.parse(&input)), outcome(parser.parse(&input)));
+    }
+
+    #[test]
+    fn map_composition(input in small_input()) {
+        let f = |value: isize| value.wrapping_mul(3);
+        let g = |value: isize| value.wrapping_sub(7);
+        let composed = p_int(10).map(move |value| g(f(value)));
+        let chained = p_int(10).map(f).map(g);
+
+        prop_assert_eq!(outcome(composed.parse(&input)), outcome(chained.parse(&input)));
+    }
+
+    #[test]
+    fn and_then_associativity(input in small_input()) {
+        let (a, b, c) = (p_char('a'), p_char('b'), p_char('a'));
+        let left = a
+            .clone()
+            .and_then(b.clone())
+            .and_then(c.clone())
+            .map(|((a, b), c)| (a, b, c));
+        let right = a.and_then(b.and_then(c)).map(|(a, (b, c))| (a, b, c));
+
+        prop_assert_eq!(outcome(left.parse(&input)), outcome(right.parse(&input)));
+    }
+
+    #[test]
+    fn many_never_fails(input in "\\PC{0,32}") {
+        prop_assert!(many(p_char('a')).parse(&input).is_ok());
+        prop_assert!(many(p_int(10)).parse(&input).is_ok());
+        prop_assert!(many(spaces()).parse(&input).is_ok());
+    }
+
+    #[test]
+    fn sep_by_round_trip(values in prop::collection::vec(any::<isize>(), 0..16)) {
+        let input = values
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let parser = sep_by(p_int(10), p_char(','));
+
+        let (_remaining, actual) = parser.parse(&input).unwrap();
+        prop_assert_eq!(actual, values);
+    }
+
+    #[test]
+    fn int_round_trip(value in any::<isize>()) {
+        let input = value.to_string();
+
+        let (_remaining, actual) = p_int(10).parse(&input).unwrap();
+        prop_assert_eq!(actual, value);
+    }
+
+    #[test]
+    fn numbers_never_panic(input in "\\PC{0,64}", base in 2u32..=36) {
+        let _ = p_int(base).parse(&input);
+        let _ = p_float(base).parse(&input);
+    }
+}